@@ -0,0 +1,70 @@
+//! Runs a powers of tau ceremony with a transcript file passed from one participant to the next.
+//!
+//! ```text
+//! ceremony bn254 new 1024 transcript.bin
+//! ceremony bn254 contribute transcript.bin
+//! ceremony bn254 verify transcript.bin
+//! ceremony bn254 export transcript.bin srs.bin
+//! ```
+use ark_ec::PairingEngine;
+use kgz::srs::ceremony::Ceremony;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: ceremony <bls12-381|bn254> <command>
+  new <gates> <transcript>      starts a ceremony for circuits of up to `gates` rows
+  contribute <transcript>       verifies the transcript then multiplies the secret by a random
+                                one, which is then forgotten
+  verify <transcript>           checks the contributions and the powers
+  export <transcript> <srs>     writes the srs of a verified transcript, read with `Srs::load`";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let result = match args.first().map(String::as_str) {
+        Some("bls12-381") => run::<ark_bls12_381::Bls12_381>(&args[1..]),
+        Some("bn254") => run::<ark_bn254::Bn254>(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run<E: PairingEngine>(args: &[String]) -> Result<(), String> {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let load = |path: &str| Ceremony::<E>::load(path).map_err(|error| error.to_string());
+    match args[..] {
+        ["new", gates, transcript] => {
+            let gates = gates.parse::<usize>().map_err(|error| error.to_string())?;
+            let ceremony = Ceremony::<E>::new(gates);
+            ceremony.save(transcript).map_err(|error| error.to_string())
+        }
+        ["contribute", transcript] => {
+            let mut ceremony = load(transcript)?;
+            // building on a broken transcript would waste the contribution
+            ceremony
+                .verify()
+                .map_err(|error| format!("not contributing to an invalid transcript: {}", error))?;
+            ceremony.contribute_random();
+            ceremony
+                .save(transcript)
+                .map_err(|error| error.to_string())?;
+            println!("contribution {} added", ceremony.contributions().len());
+            Ok(())
+        }
+        ["verify", transcript] => {
+            let ceremony = load(transcript)?;
+            ceremony.verify().map_err(|error| error.to_string())?;
+            println!("{} contributions verified", ceremony.contributions().len());
+            Ok(())
+        }
+        ["export", transcript, output] => {
+            let srs = load(transcript)?.srs().map_err(|error| error.to_string())?;
+            srs.save(output).map_err(|error| error.to_string())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
@@ -1,91 +1,258 @@
-use crate::{srs::Srs, Poly as MyPoly};
-use ark_bls12_381::{Bls12_381, Fr};
-use ark_ec::{AffineCurve, PairingEngine};
+use crate::srs::Srs;
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{One, PrimeField, Zero};
 use ark_poly::{univariate::DensePolynomial, Polynomial, UVPolynomial};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
 use std::{
     fmt::{Debug, Display},
     ops::{Add, Mul, Neg, Sub},
 };
 
+/// runs generic tests, taking the curve as their only type parameter, on every supported curve
+#[cfg(test)]
+macro_rules! curve_tests {
+    ($($test:ident),* $(,)?) => {
+        mod bls12_381 {
+            $(#[test]
+            fn $test() {
+                super::$test::<ark_bls12_381::Bls12_381>()
+            })*
+        }
+        mod bn254 {
+            $(#[test]
+            fn $test() {
+                super::$test::<ark_bn254::Bn254>()
+            })*
+        }
+    };
+}
+
+pub mod msm;
 pub mod srs;
 
-pub type G1Point = <ark_bls12_381::Bls12_381 as PairingEngine>::G1Affine;
-pub type G2Point = <ark_bls12_381::Bls12_381 as PairingEngine>::G2Affine;
-pub type Poly = DensePolynomial<Fr>;
-pub struct KzgScheme<'a>(&'a Srs);
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct KzgCommitment(pub G1Point);
+pub type Poly<F> = DensePolynomial<F>;
+/// The scheme over the pairing friendly curve `E`, like BLS12-381 or BN254.
+pub struct KzgScheme<'a, E: PairingEngine>(&'a Srs<E>);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KzgCommitment<E: PairingEngine>(pub E::G1Affine);
 
-impl KzgCommitment {
-    pub fn inner(&self) -> &G1Point {
+impl<E: PairingEngine> KzgCommitment<E> {
+    pub fn inner(&self) -> &E::G1Affine {
         &self.0
     }
 }
-#[derive(Debug, Clone, Copy)]
-pub struct KzgOpening(pub G1Point, pub Fr);
+#[derive(Debug, Clone, Copy, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KzgOpening<E: PairingEngine>(pub E::G1Affine, pub E::Fr);
 
-impl KzgOpening {
-    pub fn eval(self) -> Fr {
+impl<E: PairingEngine> KzgOpening<E> {
+    pub fn eval(self) -> E::Fr {
         self.1
     }
 }
+/// a single witness opening several polynomials at the same point, with their evaluations
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KzgBatchOpening<E: PairingEngine>(pub E::G1Affine, pub Vec<E::Fr>);
+
+impl<E: PairingEngine> KzgBatchOpening<E> {
+    pub fn evals(&self) -> &[E::Fr] {
+        &self.1
+    }
+}
+/// the commitments opened by a batch opening, and the point they are opened at
+pub type BatchQuery<'a, E> = (
+    &'a [KzgCommitment<E>],
+    &'a KzgBatchOpening<E>,
+    <E as PairingEngine>::Fr,
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KzgError {
+    /// committing to `needed` coefficients or evaluations with an srs of `size` points
+    SrsTooSmall { needed: usize, size: usize },
+    /// a batch opening with a number of evaluations different from its commitments
+    EvaluationCount { commitments: usize, evals: usize },
+}
 
-impl<'a> KzgScheme<'a> {
-    pub fn new(srs: &'a Srs) -> Self {
-        Self(&srs)
+impl Display for KzgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SrsTooSmall { needed, size } => {
+                write!(f, "the srs has {} points, {} are needed", size, needed)
+            }
+            Self::EvaluationCount { commitments, evals } => write!(
+                f,
+                "{} evaluations are opened for {} commitments",
+                evals, commitments
+            ),
+        }
+    }
+}
+impl std::error::Error for KzgError {}
+
+/// a pairing equation `e(witnesses, [s]) = e(rhs, [1])` left to check
+#[derive(Debug, Clone, Copy)]
+pub struct PairingCheck<E: PairingEngine> {
+    witnesses: E::G1Projective,
+    rhs: E::G1Projective,
+}
+
+impl<E: PairingEngine> PairingCheck<E> {
+    /// `Σ factor_i·check_i`, which holds when all the checks hold. With random factors unknown to
+    /// the prover it fails with overwhelming probability when one of them does not
+    pub fn combine(checks: impl IntoIterator<Item = (Self, E::Fr)>) -> Self {
+        checks.into_iter().fold(
+            Self {
+                witnesses: E::G1Projective::zero(),
+                rhs: E::G1Projective::zero(),
+            },
+            |acc, (check, factor)| Self {
+                witnesses: acc.witnesses + check.witnesses.mul(factor.into_repr()),
+                rhs: acc.rhs + check.rhs.mul(factor.into_repr()),
+            },
+        )
+    }
+}
+
+impl<'a, E: PairingEngine> KzgScheme<'a, E> {
+    pub fn new(srs: &'a Srs<E>) -> Self {
+        Self(srs)
     }
     /// Commit to a polynomial
-    pub fn commit(&self, polynomial: &MyPoly) -> KzgCommitment {
-        let commitment = self.evaluate_in_s(polynomial);
-        KzgCommitment(commitment)
+    pub fn commit(&self, polynomial: &Poly<E::Fr>) -> Result<KzgCommitment<E>, KzgError> {
+        let commitment = self.evaluate_in_s(polynomial)?;
+        Ok(KzgCommitment(commitment))
     }
-    fn evaluate_in_s(&self, polynomial: &MyPoly) -> G1Point {
+    fn evaluate_in_s(&self, polynomial: &Poly<E::Fr>) -> Result<E::G1Affine, KzgError> {
         let srs = self.0.g1_ref();
-        assert!(srs.len() > polynomial.degree());
-        let poly = polynomial.coeffs.iter();
-        let srs = srs.iter();
-        let point: G1Point = poly
-            .zip(srs)
-            .map::<G1Point, _>(|(cof, s)| {
-                let d = s.mul(cof.clone());
-                d.into()
-            })
-            .sum();
-        point
+        if srs.len() <= polynomial.degree() {
+            return Err(KzgError::SrsTooSmall {
+                needed: polynomial.degree() + 1,
+                size: srs.len(),
+            });
+        }
+        Ok(msm::msm(srs, &polynomial.coeffs).into_affine())
     }
-    pub fn open(&self, mut polynomial: MyPoly, z: impl Into<Fr>) -> KzgOpening {
+    pub fn open(
+        &self,
+        mut polynomial: Poly<E::Fr>,
+        z: impl Into<E::Fr>,
+    ) -> Result<KzgOpening<E>, KzgError> {
         let z = z.into();
         let evaluation_at_z = polynomial.evaluate(&z);
         let first = polynomial.coeffs.first_mut().expect("at least 1");
         *first -= evaluation_at_z;
-        let root = MyPoly::from_coefficients_slice(&[-(z), 1.into()]);
+        let root = Poly::from_coefficients_slice(&[-(z), E::Fr::one()]);
         let new_poly = &polynomial / &root;
-        let opening = self.evaluate_in_s(&new_poly);
-        KzgOpening(opening, evaluation_at_z)
+        let opening = self.evaluate_in_s(&new_poly)?;
+        Ok(KzgOpening(opening, evaluation_at_z))
     }
     ///verifies the opening P(z) = y
     pub fn verify(
         &self,
-        commitment: &KzgCommitment,
-        opening: &KzgOpening,
-        z: impl Into<Fr> + Debug + Display,
+        commitment: &KzgCommitment<E>,
+        opening: &KzgOpening<E>,
+        z: impl Into<E::Fr> + Debug + Display,
     ) -> bool {
         let y = opening.1;
         //let g1 = self.0.g1_ref();
         let g2s = self.0.g2s_ref();
         let g2 = self.0.g2_ref();
         let a = g2s.clone().into_projective() - (g2.mul(z.into()));
-        let b = commitment.0.into_projective() - G1Point::prime_subgroup_generator().mul(y);
-        let pairing1 = Bls12_381::pairing(opening.0, a);
-        let pairing2 = Bls12_381::pairing(b, g2.clone());
+        let b = commitment.0.into_projective() - E::G1Affine::prime_subgroup_generator().mul(y);
+        let pairing1 = E::pairing(opening.0, a);
+        let pairing2 = E::pairing(b, *g2);
         pairing1 == pairing2
     }
-    pub fn identity(&self) -> KzgCommitment {
-        let polynomial = Poly::from_coefficients_vec(vec![Fr::from(1)]);
-        self.commit(&polynomial)
+    /// opens all the polynomials at z with one witness, the one of `Σ challenge^i·p_i`.
+    /// the challenge must be drawn after the evaluations are known to the verifier
+    pub fn open_batch(
+        &self,
+        polynomials: &[Poly<E::Fr>],
+        z: impl Into<E::Fr>,
+        challenge: E::Fr,
+    ) -> Result<KzgBatchOpening<E>, KzgError> {
+        let z = z.into();
+        let evals = polynomials
+            .iter()
+            .map(|poly| poly.evaluate(&z))
+            .collect::<Vec<_>>();
+        let mut power = E::Fr::one();
+        let mut combined = Poly::zero();
+        for poly in polynomials {
+            combined += (power, poly);
+            power *= challenge;
+        }
+        let KzgOpening(witness, _) = self.open(combined, z)?;
+        Ok(KzgBatchOpening(witness, evals))
+    }
+    ///verifies a batch opening at z, with the challenge it was opened with
+    pub fn verify_batch(
+        &self,
+        commitments: &[KzgCommitment<E>],
+        opening: &KzgBatchOpening<E>,
+        z: impl Into<E::Fr>,
+        challenge: E::Fr,
+    ) -> bool {
+        self.verify_multi_point(&[(commitments, opening, z.into())], challenge, E::Fr::one())
+    }
+    /// verifies batch openings at several points with a single pairing check, `separator` is a
+    /// second challenge drawn after the witnesses, combining the checks of each point.
+    /// With two points this is the batched opening of the PLONK paper
+    pub fn verify_multi_point(
+        &self,
+        batches: &[BatchQuery<E>],
+        challenge: E::Fr,
+        separator: E::Fr,
+    ) -> bool {
+        self.multi_point_check(batches, challenge, separator)
+            .is_ok_and(|check| self.check(&check))
+    }
+    /// the pairing check of `verify_multi_point`, without doing the pairings
+    pub fn multi_point_check(
+        &self,
+        batches: &[BatchQuery<E>],
+        challenge: E::Fr,
+        separator: E::Fr,
+    ) -> Result<PairingCheck<E>, KzgError> {
+        let g1 = E::G1Affine::prime_subgroup_generator();
+        // Σ u^k·W_k and Σ u^k·(z_k·W_k + F_k - y_k·G)
+        let mut witnesses = E::G1Projective::zero();
+        let mut rhs = E::G1Projective::zero();
+        let mut factor = E::Fr::one();
+        for (commitments, KzgBatchOpening(witness, evals), z) in batches {
+            if commitments.len() != evals.len() {
+                return Err(KzgError::EvaluationCount {
+                    commitments: commitments.len(),
+                    evals: evals.len(),
+                });
+            }
+            let mut power = factor;
+            for (commitment, eval) in commitments.iter().zip(evals) {
+                rhs += commitment.0.mul(power) - g1.mul(power * eval);
+                power *= challenge;
+            }
+            witnesses += witness.mul(factor);
+            rhs += witness.mul(factor * z);
+            factor *= separator;
+        }
+        Ok(PairingCheck { witnesses, rhs })
+    }
+    /// e(W, [s]) = e(rhs, [1]), two pairings whatever was folded into the check
+    pub fn check(&self, check: &PairingCheck<E>) -> bool {
+        let g2s = self.0.g2s_ref();
+        let g2 = self.0.g2_ref();
+        let pairs = [
+            (check.witnesses.into_affine().into(), (*g2s).into()),
+            ((-check.rhs).into_affine().into(), (*g2).into()),
+        ];
+        E::product_of_pairings(&pairs).is_one()
+    }
+    /// the commitment to the constant 1, the generator of g1
+    pub fn identity(&self) -> KzgCommitment<E> {
+        KzgCommitment(self.0.g1_ref()[0])
     }
 }
-pub fn print_poly(poly: &MyPoly) {
+pub fn print_poly<F: PrimeField>(poly: &Poly<F>) {
     println!();
     for (i, p) in poly.iter().enumerate() {
         println!("{}.X^{}", p, i);
@@ -93,30 +260,15 @@ pub fn print_poly(poly: &MyPoly) {
     println!();
 }
 
-#[test]
-fn commit() {
-    let srs = Srs::from_secret(Fr::from(2), 10);
-    let scheme = KzgScheme(&srs);
-    let poly = MyPoly::from_coefficients_slice(&[1.into(), 2.into(), 3.into()]);
-    let commitment = scheme.commit(&poly);
-    let d = Fr::from(1_i32);
-    assert_eq!(
-        commitment.0.into_projective(),
-        G1Point::prime_subgroup_generator().mul(poly.evaluate(&Fr::from(2)))
-    );
-    assert!(poly.evaluate(&d) == 6.into());
-    let opening = scheme.open(poly, d);
-    assert!(scheme.verify(&commitment, &opening, d));
-}
-impl Add for KzgCommitment {
+impl<E: PairingEngine> Add for KzgCommitment<E> {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
         let commitment = self.0 + rhs.0;
-        Self(commitment.into())
+        Self(commitment)
     }
 }
-impl Add for KzgOpening {
+impl<E: PairingEngine> Add for KzgOpening<E> {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
@@ -125,31 +277,31 @@ impl Add for KzgOpening {
         Self(witness, eval)
     }
 }
-impl Sub for KzgCommitment {
+impl<E: PairingEngine> Sub for KzgCommitment<E> {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
         Self::add(self, -rhs)
     }
 }
-impl Mul<Fr> for KzgCommitment {
+impl<E: PairingEngine> Mul<E::Fr> for KzgCommitment<E> {
     type Output = Self;
 
-    fn mul(self, rhs: Fr) -> Self::Output {
+    fn mul(self, rhs: E::Fr) -> Self::Output {
         let element = self.0.mul(rhs);
         Self(element.into())
     }
 }
 
-impl Mul<Fr> for &KzgCommitment {
-    type Output = KzgCommitment;
+impl<E: PairingEngine> Mul<E::Fr> for &KzgCommitment<E> {
+    type Output = KzgCommitment<E>;
 
-    fn mul(self, rhs: Fr) -> Self::Output {
+    fn mul(self, rhs: E::Fr) -> Self::Output {
         let element = self.0.mul(rhs);
         KzgCommitment(element.into())
     }
 }
-impl Neg for KzgCommitment {
+impl<E: PairingEngine> Neg for KzgCommitment<E> {
     type Output = Self;
 
     fn neg(self) -> Self::Output {
@@ -158,15 +310,129 @@ impl Neg for KzgCommitment {
     }
 }
 
-#[test]
-fn scalar_mul() {
-    let srs = Srs::random(5);
-    let scheme = KzgScheme::new(&srs);
-    let coeffs = [1, 2, 3, 4, 5].map(|e| Fr::from(e));
-    let poly = Poly::from_coefficients_slice(&coeffs);
-    let commit1 = scheme.commit(&poly);
-    let factor = Fr::from(9);
-    let poly2 = poly.mul(factor);
-    let commit2 = scheme.commit(&poly2);
-    assert_eq!(commit1 * factor, commit2);
+#[cfg(test)]
+mod test {
+    use crate::{srs::Srs, KzgError, KzgScheme, PairingCheck, Poly};
+    use ark_ec::{AffineCurve, PairingEngine};
+    use ark_ff::One;
+    use ark_poly::{Polynomial, UVPolynomial};
+    use std::ops::Mul;
+
+    fn commit<E: PairingEngine>() {
+        let srs = Srs::<E>::from_secret(E::Fr::from(2_u64), 10);
+        let scheme = KzgScheme(&srs);
+        let poly = Poly::from_coefficients_slice(&[1_u64, 2, 3].map(E::Fr::from));
+        let commitment = scheme.commit(&poly).unwrap();
+        let d = E::Fr::from(1_u64);
+        assert_eq!(
+            commitment.0.into_projective(),
+            E::G1Affine::prime_subgroup_generator().mul(poly.evaluate(&E::Fr::from(2_u64)))
+        );
+        assert!(poly.evaluate(&d) == E::Fr::from(6_u64));
+        let opening = scheme.open(poly, d).unwrap();
+        assert!(scheme.verify(&commitment, &opening, d));
+        assert_eq!(
+            scheme.commit(&Poly::from_coefficients_vec(vec![E::Fr::one(); 14])),
+            Err(KzgError::SrsTooSmall {
+                needed: 14,
+                size: 13
+            })
+        );
+    }
+    fn batch_opening<E: PairingEngine>() {
+        let srs = Srs::<E>::from_secret(E::Fr::from(7_u64), 10);
+        let scheme = KzgScheme(&srs);
+        let polys = [[1_u64, 2, 3], [4, 0, 6], [0, 8, 9]]
+            .map(|coeffs| Poly::from_coefficients_slice(&coeffs.map(E::Fr::from)));
+        let commitments = polys.clone().map(|poly| scheme.commit(&poly).unwrap());
+        let [z, challenge] = [5_u64, 11].map(E::Fr::from);
+        let opening = scheme.open_batch(&polys, z, challenge).unwrap();
+        assert_eq!(opening.evals()[0], E::Fr::from(1_u64 + 2 * 5 + 3 * 25));
+        assert!(scheme.verify_batch(&commitments, &opening, z, challenge));
+        assert!(!scheme.verify_batch(&commitments, &opening, z, E::Fr::from(12_u64)));
+        assert!(!scheme.verify_batch(&commitments[..2], &opening, z, challenge));
+        let mut wrong = opening.clone();
+        wrong.1[2] += E::Fr::one();
+        assert!(!scheme.verify_batch(&commitments, &wrong, z, challenge));
+    }
+    fn batch_opening_two_points<E: PairingEngine>() {
+        let srs = Srs::<E>::from_secret(E::Fr::from(7_u64), 10);
+        let scheme = KzgScheme(&srs);
+        let polys = [[1_u64, 2, 3], [4, 0, 6], [0, 8, 9]]
+            .map(|coeffs| Poly::from_coefficients_slice(&coeffs.map(E::Fr::from)));
+        let commitments = polys.clone().map(|poly| scheme.commit(&poly).unwrap());
+        let [z, zw, challenge, separator] = [5_u64, 6, 11, 13].map(E::Fr::from);
+        let first = scheme.open_batch(&polys, z, challenge).unwrap();
+        let second = scheme.open_batch(&polys[1..], zw, challenge).unwrap();
+        let batches = [
+            (&commitments[..], &first, z),
+            (&commitments[1..], &second, zw),
+        ];
+        assert!(scheme.verify_multi_point(&batches, challenge, separator));
+        let swapped = [
+            (&commitments[..], &first, zw),
+            (&commitments[1..], &second, z),
+        ];
+        assert!(!scheme.verify_multi_point(&swapped, challenge, separator));
+        let mut wrong = second.clone();
+        wrong.1[0] += E::Fr::one();
+        let batches = [
+            (&commitments[..], &first, z),
+            (&commitments[1..], &wrong, zw),
+        ];
+        assert!(!scheme.verify_multi_point(&batches, challenge, separator));
+    }
+    fn combined_checks<E: PairingEngine>() {
+        let srs = Srs::<E>::from_secret(E::Fr::from(7_u64), 10);
+        let scheme = KzgScheme(&srs);
+        let polys = [[1_u64, 2, 3], [4, 0, 6]]
+            .map(|coeffs| Poly::from_coefficients_slice(&coeffs.map(E::Fr::from)));
+        let commitments = polys.clone().map(|poly| scheme.commit(&poly).unwrap());
+        let checks = [3_u64, 4].map(|z| {
+            let z = E::Fr::from(z);
+            let opening = scheme.open_batch(&polys, z, E::Fr::from(11_u64)).unwrap();
+            scheme
+                .multi_point_check(
+                    &[(&commitments, &opening, z)],
+                    E::Fr::from(11_u64),
+                    E::Fr::one(),
+                )
+                .unwrap()
+        });
+        let factors = [E::Fr::from(17_u64), E::Fr::from(19_u64)];
+        let combined = PairingCheck::combine(checks.into_iter().zip(factors));
+        assert!(scheme.check(&combined));
+        // a check of a wrong point
+        let opening = scheme
+            .open_batch(&polys, E::Fr::from(3_u64), E::Fr::from(11_u64))
+            .unwrap();
+        let wrong = scheme
+            .multi_point_check(
+                &[(&commitments, &opening, E::Fr::from(5_u64))],
+                E::Fr::from(11_u64),
+                E::Fr::one(),
+            )
+            .unwrap();
+        let combined = PairingCheck::combine([(checks[0], factors[0]), (wrong, factors[1])]);
+        assert!(!scheme.check(&combined));
+    }
+    fn scalar_mul<E: PairingEngine>() {
+        let srs = Srs::<E>::random(5);
+        let scheme = KzgScheme::new(&srs);
+        let coeffs = [1_u64, 2, 3, 4, 5].map(E::Fr::from);
+        let poly = Poly::from_coefficients_slice(&coeffs);
+        let commit1 = scheme.commit(&poly).unwrap();
+        let factor = E::Fr::from(9_u64);
+        let poly2 = poly.mul(factor);
+        let commit2 = scheme.commit(&poly2).unwrap();
+        assert_eq!(commit1 * factor, commit2);
+    }
+
+    curve_tests!(
+        commit,
+        batch_opening,
+        batch_opening_two_points,
+        combined_checks,
+        scalar_mul
+    );
 }
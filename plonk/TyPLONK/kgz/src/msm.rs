@@ -0,0 +1,109 @@
+//! Multi-scalar multiplication with buckets (Pippenger).
+//!
+//! The scalars are cut in windows of c bits. For each window every base is added to the bucket of
+//! its digit, and the buckets are summed as `Σ d·B_d` with a running sum. The windows are then
+//! combined with c doublings each. With the `parallel` feature the windows are computed in
+//! parallel with rayon.
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{FpParameters, PrimeField, Zero};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// `Σ scalar_i·base_i`, the extra bases or scalars are ignored
+pub fn msm<G: AffineCurve>(bases: &[G], scalars: &[G::ScalarField]) -> G::Projective {
+    let size = bases.len().min(scalars.len());
+    let scalars = scalars[..size]
+        .iter()
+        .map(|scalar| scalar.into_repr())
+        .collect::<Vec<_>>();
+    let bases = &bases[..size];
+    let c = window_size(size);
+    let bits = <G::ScalarField as PrimeField>::Params::MODULUS_BITS as usize;
+    let starts = (0..bits).step_by(c).collect::<Vec<_>>();
+
+    let window_sum = |start: &usize| {
+        let mut buckets = vec![G::Projective::zero(); (1 << c) - 1];
+        for (base, scalar) in bases.iter().zip(scalars.iter()) {
+            let digit = digit(scalar, *start, c);
+            if digit != 0 {
+                buckets[digit - 1].add_assign_mixed(base);
+            }
+        }
+        // Σ d·B_d as the sum of the running sums from the highest digit down
+        let mut running = G::Projective::zero();
+        let mut sum = G::Projective::zero();
+        for bucket in buckets.into_iter().rev() {
+            running += bucket;
+            sum += running;
+        }
+        sum
+    };
+    #[cfg(feature = "parallel")]
+    let windows = starts.par_iter().map(window_sum).collect::<Vec<_>>();
+    #[cfg(not(feature = "parallel"))]
+    let windows = starts.iter().map(window_sum).collect::<Vec<_>>();
+
+    windows
+        .into_iter()
+        .rev()
+        .fold(G::Projective::zero(), |total, window| {
+            let mut total = total;
+            if !total.is_zero() {
+                for _ in 0..c {
+                    total.double_in_place();
+                }
+            }
+            total + window
+        })
+}
+
+/// the number of bits of a window, about ln(size)
+fn window_size(size: usize) -> usize {
+    match size {
+        0..=31 => 3,
+        _ => (size as f64).ln().ceil() as usize + 2,
+    }
+}
+
+/// the c bits of the scalar starting at `start`
+fn digit(scalar: &impl AsRef<[u64]>, start: usize, c: usize) -> usize {
+    let limbs = scalar.as_ref();
+    let (limb, shift) = (start / 64, start % 64);
+    let mut bits = limbs[limb] >> shift;
+    if shift + c > 64 && limb + 1 < limbs.len() {
+        bits |= limbs[limb + 1] << (64 - shift);
+    }
+    bits as usize & ((1 << c) - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::msm;
+    use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+    use ark_ff::{UniformRand, Zero};
+
+    fn naive<G: AffineCurve>(bases: &[G], scalars: &[G::ScalarField]) -> G::Projective {
+        bases
+            .iter()
+            .zip(scalars)
+            .map(|(base, scalar)| base.mul(*scalar))
+            .sum()
+    }
+
+    fn matches_naive<E: PairingEngine>() {
+        let mut rng = rand::thread_rng();
+        for size in [0, 1, 7, 32, 100] {
+            let bases = (0..size)
+                .map(|_| E::G1Projective::rand(&mut rng).into_affine())
+                .collect::<Vec<_>>();
+            let mut scalars = (0..size).map(|_| E::Fr::rand(&mut rng)).collect::<Vec<_>>();
+            if size > 1 {
+                scalars[0] = E::Fr::zero();
+                scalars[1] = -E::Fr::from(1_u64);
+            }
+            assert_eq!(msm(&bases, &scalars), naive(&bases, &scalars));
+        }
+    }
+
+    curve_tests!(matches_naive);
+}
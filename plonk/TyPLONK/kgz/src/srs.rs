@@ -1,33 +1,45 @@
-use crate::{G1Point, G2Point};
-use ark_bls12_381::Fr;
-use ark_ec::AffineCurve;
-use ark_ff::UniformRand;
-use std::iter::Iterator;
-
-#[derive(Debug)]
-pub struct Srs {
-    g1: Vec<G1Point>,
-    g2: G2Point,
-    g2s: G2Point,
+use crate::{msm::msm, KzgCommitment, KzgError};
+use ark_ec::{
+    msm::FixedBaseMSM, short_weierstrass_jacobian::GroupAffine, AffineCurve, PairingEngine,
+    ProjectiveCurve, SWModelParameters,
+};
+use ark_ff::{One, PrimeField, UniformRand};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError, Write};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek},
+    iter::Iterator,
+    path::Path,
+};
+
+pub mod ceremony;
+mod ptau;
+
+/// Powers of a secret s: `[s^i]_1` for i below the size and `[1]_2, [s]_2`.
+///
+/// An srs made for a circuit of `gates` rows can be reused for any smaller one with [`Srs::trim`].
+/// [`ceremony`] makes one whose secret is unknown, with the contributions of several participants.
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Srs<E: PairingEngine> {
+    g1: Vec<E::G1Affine>,
+    g2: E::G2Affine,
+    g2s: E::G2Affine,
 }
 
-impl Srs {
-    fn g1(s: Fr, length: usize) -> Vec<G1Point> {
-        let generator = G1Point::prime_subgroup_generator();
-        let first = std::iter::once(generator);
-        let rest = std::iter::successors(Some(s), |previous| Some(*previous * s)).map(|sx| {
-            //println!("power:{}", sx);
-            generator.mul(sx).into()
-        });
-        let srs = first.chain(rest).take(length);
-        srs.collect()
-    }
-    fn g2(s: Fr) -> (G2Point, G2Point) {
-        let generator = G2Point::prime_subgroup_generator();
+impl<E: PairingEngine> Srs<E> {
+    fn g1(s: E::Fr, length: usize) -> Vec<E::G1Affine> {
+        let powers = std::iter::successors(Some(E::Fr::one()), |previous| Some(*previous * s))
+            .take(length)
+            .collect::<Vec<_>>();
+        fixed_base::<E>(&powers)
+    }
+    fn g2(s: E::Fr) -> (E::G2Affine, E::G2Affine) {
+        let generator = E::G2Affine::prime_subgroup_generator();
         let d = generator.mul(s).into();
         (generator, d)
     }
-    pub fn from_secret(s: Fr, gates: usize) -> Self {
+    pub fn from_secret(s: E::Fr, gates: usize) -> Self {
         let g1 = Self::g1(s, gates + 3);
         let (g2, g2s) = Self::g2(s);
         Self { g1, g2, g2s }
@@ -35,18 +47,217 @@ impl Srs {
     #[cfg(feature = "rand")]
     pub fn random(gates: usize) -> Self {
         let mut rng = rand::thread_rng();
-        let s = Fr::rand(&mut rng);
+        let s = E::Fr::rand(&mut rng);
         Self::from_secret(s, gates)
     }
+    /// like `from_secret` with the lagrange basis over `domain`, computed from the secret in the
+    /// field rather than with [`Srs::lagrange_basis`], whose fft over the curve is much slower
+    pub fn from_secret_with_basis(
+        s: E::Fr,
+        gates: usize,
+        domain: GeneralEvaluationDomain<E::Fr>,
+    ) -> Result<(Self, LagrangeBasis<E>), KzgError> {
+        let srs = Self::from_secret(s, gates);
+        srs.powers(domain.size())?;
+        let basis = fixed_base::<E>(&domain.evaluate_all_lagrange_coefficients(s));
+        Ok((srs, LagrangeBasis(basis)))
+    }
+    #[cfg(feature = "rand")]
+    pub fn random_with_basis(
+        gates: usize,
+        domain: GeneralEvaluationDomain<E::Fr>,
+    ) -> Result<(Self, LagrangeBasis<E>), KzgError> {
+        let mut rng = rand::thread_rng();
+        Self::from_secret_with_basis(E::Fr::rand(&mut rng), gates, domain)
+    }
+
+    /// reads an srs written by [`Srs::save`]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SerializationError> {
+        let file = File::open(path)?;
+        Self::deserialize(BufReader::new(file))
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SerializationError> {
+        let file = File::create(path)?;
+        self.serialize(BufWriter::new(file))
+    }
+    /// reads the powers of tau of a `.ptau` file, only the ones needed for `gates` when given.
+    /// The points of the file are affine points of short Weierstrass curves, as for BLS12-381
+    /// and BN254
+    pub fn from_ptau<R: Read + Seek, P1, P2>(
+        reader: R,
+        gates: Option<usize>,
+    ) -> Result<Self, SerializationError>
+    where
+        E: PairingEngine<G1Affine = GroupAffine<P1>, G2Affine = GroupAffine<P2>>,
+        P1: SWModelParameters,
+        P2: SWModelParameters,
+    {
+        let (g1, [g2, g2s]) = ptau::read(reader, gates.map(|gates| gates + 3))?;
+        Ok(Self { g1, g2, g2s })
+    }
+    pub fn load_ptau<P1, P2>(
+        path: impl AsRef<Path>,
+        gates: Option<usize>,
+    ) -> Result<Self, SerializationError>
+    where
+        E: PairingEngine<G1Affine = GroupAffine<P1>, G2Affine = GroupAffine<P2>>,
+        P1: SWModelParameters,
+        P2: SWModelParameters,
+    {
+        let file = File::open(path)?;
+        Self::from_ptau(BufReader::new(file), gates)
+    }
+    /// the number of powers in g1, polynomials of degree below it can be committed
+    pub fn size(&self) -> usize {
+        self.g1.len()
+    }
+    /// keeps the powers `from_secret` would make for `gates`
+    pub fn trim(&self, gates: usize) -> Result<Self, KzgError> {
+        let g1 = self.powers(gates + 3)?.to_vec();
+        Ok(Self {
+            g1,
+            g2: self.g2,
+            g2s: self.g2s,
+        })
+    }
+    /// keeps what verifying openings needs, the generator of g1 and the elements of g2
+    pub fn verifier_srs(&self) -> Self {
+        Self {
+            g1: self.g1[..1].to_vec(),
+            g2: self.g2,
+            g2s: self.g2s,
+        }
+    }
 
     /// Get a  reference to the srs's g1.
-    pub fn g1_ref(&self) -> &Vec<G1Point> {
+    pub fn g1_ref(&self) -> &Vec<E::G1Affine> {
         &self.g1
     }
-    pub fn g2_ref(&self) -> &G2Point {
+    pub fn g2_ref(&self) -> &E::G2Affine {
         &self.g2
     }
-    pub fn g2s_ref(&self) -> &G2Point {
+    pub fn g2s_ref(&self) -> &E::G2Affine {
         &self.g2s
     }
+    fn powers(&self, needed: usize) -> Result<&[E::G1Affine], KzgError> {
+        self.g1.get(..needed).ok_or(KzgError::SrsTooSmall {
+            needed,
+            size: self.g1.len(),
+        })
+    }
+    /// the powers in evaluation form over the domain
+    pub fn lagrange_basis(
+        &self,
+        domain: GeneralEvaluationDomain<E::Fr>,
+    ) -> Result<LagrangeBasis<E>, KzgError> {
+        let powers = self
+            .powers(domain.size())?
+            .iter()
+            .map(|power| power.into_projective())
+            .collect::<Vec<_>>();
+        // [L_i(s)] = 1/n·Σ w^(-ij)·[s^j], the inverse fft of the powers
+        let mut basis = domain.ifft(&powers);
+        E::G1Projective::batch_normalization(&mut basis);
+        Ok(LagrangeBasis(
+            basis.into_iter().map(|point| point.into_affine()).collect(),
+        ))
+    }
+}
+
+/// `[x]_1` for each scalar, with a table of the multiples of the generator by each window of bits
+/// shared by all the scalars
+fn fixed_base<E: PairingEngine>(scalars: &[E::Fr]) -> Vec<E::G1Affine> {
+    let generator = E::G1Projective::prime_subgroup_generator();
+    let bits = E::Fr::size_in_bits();
+    let window = FixedBaseMSM::get_mul_window_size(scalars.len());
+    let table = FixedBaseMSM::get_window_table(bits, window, generator);
+    let points = FixedBaseMSM::multi_scalar_mul::<E::G1Projective>(bits, window, &table, scalars);
+    ProjectiveCurve::batch_normalization_into_affine(&points)
+}
+
+/// `[L_i(s)]_1` for the lagrange polynomials of a domain, commits to a polynomial from its
+/// evaluations over the domain, without interpolating it.
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct LagrangeBasis<E: PairingEngine>(Vec<E::G1Affine>);
+
+impl<E: PairingEngine> LagrangeBasis<E> {
+    pub fn size(&self) -> usize {
+        self.0.len()
+    }
+    /// commits to the polynomial taking the values `evals` over the domain, missing ones are 0
+    pub fn commit(&self, evals: &[E::Fr]) -> Result<KzgCommitment<E>, KzgError> {
+        if evals.len() > self.0.len() {
+            return Err(KzgError::SrsTooSmall {
+                needed: evals.len(),
+                size: self.0.len(),
+            });
+        }
+        Ok(KzgCommitment(msm(&self.0, evals).into_affine()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Srs;
+    use crate::{KzgError, KzgScheme, Poly};
+    use ark_ec::PairingEngine;
+    use ark_ff::PrimeField;
+    use ark_poly::{EvaluationDomain, Evaluations, GeneralEvaluationDomain};
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+    fn serialization<E: PairingEngine>() {
+        let srs = Srs::<E>::from_secret(E::Fr::from(7_u64), 8);
+        let mut bytes = vec![];
+        srs.serialize(&mut bytes).unwrap();
+        let read = Srs::<E>::deserialize(&bytes[..]).unwrap();
+        assert_eq!(read.g1, srs.g1);
+        assert_eq!([read.g2, read.g2s], [srs.g2, srs.g2s]);
+        assert!(Srs::<E>::deserialize(&bytes[..bytes.len() - 1]).is_err());
+    }
+    fn file<E: PairingEngine>() {
+        let srs = Srs::<E>::from_secret(E::Fr::from(7_u64), 4);
+        // the tests of each curve run at the same time
+        let bits = <E::Fq as PrimeField>::size_in_bits();
+        let name = format!("kgz-srs-{}-{}.bin", std::process::id(), bits);
+        let path = std::env::temp_dir().join(name);
+        srs.save(&path).unwrap();
+        let read = Srs::<E>::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.g1, srs.g1);
+    }
+    fn trim<E: PairingEngine>() {
+        let srs = Srs::<E>::from_secret(E::Fr::from(7_u64), 16);
+        let trimmed = srs.trim(4).unwrap();
+        assert_eq!(trimmed.g1, Srs::<E>::from_secret(E::Fr::from(7_u64), 4).g1);
+        assert_eq!(trimmed.g2s, srs.g2s);
+        let error = KzgError::SrsTooSmall {
+            needed: 20,
+            size: 19,
+        };
+        assert_eq!(srs.trim(17).unwrap_err(), error);
+    }
+    fn lagrange_basis<E: PairingEngine>() {
+        let srs = Srs::<E>::from_secret(E::Fr::from(7_u64), 8);
+        let domain = <GeneralEvaluationDomain<E::Fr>>::new(8).unwrap();
+        let basis = srs.lagrange_basis(domain).unwrap();
+        assert_eq!(basis.size(), 8);
+        let evals = [3_u64, 1, 4, 1, 5, 9, 2, 6].map(E::Fr::from).to_vec();
+        let poly: Poly<E::Fr> =
+            Evaluations::from_vec_and_domain(evals.clone(), domain).interpolate();
+        let scheme = KzgScheme::new(&srs);
+        assert_eq!(basis.commit(&evals), scheme.commit(&poly));
+        assert!(basis.commit(&[E::Fr::from(1_u64); 9]).is_err());
+        let big = <GeneralEvaluationDomain<E::Fr>>::new(16).unwrap();
+        assert!(srs.lagrange_basis(big).is_err());
+    }
+    fn basis_from_secret<E: PairingEngine>() {
+        let domain = <GeneralEvaluationDomain<E::Fr>>::new(8).unwrap();
+        let (srs, basis) = Srs::<E>::from_secret_with_basis(E::Fr::from(7_u64), 8, domain).unwrap();
+        assert_eq!(basis.0, srs.lagrange_basis(domain).unwrap().0);
+        assert_eq!(srs.g1, Srs::<E>::from_secret(E::Fr::from(7_u64), 8).g1);
+        let big = <GeneralEvaluationDomain<E::Fr>>::new(16).unwrap();
+        assert!(Srs::<E>::from_secret_with_basis(E::Fr::from(7_u64), 8, big).is_err());
+    }
+
+    curve_tests!(serialization, file, trim, lagrange_basis, basis_from_secret);
 }
@@ -0,0 +1,362 @@
+//! Powers of tau ceremony, making an [`Srs`] whose secret nobody knows.
+//!
+//! The ceremony starts from the powers of τ = 1. Each participant multiplies τ by a fresh secret x,
+//! the powers `[τ^i]_1` by `x^i` and `[τ]_2` by x, then forgets x. The secret of the final srs is
+//! the product of all the secrets and stays unknown as long as one participant was honest.
+//!
+//! A contribution publishes `[x]_1`, `[x]_2` and a Schnorr proof of knowledge of x, whose challenge
+//! is bound to the τ it multiplies, so it can't be replayed on another transcript. The transcript
+//! is verified with pairings:
+//! - `e([x]_1, [1]_2) = e([1]_1, [x]_2)`, the same x in both groups
+//! - `e([τ']_1, [1]_2) = e([τ]_1, [x]_2)` and `e([τ']_1, [1]_2) = e([1]_1, [τ']_2)`, the new τ' is
+//!   the previous τ times x in both groups
+//! - `e(Σ r^i·[τ^i]_1, [τ]_2) = e(Σ r^i·[τ^(i+1)]_1, [1]_2)`, the final points are the powers of the
+//!   last τ, with r a hash of them
+use super::Srs;
+use crate::msm::msm;
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{One, PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use blake2::{Blake2b, Digest};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+/// What a participant publishes: its secret x in both groups, a proof of knowledge of x and the τ
+/// of the accumulator after it.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Contribution<E: PairingEngine> {
+    x_g1: E::G1Affine,
+    x_g2: E::G2Affine,
+    /// `[k]_1` for the nonce k of the proof of knowledge
+    commitment: E::G1Affine,
+    /// `k + c·x` for the challenge c
+    response: E::Fr,
+    tau_g1: E::G1Affine,
+    tau_g2: E::G2Affine,
+}
+
+/// The transcript of a ceremony, the contributions in order and the powers after the last one.
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Ceremony<E: PairingEngine> {
+    contributions: Vec<Contribution<E>>,
+    g1: Vec<E::G1Affine>,
+    g2s: E::G2Affine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CeremonyError {
+    /// the proof of knowledge of the secret of a contribution is wrong
+    KnowledgeProof { contribution: usize },
+    /// a contribution does not multiply τ by its secret, or its secret is 0
+    Contribution { contribution: usize },
+    /// the points are not the powers of the τ of the last contribution
+    Powers,
+}
+
+impl Display for CeremonyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::KnowledgeProof { contribution } => write!(
+                f,
+                "the proof of knowledge of contribution {} is wrong",
+                contribution
+            ),
+            Self::Contribution { contribution } => write!(
+                f,
+                "contribution {} does not multiply the previous secret",
+                contribution
+            ),
+            Self::Powers => write!(f, "the points are not the powers of the last secret"),
+        }
+    }
+}
+impl std::error::Error for CeremonyError {}
+
+impl<E: PairingEngine> Ceremony<E> {
+    /// starts a ceremony for circuits of up to `gates` rows, with the powers `from_secret` makes
+    pub fn new(gates: usize) -> Self {
+        Self {
+            contributions: vec![],
+            g1: vec![E::G1Affine::prime_subgroup_generator(); gates + 3],
+            g2s: E::G2Affine::prime_subgroup_generator(),
+        }
+    }
+    /// multiplies τ by the secret, which must not be known to anyone after this call
+    pub fn contribute(&mut self, secret: E::Fr) {
+        assert!(!secret.is_zero(), "a secret of 0 erases the ceremony");
+        let (g1, g2) = generators::<E>();
+        let (x_g1, x_g2) = (g1.mul(secret).into_affine(), g2.mul(secret).into_affine());
+        let (tau_g1, tau_g2) = self.tau();
+        // a deterministic nonce, from the secret and the τ it multiplies
+        let mut bytes = vec![];
+        (secret, tau_g1, tau_g2).serialize(&mut bytes).unwrap();
+        let nonce = hash::<E>(&bytes);
+        let commitment = g1.mul(nonce).into_affine();
+        let index = self.contributions.len();
+        let challenge = challenge::<E>(index, (tau_g1, tau_g2), x_g1, x_g2, commitment);
+
+        let powers = std::iter::successors(Some(E::Fr::one()), |power| Some(*power * secret))
+            .take(self.g1.len())
+            .collect::<Vec<_>>();
+        let scale = |(point, power): (&E::G1Affine, &E::Fr)| point.mul(*power);
+        #[cfg(feature = "parallel")]
+        let g1 = self
+            .g1
+            .par_iter()
+            .zip(&powers)
+            .map(scale)
+            .collect::<Vec<_>>();
+        #[cfg(not(feature = "parallel"))]
+        let g1 = self.g1.iter().zip(&powers).map(scale).collect::<Vec<_>>();
+        self.g1 = E::G1Projective::batch_normalization_into_affine(&g1);
+        self.g2s = self.g2s.mul(secret).into_affine();
+
+        let (tau_g1, tau_g2) = self.tau();
+        self.contributions.push(Contribution {
+            x_g1,
+            x_g2,
+            commitment,
+            response: nonce + challenge * secret,
+            tau_g1,
+            tau_g2,
+        });
+    }
+    /// contributes a random secret, which is dropped when the call returns
+    #[cfg(feature = "rand")]
+    pub fn contribute_random(&mut self) {
+        use ark_ff::UniformRand;
+        let mut rng = rand::thread_rng();
+        let secret = loop {
+            let secret = E::Fr::rand(&mut rng);
+            if !secret.is_zero() {
+                break secret;
+            }
+        };
+        self.contribute(secret);
+    }
+    pub fn contributions(&self) -> &[Contribution<E>] {
+        &self.contributions
+    }
+    /// checks the proofs of knowledge, that each contribution multiplies the previous τ by its
+    /// secret and that the points are the powers of the last τ
+    pub fn verify(&self) -> Result<(), CeremonyError> {
+        let (g1, g2) = generators::<E>();
+        let mut previous = (g1, g2);
+        for (index, contribution) in self.contributions.iter().enumerate() {
+            let Contribution {
+                x_g1,
+                x_g2,
+                commitment,
+                response,
+                tau_g1,
+                tau_g2,
+            } = *contribution;
+            let challenge = challenge::<E>(index, previous, x_g1, x_g2, commitment);
+            // [k + c·x]_1 = [k]_1 + c·[x]_1
+            if g1.mul(response) != commitment.into_projective() + x_g1.mul(challenge) {
+                return Err(CeremonyError::KnowledgeProof {
+                    contribution: index,
+                });
+            }
+            let consistent = !x_g1.is_zero()
+                && same_pairing::<E>((x_g1, g2), (g1, x_g2))
+                && same_pairing::<E>((tau_g1, g2), (previous.0, x_g2))
+                && same_pairing::<E>((tau_g1, g2), (g1, tau_g2));
+            if !consistent {
+                return Err(CeremonyError::Contribution {
+                    contribution: index,
+                });
+            }
+            previous = (tau_g1, tau_g2);
+        }
+
+        if self.g1.len() < 2 || self.g1[0] != g1 || self.tau() != previous {
+            return Err(CeremonyError::Powers);
+        }
+        let mut bytes = vec![];
+        self.g1.serialize(&mut bytes).unwrap();
+        self.g2s.serialize(&mut bytes).unwrap();
+        let r = hash::<E>(&bytes);
+        let factors = std::iter::successors(Some(E::Fr::one()), |power| Some(*power * r))
+            .take(self.g1.len() - 1)
+            .collect::<Vec<_>>();
+        let lower = msm(&self.g1[..self.g1.len() - 1], &factors).into_affine();
+        let higher = msm(&self.g1[1..], &factors).into_affine();
+        match same_pairing::<E>((lower, self.g2s), (higher, g2)) {
+            true => Ok(()),
+            false => Err(CeremonyError::Powers),
+        }
+    }
+    /// the srs of a verified transcript
+    pub fn srs(&self) -> Result<Srs<E>, CeremonyError> {
+        self.verify()?;
+        Ok(Srs {
+            g1: self.g1.clone(),
+            g2: E::G2Affine::prime_subgroup_generator(),
+            g2s: self.g2s,
+        })
+    }
+
+    /// reads a transcript written by [`Ceremony::save`]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SerializationError> {
+        let file = File::open(path)?;
+        Self::deserialize(BufReader::new(file))
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SerializationError> {
+        let file = File::create(path)?;
+        self.serialize(BufWriter::new(file))
+    }
+    /// `[τ]_1` and `[τ]_2`
+    fn tau(&self) -> (E::G1Affine, E::G2Affine) {
+        (self.g1[1], self.g2s)
+    }
+}
+
+fn generators<E: PairingEngine>() -> (E::G1Affine, E::G2Affine) {
+    (
+        E::G1Affine::prime_subgroup_generator(),
+        E::G2Affine::prime_subgroup_generator(),
+    )
+}
+/// e(a, b) = e(c, d)
+fn same_pairing<E: PairingEngine>(
+    (a, b): (E::G1Affine, E::G2Affine),
+    (c, d): (E::G1Affine, E::G2Affine),
+) -> bool {
+    let pairs = [(a.into(), b.into()), ((-c).into(), d.into())];
+    E::product_of_pairings(&pairs).is_one()
+}
+/// a scalar from the blake2b hash of the bytes
+fn hash<E: PairingEngine>(bytes: &[u8]) -> E::Fr {
+    E::Fr::from_le_bytes_mod_order(&Blake2b::digest(bytes))
+}
+/// the challenge of the proof of knowledge of the contribution `index`, made on the τ `previous`
+fn challenge<E: PairingEngine>(
+    index: usize,
+    previous: (E::G1Affine, E::G2Affine),
+    x_g1: E::G1Affine,
+    x_g2: E::G2Affine,
+    commitment: E::G1Affine,
+) -> E::Fr {
+    let mut bytes = vec![];
+    (index as u64, previous).serialize(&mut bytes).unwrap();
+    (x_g1, x_g2, commitment).serialize(&mut bytes).unwrap();
+    hash::<E>(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Ceremony, CeremonyError};
+    use crate::{srs::Srs, KzgScheme, Poly};
+    use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+    use ark_ff::One;
+    use ark_poly::UVPolynomial;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+    /// three participants with known secrets
+    fn three_participants<E: PairingEngine>() -> Ceremony<E> {
+        let mut ceremony = Ceremony::<E>::new(8);
+        for secret in [3_u64, 5, 7] {
+            ceremony.contribute(E::Fr::from(secret));
+        }
+        ceremony
+    }
+
+    fn participants<E: PairingEngine>() {
+        let ceremony = three_participants::<E>();
+        assert_eq!(ceremony.contributions().len(), 3);
+        assert_eq!(ceremony.verify(), Ok(()));
+        // the secret is the product of the secrets of the participants
+        let srs = ceremony.srs().unwrap();
+        let expected = Srs::<E>::from_secret(E::Fr::from(105_u64), 8);
+        assert_eq!(srs.g1, expected.g1);
+        assert_eq!([srs.g2, srs.g2s], [expected.g2, expected.g2s]);
+
+        let scheme = KzgScheme::new(&srs);
+        let poly = Poly::from_coefficients_slice(&[1_u64, 2, 3].map(E::Fr::from));
+        let commitment = scheme.commit(&poly).unwrap();
+        let opening = scheme.open(poly, E::Fr::from(4_u64)).unwrap();
+        assert!(scheme.verify(&commitment, &opening, E::Fr::from(4_u64)));
+
+        // no contribution is the srs of 1, which is still consistent
+        assert_eq!(Ceremony::<E>::new(4).verify(), Ok(()));
+    }
+    fn random_participants<E: PairingEngine>() {
+        let mut ceremony = Ceremony::<E>::new(4);
+        ceremony.contribute_random();
+        ceremony.contribute_random();
+        assert_eq!(ceremony.verify(), Ok(()));
+    }
+    fn wrong_proof<E: PairingEngine>() {
+        let mut ceremony = three_participants::<E>();
+        ceremony.contributions[1].response += E::Fr::one();
+        let error = CeremonyError::KnowledgeProof { contribution: 1 };
+        assert_eq!(ceremony.verify(), Err(error));
+        assert_eq!(ceremony.srs().unwrap_err(), error);
+    }
+    fn replayed_proof<E: PairingEngine>() {
+        // a participant copying the contribution of another one to a different transcript
+        let mut other = Ceremony::<E>::new(8);
+        other.contribute(E::Fr::from(11_u64));
+        let mut ceremony = three_participants::<E>();
+        ceremony.contributions[0] = other.contributions[0].clone();
+        assert!(ceremony.verify().is_err());
+        // the proof is bound to the τ it was made on
+        let mut ceremony = three_participants::<E>();
+        let (first, second) = ceremony.contributions.split_at_mut(2);
+        let copy = first[1].clone();
+        second[0].x_g1 = copy.x_g1;
+        second[0].x_g2 = copy.x_g2;
+        second[0].commitment = copy.commitment;
+        second[0].response = copy.response;
+        let error = CeremonyError::KnowledgeProof { contribution: 2 };
+        assert_eq!(ceremony.verify(), Err(error));
+    }
+    fn inconsistent_contribution<E: PairingEngine>() {
+        // a participant publishing τ·x without having multiplied the powers by x
+        let mut ceremony = three_participants::<E>();
+        let tau = ceremony.contributions[2].tau_g1;
+        ceremony.contributions[2].tau_g1 = tau.mul(E::Fr::from(2_u64)).into_affine();
+        let error = CeremonyError::Contribution { contribution: 2 };
+        assert_eq!(ceremony.verify(), Err(error));
+    }
+    fn wrong_powers<E: PairingEngine>() {
+        let mut ceremony = three_participants::<E>();
+        ceremony.g1.swap(3, 4);
+        assert_eq!(ceremony.verify(), Err(CeremonyError::Powers));
+        let mut ceremony = three_participants::<E>();
+        ceremony.g1[1] = ceremony.g1[2];
+        assert!(ceremony.verify().is_err());
+        let mut ceremony = three_participants::<E>();
+        ceremony.g1.truncate(1);
+        assert_eq!(ceremony.verify(), Err(CeremonyError::Powers));
+    }
+    fn serialization<E: PairingEngine>() {
+        let ceremony = three_participants::<E>();
+        let mut bytes = vec![];
+        ceremony.serialize(&mut bytes).unwrap();
+        let mut read = Ceremony::<E>::deserialize(&bytes[..]).unwrap();
+        assert_eq!(read.contributions, ceremony.contributions);
+        assert_eq!(read.g1, ceremony.g1);
+        // a participant contributes to the transcript read back
+        read.contribute(E::Fr::from(9_u64));
+        let expected = Srs::<E>::from_secret(E::Fr::from(945_u64), 8);
+        assert_eq!(read.srs().unwrap().g1, expected.g1);
+    }
+
+    curve_tests!(
+        participants,
+        random_participants,
+        wrong_proof,
+        replayed_proof,
+        inconsistent_contribution,
+        wrong_powers,
+        serialization
+    );
+}
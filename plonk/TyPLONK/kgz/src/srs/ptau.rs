@@ -0,0 +1,245 @@
+//! Reader for the `.ptau` files of snarkjs, the format the perpetual powers of tau are distributed in.
+//!
+//! A file is `"ptau"`, a u32 version and a u32 number of sections, each section being a u32 type, a
+//! u64 size and its data. Section 1 is the header: n8q, the modulus q in n8q bytes, the power and
+//! the power of the ceremony. Section 2 holds the `2^(power + 1) - 1` powers of tau in G1 and
+//! section 3 the `2^power` powers in G2. Integers and field elements are little endian, field elements
+//! in Montgomery form, and points are uncompressed with the point at infinity written as zeros.
+use ark_ec::{
+    short_weierstrass_jacobian::GroupAffine, AffineCurve, ModelParameters, SWModelParameters,
+};
+use ark_ff::{BigInteger, Field, FpParameters, FromBytes, PrimeField, Zero};
+use ark_serialize::SerializationError;
+use std::{
+    collections::HashMap,
+    io::{Read, Seek, SeekFrom},
+};
+
+const MAGIC: &[u8; 4] = b"ptau";
+const HEADER: u32 = 1;
+const TAU_G1: u32 = 2;
+const TAU_G2: u32 = 3;
+/// the prime field of the coordinates of the points of `P`
+type Fq<P> = <<P as ModelParameters>::BaseField as Field>::BasePrimeField;
+
+/// bytes of an element of the prime field
+fn element_bytes<F: PrimeField>() -> usize {
+    <F::BigInt as BigInteger>::NUM_LIMBS * 8
+}
+
+/// the powers of tau in G1 and the first 2 in G2
+type Powers<P1, P2> = (Vec<GroupAffine<P1>>, [GroupAffine<P2>; 2]);
+
+/// reads `length` powers of tau in G1, all of them when not given, and the first 2 in G2
+pub(super) fn read<R: Read + Seek, P1, P2>(
+    mut reader: R,
+    length: Option<usize>,
+) -> Result<Powers<P1, P2>, SerializationError>
+where
+    P1: SWModelParameters,
+    P2: SWModelParameters,
+{
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(SerializationError::InvalidData);
+    }
+    let _version = read_u32(&mut reader)?;
+    let sections = read_u32(&mut reader)?;
+    // section type -> position of its data
+    let mut positions = HashMap::new();
+    for _ in 0..sections {
+        let kind = read_u32(&mut reader)?;
+        let size = read_u64(&mut reader)?;
+        let position = reader.stream_position()?;
+        positions.insert(kind, position);
+        reader.seek(SeekFrom::Current(size as i64))?;
+    }
+
+    seek_section(&mut reader, &positions, HEADER)?;
+    let n8q = read_u32(&mut reader)? as usize;
+    let mut modulus = vec![0; n8q];
+    reader.read_exact(&mut modulus)?;
+    if n8q != element_bytes::<Fq<P1>>()
+        || modulus != <Fq<P1> as PrimeField>::Params::MODULUS.to_bytes_le()
+    {
+        return Err(SerializationError::InvalidData);
+    }
+    let power = read_u32(&mut reader)?;
+    let available = (1_usize << (power + 1)) - 1;
+    let length = length.unwrap_or(available);
+    if length > available {
+        return Err(SerializationError::NotEnoughSpace);
+    }
+
+    seek_section(&mut reader, &positions, TAU_G1)?;
+    let g1 = (0..length)
+        .map(|_| read_point(&mut reader))
+        .collect::<Result<Vec<GroupAffine<P1>>, _>>()?;
+    seek_section(&mut reader, &positions, TAU_G2)?;
+    let g2: [GroupAffine<P2>; 2] = [read_point(&mut reader)?, read_point(&mut reader)?];
+    if g1.first() != Some(&GroupAffine::prime_subgroup_generator())
+        || g2[0] != GroupAffine::prime_subgroup_generator()
+    {
+        return Err(SerializationError::InvalidData);
+    }
+    Ok((g1, g2))
+}
+
+fn seek_section(
+    reader: &mut impl Seek,
+    positions: &HashMap<u32, u64>,
+    kind: u32,
+) -> Result<(), SerializationError> {
+    let position = positions
+        .get(&kind)
+        .ok_or(SerializationError::InvalidData)?;
+    reader.seek(SeekFrom::Start(*position))?;
+    Ok(())
+}
+fn read_u32(reader: &mut impl Read) -> Result<u32, SerializationError> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+fn read_u64(reader: &mut impl Read) -> Result<u64, SerializationError> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+/// `x·R`, the montgomery form of x, is read as an integer and multiplied by `R^-1`
+fn read_fq<F: PrimeField>(reader: &mut impl Read) -> Result<F, SerializationError> {
+    let repr = F::BigInt::read(reader)?;
+    let montgomery = F::from_repr(repr).ok_or(SerializationError::InvalidData)?;
+    let r = F::from_repr(F::Params::R).unwrap();
+    Ok(montgomery * r.inverse().unwrap())
+}
+/// a coordinate is the elements of the prime field it is made of, one in G1 and two in G2
+fn read_coordinate<F: Field>(reader: &mut impl Read) -> Result<F, SerializationError> {
+    let elements = (0..F::extension_degree())
+        .map(|_| read_fq(reader))
+        .collect::<Result<Vec<_>, _>>()?;
+    F::from_base_prime_field_elems(&elements).ok_or(SerializationError::InvalidData)
+}
+/// rejects points that are not on the curve or not in the prime order subgroup
+fn read_point<P: SWModelParameters>(
+    reader: &mut impl Read,
+) -> Result<GroupAffine<P>, SerializationError> {
+    let [x, y] = [read_coordinate(reader)?, read_coordinate(reader)?];
+    let point = GroupAffine::<P>::new(x, y, x.is_zero() && y.is_zero());
+    match point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve() {
+        true => Ok(point),
+        false => Err(SerializationError::InvalidData),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{element_bytes, HEADER, MAGIC, TAU_G1, TAU_G2};
+    use crate::srs::Srs;
+    use ark_ec::{
+        short_weierstrass_jacobian::GroupAffine, AffineCurve, PairingEngine, SWModelParameters,
+    };
+    use ark_ff::{BigInteger, FpParameters, PrimeField, ToBytes};
+    use ark_serialize::CanonicalSerialize;
+    use std::io::Cursor;
+
+    /// the coordinates of the point in montgomery form, from its uncompressed serialization whose
+    /// field elements are little endian integers, the flags are 0 for points other than infinity
+    fn write_point<E: PairingEngine>(bytes: &mut Vec<u8>, point: impl CanonicalSerialize) {
+        let mut serialized = vec![];
+        point.serialize_uncompressed(&mut serialized).unwrap();
+        let r = E::Fq::from_repr(<E::Fq as PrimeField>::Params::R).unwrap();
+        for element in serialized.chunks(element_bytes::<E::Fq>()) {
+            let fq = E::Fq::from_le_bytes_mod_order(element);
+            (fq * r).into_repr().write(&mut *bytes).unwrap();
+        }
+    }
+    fn section(bytes: &mut Vec<u8>, kind: u32, data: Vec<u8>) {
+        bytes.extend_from_slice(&kind.to_le_bytes());
+        bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        bytes.extend(data);
+    }
+    /// a ptau file with the powers of `s`, the G2 section first to check sections are looked up
+    fn ptau<E: PairingEngine>(s: E::Fr, power: u32) -> Vec<u8> {
+        let powers =
+            std::iter::successors(Some(E::Fr::from(1_u64)), |previous| Some(*previous * s));
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&1_u32.to_le_bytes());
+        bytes.extend_from_slice(&3_u32.to_le_bytes());
+        let mut g2 = vec![];
+        for power in powers.clone().take(1 << power) {
+            let point = E::G2Affine::prime_subgroup_generator().mul(power);
+            write_point::<E>(&mut g2, point);
+        }
+        section(&mut bytes, TAU_G2, g2);
+        let mut header = (element_bytes::<E::Fq>() as u32).to_le_bytes().to_vec();
+        header.extend(<E::Fq as PrimeField>::Params::MODULUS.to_bytes_le());
+        header.extend_from_slice(&power.to_le_bytes());
+        header.extend_from_slice(&power.to_le_bytes());
+        section(&mut bytes, HEADER, header);
+        let mut g1 = vec![];
+        for power in powers.take((1 << (power + 1)) - 1) {
+            let point = E::G1Affine::prime_subgroup_generator().mul(power);
+            write_point::<E>(&mut g1, point);
+        }
+        section(&mut bytes, TAU_G1, g1);
+        bytes
+    }
+
+    fn powers_of_tau<E, P1, P2>()
+    where
+        E: PairingEngine<G1Affine = GroupAffine<P1>, G2Affine = GroupAffine<P2>>,
+        P1: SWModelParameters,
+        P2: SWModelParameters,
+    {
+        let s = E::Fr::from(11_u64);
+        let file = ptau::<E>(s, 3);
+        let srs = Srs::<E>::from_ptau(Cursor::new(&file), Some(4)).unwrap();
+        let expected = Srs::<E>::from_secret(s, 4);
+        assert_eq!(srs.g1_ref(), expected.g1_ref());
+        assert_eq!(srs.g2s_ref(), expected.g2s_ref());
+        let srs = Srs::<E>::from_ptau(Cursor::new(&file), None).unwrap();
+        assert_eq!(srs.size(), 15);
+        assert!(Srs::<E>::from_ptau(Cursor::new(&file), Some(14)).is_err());
+    }
+    fn invalid_points<E, P1, P2>()
+    where
+        E: PairingEngine<G1Affine = GroupAffine<P1>, G2Affine = GroupAffine<P2>>,
+        P1: SWModelParameters,
+        P2: SWModelParameters,
+    {
+        let mut file = ptau::<E>(E::Fr::from(11_u64), 2);
+        // the last byte of y of the last power in G1
+        let last = file.len() - 1;
+        file[last] ^= 1;
+        assert!(Srs::<E>::from_ptau(Cursor::new(&file), None).is_err());
+        assert!(Srs::<E>::from_ptau(Cursor::new(&file[..10]), None).is_err());
+    }
+
+    // the curves with the parameters of their groups
+    mod bls12_381 {
+        use ark_bls12_381::{g1, g2, Bls12_381};
+
+        #[test]
+        fn powers_of_tau() {
+            super::powers_of_tau::<Bls12_381, g1::Parameters, g2::Parameters>()
+        }
+        #[test]
+        fn invalid_points() {
+            super::invalid_points::<Bls12_381, g1::Parameters, g2::Parameters>()
+        }
+    }
+    mod bn254 {
+        use ark_bn254::{g1, g2, Bn254};
+
+        #[test]
+        fn powers_of_tau() {
+            super::powers_of_tau::<Bn254, g1::Parameters, g2::Parameters>()
+        }
+        #[test]
+        fn invalid_points() {
+            super::invalid_points::<Bn254, g1::Parameters, g2::Parameters>()
+        }
+    }
+}
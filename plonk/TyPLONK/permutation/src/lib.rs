@@ -1,11 +1,8 @@
-use ark_bls12_381::Fr;
-use ark_ff::{One, Zero};
+use ark_ec::PairingEngine;
+use ark_ff::{FftField, PrimeField};
 use ark_poly::{EvaluationDomain, Evaluations, GeneralEvaluationDomain, Polynomial};
-use kgz::{KzgCommitment, KzgScheme};
-use std::{
-    collections::{HashMap, HashSet},
-    mem::swap,
-};
+use kgz::{KzgCommitment, KzgError, KzgScheme, Poly};
+use std::{collections::HashMap, mem::swap};
 
 pub mod proving;
 
@@ -16,7 +13,7 @@ pub struct Tag {
 }
 impl Tag {
     /// flatten tag to index
-    fn to_index(&self, rows: &usize) -> usize {
+    fn to_index(self, rows: &usize) -> usize {
         self.j + self.i * rows
     }
     fn from_index(index: &usize, rows: &usize) -> Self {
@@ -25,6 +22,10 @@ impl Tag {
         Tag { i, j }
     }
 }
+/// a constrain on a cell outside of the columns and rows of the permutation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidTag(pub Tag);
+
 #[derive(Default, Debug)]
 pub struct PermutationBuilder<const C: usize> {
     /// stores the constraints(equal) between tags
@@ -38,28 +39,71 @@ impl<const C: usize> PermutationBuilder<C> {
         self.rows += 1;
     }
     pub fn with_rows(rows: usize) -> Self {
-        let mut new = Self::default();
-        new.rows = rows;
-        new
+        Self {
+            rows,
+            ..Default::default()
+        }
     }
     ///checks if a tag is valid
     fn check_tag(&self, tag: &Tag) -> bool {
         let Tag { i, j } = tag;
-        i <= &C && j < &self.rows
+        i < &C && j < &self.rows
     }
-    pub fn add_constrain(&mut self, left: Tag, right: Tag) -> Result<(), ()> {
-        if !(self.check_tag(&left) && self.check_tag(&right)) {
-            Err(())
+    pub fn add_constrain(&mut self, left: Tag, right: Tag) -> Result<(), InvalidTag> {
+        if let Some(tag) = [left, right].into_iter().find(|tag| !self.check_tag(tag)) {
+            Err(InvalidTag(tag))
         } else {
             let cell = self.constrains.entry(left).or_insert(Vec::with_capacity(8));
             cell.push(right);
             Ok(())
         }
     }
-    pub fn add_constrains(&mut self, constrains: Vec<(Tag, Tag)>) {
+    pub fn add_constrains(&mut self, constrains: Vec<(Tag, Tag)>) -> Result<(), InvalidTag> {
         for (left, right) in constrains {
-            self.add_constrain(left, right).unwrap();
+            self.add_constrain(left, right)?;
         }
+        Ok(())
+    }
+    /// the pairs of cells added with `add_constrain`
+    pub fn constrains(&self) -> impl Iterator<Item = (Tag, Tag)> + '_ {
+        self.constrains
+            .iter()
+            .flat_map(|(left, rights)| rights.iter().map(move |right| (*left, *right)))
+    }
+    /// the sets of cells constrained to be equal, the cycles `build` makes of 2 cells or more.
+    /// The cells of a set are sorted by row then column, the sets by their first cell
+    pub fn classes(&self) -> Vec<Vec<Tag>> {
+        fn root(parents: &HashMap<Tag, Tag>, mut tag: Tag) -> Tag {
+            while let Some(parent) = parents.get(&tag).filter(|parent| **parent != tag) {
+                tag = *parent;
+            }
+            tag
+        }
+        // union-find over the constrained cells, each root pointing to itself
+        let mut parents = HashMap::<Tag, Tag>::new();
+        for (left, right) in self.constrains() {
+            let [left, right] = [left, right].map(|tag| {
+                parents.entry(tag).or_insert(tag);
+                root(&parents, tag)
+            });
+            if left != right {
+                parents.insert(left, right);
+            }
+        }
+        let mut classes = HashMap::<Tag, Vec<Tag>>::new();
+        for tag in parents.keys() {
+            classes.entry(root(&parents, *tag)).or_default().push(*tag);
+        }
+        let mut classes = classes
+            .into_values()
+            .filter(|class| class.len() > 1)
+            .map(|mut class| {
+                class.sort_by_key(|tag| (tag.j, tag.i));
+                class
+            })
+            .collect::<Vec<_>>();
+        classes.sort_by_key(|class| (class[0].j, class[0].i));
+        classes
     }
 
     /// designed to execute the permutation based on the constraints that have been added. It effectively ensures that the variables constrained to be equal are correctly permuted by swapping their positions in the permutation vector
@@ -73,7 +117,7 @@ impl<const C: usize> PermutationBuilder<C> {
         // the aux vector as a mapping where each position (index) represents a tag, and the value at that position represents the parent tag or the representative element of the set to which the tag belongs.
         let mut aux = (0..len).collect::<Vec<_>>();
         // used to keep track of the size of each set in the union-find structure.
-        let mut sizes = std::iter::repeat(1).take(len).collect::<Vec<_>>();
+        let mut sizes = std::iter::repeat_n(1, len).collect::<Vec<_>>();
         // take the value from self.constrains and then set it to empty
         let constrains = std::mem::take(&mut self.constrains);
         for (left, rights) in constrains.into_iter() {
@@ -99,9 +143,9 @@ impl<const C: usize> PermutationBuilder<C> {
                 let aux_left = aux[left];
                 // This loop is ensuring that the newly merged set has consistent parent (or root) information across all its elements.
                 /* The aux array is used to store the parent or representative of each element. Initially, each element is its own parent.
-                   When two sets are merged, the parent of the right set is set to the parent of the left set.
-                   When merging two sets, not only the direct elements but also all elements in the chain need to be updated to reflect the new parent/root.
-                 */
+                  When two sets are merged, the parent of the right set is set to the parent of the left set.
+                  When merging two sets, not only the direct elements but also all elements in the chain need to be updated to reflect the new parent/root.
+                */
                 loop {
                     // This line sets the parent of next to aux_left, which is the parent of left.
                     aux[next] = aux_left;
@@ -120,7 +164,7 @@ impl<const C: usize> PermutationBuilder<C> {
 #[derive(Debug)]
 pub struct Permutation<const C: usize> {
     /// A vector that holds the permutation. Each element in the vector indicates the new position of the corresponding tag after applying the permutation
-    /// 
+    ///
     /// for example `let perm = vec![3, 1, 2, 0, 4, 5, 6, 7, 8, 9, 10, 11];`
     /// The element originally at index 0 is now at index 3.
     /// The element originally at index 3 is now at index 0.
@@ -129,13 +173,14 @@ pub struct Permutation<const C: usize> {
 }
 
 impl<const C: usize> Permutation<C> {
-    pub fn compile(self) -> CompiledPermutation<C> {
+    /// the sigma columns over the field `F` of the circuit
+    pub fn compile<F: PrimeField>(self) -> CompiledPermutation<F, C> {
         // permu now is permued mapping
         assert_eq!(self.perm.len() % C, 0);
         let rows = self.perm.len() / C;
         let cols = self.perm.chunks(rows);
-        let cosets = Self::cosets(rows);
-        let domain = <GeneralEvaluationDomain<Fr>>::new(rows).unwrap();
+        let cosets = Self::cosets::<F>(rows);
+        let domain = <GeneralEvaluationDomain<F>>::new(rows).unwrap();
         let roots = domain.elements().collect::<Vec<_>>();
         let perm = cols.enumerate().map(|(i, col)| {
             // generate a collection of (tag, value) pairs
@@ -156,108 +201,89 @@ impl<const C: usize> Permutation<C> {
             //let poly = DensePolynomial::from_coefficients_vec(coefficients);
             //poly
         });
-        let mut cols: [Vec<(Fr, Fr)>; C] = [0_u8; C].map(|_| Default::default());
+        let mut cols: [Vec<(F, F)>; C] = [0_u8; C].map(|_| Default::default());
         for (i, col) in perm.enumerate() {
             cols[i] = col;
         }
         CompiledPermutation { cols, cosets, rows }
     }
+    /// logs the cell each cell is mapped to, row by row, at the trace level
     pub fn print(&self) {
-        println!("len: {}", self.perm.len());
+        if !log::log_enabled!(log::Level::Trace) {
+            return;
+        }
+        log::trace!("permutation of {} cells", self.perm.len());
         let rows = self.perm.len() / C;
         let perm = &self.perm;
         for j in 0..rows {
-            let mut row = vec![];
-            for i in 0..C {
-                row.push(perm[j + i * rows]);
-            }
-            println!("{:?}", row);
+            let row = (0..C).map(|i| perm[j + i * rows]).collect::<Vec<_>>();
+            log::trace!("{:?}", row);
         }
     }
-    fn cosets(gates: usize) -> [Fr; C] {
-        let domain = <GeneralEvaluationDomain<Fr>>::new(gates).unwrap();
-        let mut cosets = [Fr::zero(); C];
+    fn cosets<F: FftField>(gates: usize) -> [F; C] {
+        let domain = <GeneralEvaluationDomain<F>>::new(gates).unwrap();
+        let mut cosets = [F::zero(); C];
 
-        let mut k = Fr::one();
+        let mut k = F::one();
         for coset in cosets.iter_mut() {
             // check if k is a root of the vanishing polynomial of the domain.
             while domain.evaluate_vanishing_polynomial(k).is_zero() {
-                k += Fr::from(1);
+                k += F::one();
             }
             *coset = k;
-            k += Fr::from(1);
+            k += F::one();
         }
         cosets
     }
 }
 #[derive(Debug)]
-pub struct CompiledPermutation<const C: usize> {
+pub struct CompiledPermutation<F: PrimeField, const C: usize> {
     //cols: Vec<Vec<(Fr, Fr)>>,
-    pub cols: [Vec<(Fr, Fr)>; C],
-    pub cosets: [Fr; C],
+    pub cols: [Vec<(F, F)>; C],
+    pub cosets: [F; C],
     rows: usize,
 }
 
-impl<const C: usize> CompiledPermutation<C> {
-    pub fn sigma_evals(&self, point: &Fr, domain: GeneralEvaluationDomain<Fr>) -> [Fr; C] {
-        self.cols
-            .iter()
-            .map(|col| {
-                let evals = col.iter().map(|cell| cell.1).collect();
-                let eval = <Evaluations<Fr>>::from_vec_and_domain(evals, domain);
-                let poly = eval.interpolate();
-                poly.evaluate(point)
-            })
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap()
+impl<F: PrimeField, const C: usize> CompiledPermutation<F, C> {
+    /// rebuilds the permutation from the values of its sigma columns, see `sigma_values`
+    pub fn from_sigmas(sigmas: [Vec<F>; C]) -> Self {
+        let rows = sigmas[0].len();
+        let cosets = Permutation::<C>::cosets(rows);
+        let domain = <GeneralEvaluationDomain<F>>::new(rows).unwrap();
+        let mut i = 0;
+        let cols = sigmas.map(|col| {
+            let coset = cosets[i];
+            i += 1;
+            domain
+                .elements()
+                .zip(col)
+                .map(|(root, value)| (coset * root, value))
+                .collect()
+        });
+        CompiledPermutation { cols, cosets, rows }
     }
-    pub fn sigma_commitments(
-        &self,
-        scheme: &KzgScheme,
-        domain: GeneralEvaluationDomain<Fr>,
-    ) -> [KzgCommitment; C] {
+    /// the values of the sigma columns
+    pub fn sigma_values(&self) -> [Vec<F>; C] {
         self.cols
-            .iter()
-            .map(|col| {
-                let evals = col.iter().map(|cell| cell.1).collect();
-                let eval = <Evaluations<Fr>>::from_vec_and_domain(evals, domain);
-                let poly = eval.interpolate();
-                scheme.commit(&poly)
-            })
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap()
+            .clone()
+            .map(|col| col.iter().map(|cell| cell.1).collect())
     }
-}
-
-#[allow(dead_code)]
-fn print_matrix(matrix: &[usize], cols: usize) {
-    println!();
-    for row in matrix.chunks(cols) {
-        println!("{:?}", row);
+    pub fn sigma_polys(&self, domain: GeneralEvaluationDomain<F>) -> [Poly<F>; C] {
+        self.sigma_values()
+            .map(|evals| <Evaluations<F>>::from_vec_and_domain(evals, domain).interpolate())
     }
-}
-
-#[allow(dead_code)]
-fn print_cycle(elems: &[usize]) {
-    let mut seen = HashSet::new();
-    for elem in elems {
-        if seen.contains(elem) {
-            continue;
-        } else {
-            seen.insert(*elem);
-            let mut cycle = vec![*elem];
-            let mut next = elems[*elem];
-            loop {
-                if seen.contains(&next) {
-                    break;
-                }
-                seen.insert(next);
-                cycle.push(next);
-                next = elems[next];
-            }
-            println!("{:?}", cycle);
+    pub fn sigma_evals(&self, point: &F, domain: GeneralEvaluationDomain<F>) -> [F; C] {
+        self.sigma_polys(domain).map(|poly| poly.evaluate(point))
+    }
+    pub fn sigma_commitments<E: PairingEngine<Fr = F>>(
+        &self,
+        scheme: &KzgScheme<E>,
+        domain: GeneralEvaluationDomain<F>,
+    ) -> Result<[KzgCommitment<E>; C], KzgError> {
+        let mut commitments = [KzgCommitment(Default::default()); C];
+        for (commitment, poly) in commitments.iter_mut().zip(self.sigma_polys(domain)) {
+            *commitment = scheme.commit(&poly)?;
         }
+        Ok(commitments)
     }
 }
@@ -1,77 +1,57 @@
 use crate::CompiledPermutation;
-use ark_bls12_381::Fr;
-use ark_ff::One;
-use std::iter::Iterator;
+use ark_ff::{batch_inversion, PrimeField};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
-impl<const C: usize> CompiledPermutation<C> {
-    pub fn prove(&self, values: &[Vec<Fr>; C], beta: Fr, gamma: Fr) -> Vec<Fr> {
-        let cosets = self.cosets;
+impl<F: PrimeField, const C: usize> CompiledPermutation<F, C> {
+    /// evaluations of the accumulator, `Z(w^(j+1)) = Z(w^j)·Π(v + β·tag + γ)/Π(v + β·σ + γ)`
+    /// starting at 1. The denominators are inverted in a single batch, and with the `parallel`
+    /// feature the rows are computed in parallel
+    pub fn prove(&self, values: &[Vec<F>; C], beta: F, gamma: F) -> Vec<F> {
         // tag(origianl), value(copy permuted)
         let perms = &self.cols;
-        assert_eq!(cosets.len(), C);
-        let acc = ColIterator::new(values.clone(), perms.clone());
-        let acc = acc.scan(Fr::one(), |state, vals| {
-            let mut num = Fr::one();
-            let mut den = Fr::one();
-
-            let row_val = vals.into_iter().fold(Fr::one(), |state, val| {
+        let rows = values.iter().map(Vec::len).fold(self.rows, usize::min);
+        let row = |j: usize| {
+            let mut num = F::one();
+            let mut den = F::one();
+            for (col, perm) in values.iter().zip(perms.iter()) {
                 // cell_val -> w_(x)
-                let (cell_val, (tag, value)) = val;
-                let numerator = cell_val + beta * tag + gamma;
-                num *= numerator;
-                let denominator = cell_val + beta * value + gamma;
-                den *= denominator;
-                let result = numerator / denominator;
-                state * result
-            });
-
-            *state *= row_val;
-            Some(*state)
-        });
+                let (tag, value) = perm[j];
+                num *= col[j] + beta * tag + gamma;
+                den *= col[j] + beta * value + gamma;
+            }
+            (num, den)
+        };
+        #[cfg(feature = "parallel")]
+        let (nums, mut dens): (Vec<_>, Vec<_>) = (0..rows).into_par_iter().map(row).unzip();
+        #[cfg(not(feature = "parallel"))]
+        let (nums, mut dens): (Vec<_>, Vec<_>) = (0..rows).map(row).unzip();
+        batch_inversion(&mut dens);
 
-        let iter_one = std::iter::repeat(Fr::one()).take(1);
-        let acc = iter_one.clone().chain(acc).collect();
+        let mut acc = Vec::with_capacity(rows + 1);
+        acc.push(F::one());
+        for (num, den) in nums.into_iter().zip(dens) {
+            let last = acc[acc.len() - 1];
+            acc.push(last * num * den);
+        }
         acc
     }
+    /// logs the sigma values, or the tags of the cells when `val` is false, row by row at the
+    /// trace level
     pub fn print(&self, val: bool) {
-        let rows = self.rows;
-        let cols = &self.cols;
-        for j in 0..rows {
-            for i in 0..C {
-                if val {
-                    print!("{}", cols[i][j].1);
-                } else {
-                    print!("{}", cols[i][j].0);
-                }
-            }
-            println!("");
+        if !log::log_enabled!(log::Level::Trace) {
+            return;
+        }
+        for j in 0..self.rows {
+            let row = self
+                .cols
+                .iter()
+                .map(|col| match val {
+                    true => col[j].1.to_string(),
+                    false => col[j].0.to_string(),
+                })
+                .collect::<Vec<_>>();
+            log::trace!("{}", row.join(" "));
         }
-    }
-}
-
-struct ColIterator<const C: usize>(
-    [std::vec::IntoIter<Fr>; C],
-    [std::vec::IntoIter<(Fr, Fr)>; C],
-);
-impl<const C: usize> ColIterator<C> {
-    pub fn new(vals: [Vec<Fr>; C], perms: [Vec<(Fr, Fr)>; C]) -> Self {
-        let vals = vals.map(|evals| evals.into_iter());
-        let perms = perms.map(|evals| evals.into_iter());
-        Self(vals, perms)
-    }
-}
-impl<const C: usize> Iterator for ColIterator<C> {
-    type Item = Vec<(Fr, (Fr, Fr))>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let i1 = self.0.iter_mut();
-        let i2 = self.1.iter_mut();
-        i1.zip(i2)
-            .map(|(val, perm)| {
-                let val = val.next()?;
-                let perm = perm.next()?;
-                Some((val, perm))
-            })
-            .collect()
     }
 }
@@ -0,0 +1,48 @@
+//! Proving time of circuits from 2^10 to 2^18 rows, run with `cargo bench -p plonk`.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use plonk::{
+    description::{CircuitDescription, Var},
+    Bls12_381,
+};
+
+/// squares its input until it fills `2^LOG` rows
+struct Squares<const LOG: usize>;
+impl<const LOG: usize> CircuitDescription<1> for Squares<LOG> {
+    fn run<V: Var>(inputs: [V; 1]) {
+        let [mut x] = inputs;
+        // a row for each square, the rows must stay below the power of two
+        for _ in 0..(1 << LOG) - 1 {
+            x = x.clone() * x;
+        }
+    }
+}
+
+fn prove<const LOG: usize>(c: &mut Criterion) {
+    let mut group = c.benchmark_group("prove");
+    group.sample_size(10);
+    // compiled on first use, so that filtered out sizes are not compiled
+    let mut circuit = None;
+    group.bench_function(BenchmarkId::from_parameter(1 << LOG), |b| {
+        let circuit = circuit.get_or_insert_with(|| {
+            let circuit = Squares::<LOG>::build::<Bls12_381>().unwrap();
+            assert_eq!(circuit.rows, 1 << LOG);
+            circuit
+        });
+        b.iter(|| circuit.prove([3]).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    prove<10>,
+    prove<11>,
+    prove<12>,
+    prove<13>,
+    prove<14>,
+    prove<15>,
+    prove<16>,
+    prove<17>,
+    prove<18>
+);
+criterion_main!(benches);
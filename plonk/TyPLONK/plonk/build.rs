@@ -1,4 +0,0 @@
-fn main() {
-    // generates doc tests for `README.md`.
-    skeptic::generate_doc_tests(&["../README.md"]);
-}
@@ -1,93 +1,226 @@
 use crate::{
-    description::{CircuitDescription, Var},
-    CompiledCircuit, GateConstrains,
+    description::{Circuit, ConstraintSystem, Var},
+    gate::CustomGate,
+    lookup::Table,
+    CompiledCircuit, CosetEvals, CustomSelectors, GateConstrains, LookupTables, PlonkError,
 };
-use ark_bls12_381::Fr;
-use ark_ff::{One, Zero};
+use arena::push_row;
+use ark_ec::PairingEngine;
+use ark_ff::{One, PrimeField, Zero};
 use ark_poly::{EvaluationDomain, Evaluations, GeneralEvaluationDomain};
-use kgz::{srs::Srs, KzgScheme};
-use permutation::{PermutationBuilder, Tag};
+use kgz::{srs::Srs, KzgCommitment, KzgError, KzgScheme};
+use permutation::PermutationBuilder;
 use std::{
     collections::HashMap,
     iter::repeat,
-    marker::PhantomData,
-    ops::{Add, Mul},
-    rc::Rc,
+    ops::{Add, Mul, Neg, Sub},
     sync::{
         atomic::{AtomicUsize, Ordering},
-        Mutex,
+        Arc, Mutex, OnceLock,
     },
 };
-// #[cfg(test)]
-// mod test;
+mod arena;
+mod check;
+mod report;
+#[cfg(test)]
+mod test;
+
+pub(crate) use arena::with_arena;
+pub use check::{ConstraintFailure, ConstraintKind};
+pub use permutation::Tag;
+pub use report::{CircuitGraph, CircuitReport, EquivalenceClass, LARGEST_CLASSES};
 
+/// lays out the rows of a circuit with `W` wires, a, b, c and then d, e, … Every row has the
+/// same wires, a wider circuit packs more inputs in a row, see [`Var::linear_combination`]
 #[derive(Debug, Default)]
-pub struct CircuitBuilder {
-    gates: Vec<Gate>,
-    permutation: PermutationBuilder<3>,
+pub struct CircuitBuilder<F: PrimeField, const W: usize = 3> {
+    gates: Vec<Gate<F>>,
+    permutation: PermutationBuilder<W>,
+    /// rows of the `PublicInput` gates, in the order the variables were exposed
+    public_rows: Vec<usize>,
+    /// names of the registered custom gates, `Gate::Custom` refers to them by index
+    custom_gates: Vec<&'static str>,
+    /// the declared tables, `Gate::Lookup` refers to them by index
+    tables: Vec<Table<F>>,
 }
 
 #[derive(Debug, Clone)]
-enum Gate {
+enum Gate<F> {
     Mul,
     Add,
+    /// binds the value in column a to a public input, the row only has `q_l = 1`
+    PublicInput,
+    /// general row `q_l·a + q_r·b - q_o·c + q_m·ab + q_c = 0`, used for constants and linear combinations
+    Arith {
+        q_l: F,
+        q_r: F,
+        q_o: F,
+        q_m: F,
+        q_c: F,
+    },
+    /// `c = Σ k_i·w_i + q_c` over the wires but c, in the order a, b, d, e, …, a row of
+    /// `Var::linear_combination`
+    Linear {
+        coefficients: Vec<F>,
+        constant: F,
+    },
+    /// row of a custom gate, `output` sets `q_o = 1`
+    Custom {
+        gate: usize,
+        selectors: Vec<F>,
+        output: bool,
+    },
+    /// the value in column a is an entry of the table, all the arithmetic selectors are 0
+    Lookup {
+        table: usize,
+    },
+    /// holds a hint in column a, constrained only by the copies of it in other rows
+    Hint,
 
     // ensures that the circuit can be padded to the appropriate size, which is particularly important for FFT operations required in polynomial commitment schemes like PLONK
     Dummy,
 }
 
-impl CircuitBuilder {
+impl<F: PrimeField, const W: usize> CircuitBuilder<F, W> {
     ///adds a general gate and permutation row add 1
-    fn add_gate(&mut self, gate: Gate) -> usize {
+    fn add_gate(&mut self, gate: Gate<F>) -> usize {
         self.gates.push(gate);
         self.permutation.add_row();
-        let index = self.gates.len() - 1;
-        index
+        self.gates.len() - 1
     }
     /// ensure that the number of gates in the circuit is a power of two(for FFT) and sufficiently large
     fn fill(&mut self) {
         let rows = self.gates.len();
-        // the next power of two that is greater than or equal to row(gates) + 3
-        // repeat(()) creates an iterator that repeats an empty tuple indefinitely.
-        // .find(|size| *size >= rows + 3) finds the first value of old that is greater than or equal to rows + 3.
+        // the table column needs a row for each entry
+        let entries = self
+            .tables
+            .iter()
+            .map(|table| table.values().len())
+            .sum::<usize>();
+        // the smallest power of two above the rows with room for every table entry
         let size = repeat(())
             .scan(2_usize, |state, _| {
                 let old = *state;
                 *state = old * 2;
                 Some(old)
             })
-            .find(|size| *size >= rows + 3)
+            .find(|size| *size > rows && *size >= entries)
             .unwrap();
-        /* If the vector is smaller than the new size: The resize function will add elements to the vector until it reaches the new size. These new elements will be initialized with the value you specify (in this case, Gate::Dummy).
-         */
         self.gates.resize(size, Gate::Dummy);
     }
 
-    pub fn compile<const I: usize, C: CircuitDescription<I>>() -> CompiledCircuit<I, C> {
-        let circuit = C::run::<BuildVar>;
-        let context = Context::default();
-        let inputs = [(); I].map(|_| BuildVar::input(&context));
-        circuit(inputs);
-
-        let (gates, mut permutation) = context.finish();
+    /// compiles with a fresh random srs, the prover and verifier must share the compiled circuit
+    pub fn compile<E: PairingEngine<Fr = F>, C: Circuit>(
+        circuit: C,
+    ) -> Result<CompiledCircuit<E, C, W>, PlonkError> {
+        Self::compile_inner(circuit, None)
+    }
+    /// compiles with a universal srs, trimmed to the size of the circuit
+    pub fn compile_with_srs<E: PairingEngine<Fr = F>, C: Circuit>(
+        circuit: C,
+        srs: &Srs<E>,
+    ) -> Result<CompiledCircuit<E, C, W>, PlonkError> {
+        Self::compile_inner(circuit, Some(srs))
+    }
+    /// the rows the circuit takes before padding, its public inputs included
+    pub fn gates<C: Circuit>(circuit: &C) -> usize {
+        let layout = Self::synthesize(circuit);
+        let gates = layout
+            .gates
+            .iter()
+            .filter(|gate| !matches!(gate, Gate::Dummy));
+        gates.count()
+    }
+    /// runs the circuit with build variables and lays out its rows
+    fn synthesize<C: Circuit>(circuit: &C) -> Layout<F, W> {
+        let context = Context::<F, W>::default();
+        {
+            let builder = &mut context.inner.lock().unwrap().builder;
+            builder.custom_gates = circuit
+                .custom_gates::<F>()
+                .iter()
+                .map(|gate| gate.name())
+                .collect();
+            builder.tables = circuit.tables();
+        }
+        let inputs = (0..circuit.inputs())
+            .map(|_| BuildVar::input(&context))
+            .collect();
+        circuit.synthesize(&mut ConstraintSystem::new(inputs));
+        context.finish()
+    }
+    fn compile_inner<E: PairingEngine<Fr = F>, C: Circuit>(
+        circuit: C,
+        srs: Option<&Srs<E>>,
+    ) -> Result<CompiledCircuit<E, C, W>, PlonkError> {
+        assert!(W >= 3, "a circuit has at least the wires a, b and c");
+        let custom_gates = circuit.custom_gates();
+        let tables = circuit.tables();
+        let Layout {
+            gates,
+            mut permutation,
+            public_rows,
+            ..
+        } = Self::synthesize(&circuit);
 
         {
             // gates have been expanded in context.finish(), now rows are 2^n
             let rows = gates.len();
-            /* Fr is finite field such as bls12_381::Fr
-               domain is a structure that represents a set of points (which often includes roots of unity)(based on rows size) where polynomials will be evaluated or interpolated
-             */
-            let domain = <GeneralEvaluationDomain<Fr>>::new(rows).unwrap();
-            let srs = Srs::random(domain.size());
-            let mut polys = [(); 5].map(|_| <Vec<Fr>>::with_capacity(rows));
+            /* F is the scalar field of the curve, such as bls12_381::Fr
+              domain is a structure that represents a set of points (which often includes roots of unity)(based on rows size) where polynomials will be evaluated or interpolated
+            */
+            let domain = <GeneralEvaluationDomain<F>>::new(rows).unwrap();
+            let degree = gates_degree::<W>(custom_gates.iter().map(|gate| gate.degree()));
+            let slice_degree = slice_degree(degree, domain.size());
+            log::debug!(
+                "compiling {} rows of {} wires, quotient of degree {}",
+                rows,
+                W,
+                degree
+            );
+            let (srs, basis) = match srs {
+                Some(srs) => {
+                    let srs = srs.trim(slice_degree)?;
+                    let basis = srs.lagrange_basis(domain)?;
+                    (srs, basis)
+                }
+                // the basis is much faster to compute from the secret
+                None => Srs::random_with_basis(slice_degree, domain)?,
+            };
+            let mut polys = [(); 5].map(|_| <Vec<F>>::with_capacity(rows));
+            let mut wire_polys = vec![<Vec<F>>::with_capacity(rows); W - 3];
+            let mut queries = vec![];
+            let mut custom_polys = custom_gates
+                .iter()
+                .map(|gate| vec![<Vec<F>>::with_capacity(rows); gate.selectors()])
+                .collect::<Vec<_>>();
             // if the row is [1,1,1,0,0]
             // then after calculate polys will be like [[1], [1], [1], [0], [0]]
-            gates.into_iter().for_each(|gate| {
+            gates.into_iter().enumerate().for_each(|(j, gate)| {
                 let row = gate.to_row();
+                if let Gate::Lookup { table } = gate {
+                    queries.push((j, F::from(table as u64 + 1)));
+                }
+                // custom selectors are 0 outside of the rows of their gate
+                for (index, cols) in custom_polys.iter_mut().enumerate() {
+                    for (k, col) in cols.iter_mut().enumerate() {
+                        let value = match &gate {
+                            Gate::Custom {
+                                gate, selectors, ..
+                            } if *gate == index => selectors[k],
+                            _ => F::zero(),
+                        };
+                        col.push(value);
+                    }
+                }
                 // poly is initial [[q_L], [q_R], [q_O], [q_M], [q_C]]
                 polys
                     .iter_mut()
-                    .zip(row.into_iter())
+                    .zip(row)
+                    .for_each(|(col, value)| col.push(value));
+                wire_polys
+                    .iter_mut()
+                    .zip(gate.wire_selectors(W))
                     .for_each(|(col, value)| col.push(value));
             });
             // w_a, w_b, w_c
@@ -96,18 +229,45 @@ impl CircuitBuilder {
             let permutation = permutation.compile();
             let scheme = KzgScheme::new(&srs);
             // poly for each gate and evaluate it(commitment)
-            let polys = polys.map(|evals| {
-                let poly = Evaluations::from_vec_and_domain(evals, domain).interpolate();
-                let commitment = scheme.commit(&poly);
-                (poly, commitment)
-            });
+            let polys =
+                polys.map(|evals| Evaluations::from_vec_and_domain(evals, domain).interpolate());
             let commitments = polys
                 .iter()
-                .map(|(_, commitment)| commitment.clone())
-                .collect::<Vec<_>>();
-            let polys = polys.map(|(poly, _)| poly);
+                .map(|poly| scheme.commit(poly))
+                .collect::<Result<Vec<_>, _>>()?;
 
             let [q_l, q_r, q_o, q_m, q_c] = polys;
+            let wire_selectors = wire_polys
+                .into_iter()
+                .map(|evals| Evaluations::from_vec_and_domain(evals, domain).interpolate())
+                .collect::<Vec<_>>();
+            let wire_commitments = wire_selectors
+                .iter()
+                .map(|poly| scheme.commit(poly))
+                .collect::<Result<_, _>>()?;
+            let custom = custom_gates
+                .into_iter()
+                .zip(custom_polys)
+                .map(|(gate, cols)| {
+                    let polys = cols
+                        .into_iter()
+                        .map(|evals| Evaluations::from_vec_and_domain(evals, domain).interpolate())
+                        .collect::<Vec<_>>();
+                    let commitments = polys
+                        .iter()
+                        .map(|poly| scheme.commit(poly))
+                        .collect::<Result<_, _>>()?;
+                    Ok(CustomSelectors {
+                        gate,
+                        polys,
+                        commitments,
+                    })
+                })
+                .collect::<Result<_, PlonkError>>()?;
+            let lookup = match tables.is_empty() {
+                true => None,
+                false => Some(lookup_tables(&tables, queries, domain, &scheme)?),
+            };
             let gate_constrains = GateConstrains {
                 q_l,
                 q_r,
@@ -115,26 +275,119 @@ impl CircuitBuilder {
                 q_m,
                 q_c,
                 fixed_commitments: commitments.try_into().unwrap(),
+                wire_selectors,
+                wire_commitments,
+                custom,
             };
-            CompiledCircuit {
+            let sigmas = permutation.sigma_polys(domain);
+            let sigma_commitments = permutation.sigma_commitments(&scheme, domain)?;
+            let coset = CosetEvals::new(
+                &gate_constrains,
+                &sigmas,
+                lookup.as_ref(),
+                domain,
+                slice_degree,
+            );
+            Ok(CompiledCircuit {
                 gate_constrains,
                 copy_constrains: permutation,
                 srs,
                 domain,
-                circuit_definition: PhantomData,
+                circuit,
                 rows,
-            }
+                public_rows,
+                slice_degree,
+                lookup,
+                sigmas,
+                sigma_commitments,
+                coset,
+                basis,
+                verifying_key: OnceLock::new(),
+            })
         }
     }
 }
 
-#[derive(Hash, Debug, Clone, Copy, PartialEq, Eq)]
+/// the highest degree of the gates, the permutation multiplies the accumulator by a term of each
+/// wire
+pub(crate) fn gates_degree<const W: usize>(custom: impl IntoIterator<Item = usize>) -> usize {
+    custom.into_iter().fold(W, usize::max)
+}
+/// the size of each of the 3 slices of the quotient. The blinded wires and accumulators have
+/// degree n + 2 at most, so the quotient has degree `degree·n + 2·degree + 2` at most
+pub(crate) fn slice_degree(degree: usize, n: usize) -> usize {
+    (degree * n + 2 * degree + 3).div_ceil(3)
+}
+/// interpolates the selectors of the lookup rows and the column of all the tables
+fn lookup_tables<E: PairingEngine>(
+    tables: &[Table<E::Fr>],
+    queries: Vec<(usize, E::Fr)>,
+    domain: GeneralEvaluationDomain<E::Fr>,
+    scheme: &KzgScheme<E>,
+) -> Result<LookupTables<E>, KzgError> {
+    let rows = domain.size();
+    let mut q_lookup = vec![E::Fr::zero(); rows];
+    let mut q_table = vec![E::Fr::zero(); rows];
+    for (row, id) in queries.iter() {
+        q_lookup[*row] = E::Fr::one();
+        q_table[*row] = *id;
+    }
+    let mut entries = tables
+        .iter()
+        .enumerate()
+        .flat_map(|(index, table)| {
+            let id = E::Fr::from(index as u64 + 1);
+            table.values().iter().map(move |value| (*value, id))
+        })
+        .collect::<Vec<_>>();
+    let last = *entries.last().expect("tables are not empty");
+    entries.resize(rows, last);
+    let values = entries.iter().map(|entry| entry.0).collect();
+    let ids = entries.iter().map(|entry| entry.1).collect();
+    let [q_lookup, q_table, values, ids] = [q_lookup, q_table, values, ids]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain).interpolate());
+    let mut commitments = [KzgCommitment(Default::default()); 4];
+    for (commitment, poly) in commitments
+        .iter_mut()
+        .zip([&q_lookup, &q_table, &values, &ids])
+    {
+        *commitment = scheme.commit(poly)?;
+    }
+    Ok(LookupTables {
+        q_lookup,
+        q_table,
+        values,
+        ids,
+        commitments,
+        queries,
+        entries,
+    })
+}
+
+#[derive(Hash, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct VarId(usize);
 
+impl VarId {
+    /// variables are numbered in the order they are created, the inputs first
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// the rows of a circuit before they are compiled into polynomials
+struct Layout<F: PrimeField, const W: usize> {
+    /// padded to a power of two
+    gates: Vec<Gate<F>>,
+    permutation: PermutationBuilder<W>,
+    public_rows: Vec<usize>,
+    /// the variable of the description each cell holds
+    cells: HashMap<Tag, VarId>,
+}
+
 #[derive(Default, Debug)]
-pub struct InnerContext {
+pub struct InnerContext<F: PrimeField, const W: usize = 3> {
     /// responsible for constructing the gates and permutations that define the circuit.
-    builder: CircuitBuilder,
+    builder: CircuitBuilder<F, W>,
     /// generates unique IDs for variables. Each new variable gets a unique VarId.
     next_var_id: AtomicUsize,
     /// A list of pending equality constraints. These are pairs of variables (VarId) that should be equal but whose exact positions in the circuit are not yet known.
@@ -142,15 +395,17 @@ pub struct InnerContext {
     /// managing the mapping between variables and their positions within the arithmetic circuit.
     /// The key is the variable ID, The Tag represents the position (i.e., the row(j) and column(i)) of the variable in the circuit.
     var_map: HashMap<VarId, Tag>,
+    /// the variable each cell holds, a cell holding a copy of a variable maps to the variable
+    cells: HashMap<Tag, VarId>,
 }
 
 #[derive(Clone, Default, Debug)]
-pub struct Context {
-    inner: Rc<Mutex<InnerContext>>,
+pub struct Context<F: PrimeField, const W: usize = 3> {
+    inner: Arc<Mutex<InnerContext<F, W>>>,
 }
 
 /// Implementation of the `Context` struct.
-impl Context {
+impl<F: PrimeField, const W: usize> Context<F, W> {
     /// Generates a new variable ID. By continueously add 1
     fn new_id(&self) -> VarId {
         let id = self
@@ -163,14 +418,16 @@ impl Context {
     }
 
     /// Adds a gate to the builder and returns its index.
-    fn add_gate(&self, gate: Gate) -> usize {
+    fn add_gate(&self, gate: Gate<F>) -> usize {
         let builder = &mut self.inner.lock().unwrap().builder;
         builder.add_gate(gate)
     }
 
     /// Adds a variable with the given ID and tag to the variable map.
     fn add_var(&self, id: VarId, tag: Tag) {
-        self.inner.lock().unwrap().var_map.insert(id, tag);
+        let inner = &mut self.inner.lock().unwrap();
+        inner.var_map.insert(id, tag);
+        inner.cells.insert(tag, id);
     }
 
     /// Retrieves the tag associated with the given variable ID.
@@ -181,17 +438,17 @@ impl Context {
     /// Adds an equality constraint between two variables.
     /// This method checks if both variables have known positions by querying var_map. If both positions are known, the constraint is added to the permutation builder. If not, the constraint is added to pending_eq to be resolved later.
     fn add_eq(&self, left: VarId, right: VarId) {
-        println!("new eq: {} = {}", &left.0, &right.0);
+        log::trace!("new eq: {} = {}", &left.0, &right.0);
         let [a, b] = [left, right].map(|id| self.get_var(&id));
         let context = &mut self.inner.lock().unwrap();
         match a.zip(b) {
             Some((left, right)) => {
-                println!("tags: {:?} = {:?}", &left, &right);
+                log::trace!("tags: {:?} = {:?}", &left, &right);
                 context
                     .builder
                     .permutation
                     .add_constrain(left, right)
-                    .unwrap();
+                    .expect("variables are placed in the rows of the circuit");
             }
             None => {
                 context.pending_eq.push((left, right));
@@ -199,8 +456,42 @@ impl Context {
         }
     }
 
-    /// Finishes the construction of the context and returns the gates and permutation builder.
-    fn finish(self) -> (Vec<Gate>, PermutationBuilder<3>) {
+    /// Adds a public input gate and returns its index.
+    fn add_public_input(&self) -> usize {
+        let builder = &mut self.inner.lock().unwrap().builder;
+        let row = builder.add_gate(Gate::PublicInput);
+        builder.public_rows.push(row);
+        row
+    }
+
+    /// Adds a row of the custom gate `name` and returns its index.
+    fn add_custom_gate(&self, name: &str, selectors: &[F], output: bool) -> usize {
+        let builder = &mut self.inner.lock().unwrap().builder;
+        let gate = builder
+            .custom_gates
+            .iter()
+            .position(|registered| *registered == name)
+            .unwrap_or_else(|| panic!("custom gate {} is not registered", name));
+        builder.add_gate(Gate::Custom {
+            gate,
+            selectors: selectors.to_vec(),
+            output,
+        })
+    }
+
+    /// Adds a lookup row in the table `name` and returns its index.
+    fn add_lookup(&self, name: &str) -> usize {
+        let builder = &mut self.inner.lock().unwrap().builder;
+        let table = builder
+            .tables
+            .iter()
+            .position(|table| table.name() == name)
+            .unwrap_or_else(|| panic!("table {} is not declared", name));
+        builder.add_gate(Gate::Lookup { table })
+    }
+
+    /// Finishes the construction of the context and returns the layout of the circuit.
+    fn finish(self) -> Layout<F, W> {
         let pending_eq = {
             let mut inner = self.inner.lock().unwrap();
             std::mem::take(&mut inner.pending_eq)
@@ -208,48 +499,49 @@ impl Context {
         for (left, right) in pending_eq {
             self.add_eq(left, right);
         }
-        let mut inner = Rc::try_unwrap(self.inner).unwrap().into_inner().unwrap();
+        let mut inner = Arc::try_unwrap(self.inner).unwrap().into_inner().unwrap();
         assert!(inner.pending_eq.is_empty());
         inner.builder.fill();
         let InnerContext {
-            builder: CircuitBuilder {
-                gates, permutation, ..
-            },
+            builder:
+                CircuitBuilder {
+                    gates,
+                    permutation,
+                    public_rows,
+                    ..
+                },
+            cells,
             ..
         } = inner;
-        (gates, permutation)
+        Layout {
+            gates,
+            permutation,
+            public_rows,
+            cells,
+        }
     }
 }
 
 #[derive(Clone)]
-pub enum Variable {
-    Build {
-        context: Context,
-        id: VarId,
-    },
-    Compute {
-        value: Fr,
-        advice_values: Rc<Mutex<[Vec<Fr>; 3]>>,
-    },
+pub enum Variable<F: PrimeField> {
+    Build { context: Context<F>, id: VarId },
+    Compute { value: F },
 }
 
-impl Variable {
-    pub fn assert_eq(&self, other: &Variable) {
-        match self {
-            Variable::Build { context, id } => {
-                let left = id;
-                match other {
-                    Variable::Build { id, .. } => {
-                        context.add_eq(*left, *id);
-                    }
-                    _ => unreachable!(),
+impl<F: PrimeField> Variable<F> {
+    pub fn assert_eq(&self, other: &Variable<F>) {
+        if let Variable::Build { context, id } = self {
+            let left = id;
+            match other {
+                Variable::Build { id, .. } => {
+                    context.add_eq(*left, *id);
                 }
+                _ => unreachable!(),
             }
-            _ => {}
         };
     }
 
-    fn binary_operation(self, right: Variable, operation: GateOperation) -> Variable {
+    fn binary_operation(self, right: Variable<F>, operation: GateOperation) -> Variable<F> {
         match self {
             Variable::Build { context, id } => {
                 let right_id = match right {
@@ -286,106 +578,248 @@ impl Variable {
                     context: context.clone(),
                 }
             }
-            Variable::Compute {
-                value,
-                advice_values,
-            } => {
+            Variable::Compute { value } => {
                 let left = value;
                 let right = match right {
-                    Variable::Compute { value, .. } => value,
+                    Variable::Compute { value } => value,
                     _ => unreachable!(),
                 };
                 let value = operation.compute(left, right);
-                {
-                    let mut advice = advice_values.lock().unwrap();
-                    advice
-                        .iter_mut()
-                        .zip([left, right, value].into_iter())
-                        .for_each(|(col, value)| col.push(value));
-                }
-                Variable::Compute {
-                    value,
-                    advice_values,
-                }
+                push_row::<F, 3>(&[left, right, value]);
+                Variable::Compute { value }
             }
         }
     }
 }
 
+#[derive(Clone, Copy)]
 enum GateOperation {
     Sum,
+    Sub,
     Mul,
 }
 
 impl GateOperation {
-    fn compute(self, a: Fr, b: Fr) -> Fr {
+    fn compute<F: PrimeField>(self, a: F, b: F) -> F {
         let d = match self {
             GateOperation::Sum => a + b,
+            GateOperation::Sub => a - b,
             GateOperation::Mul => a * b,
         };
-        println!("compute result:{}", d);
+        log::trace!("compute result: {}", d);
         d
     }
-    fn build(self) -> Gate {
+    fn build<F: PrimeField>(self) -> Gate<F> {
         match self {
             GateOperation::Sum => Gate::Add,
             GateOperation::Mul => Gate::Mul,
+            GateOperation::Sub => Gate::Arith {
+                q_l: F::one(),
+                q_r: -F::one(),
+                q_o: F::one(),
+                q_m: F::zero(),
+                q_c: F::zero(),
+            },
+        }
+    }
+    /// `[q_l, q_r, q_m, q_c]` of the operation written as `c = q_l·a + q_r·b + q_m·ab + q_c`
+    fn selectors<F: PrimeField>(self) -> [F; 4] {
+        let (one, zero) = (F::one(), F::zero());
+        match self {
+            GateOperation::Sum => [one, one, zero, zero],
+            GateOperation::Sub => [one, -one, zero, zero],
+            GateOperation::Mul => [zero, zero, one, zero],
+        }
+    }
+    /// gate for the operation where one side is the constant `k`, the wire always goes in column a.
+    /// substituting b = k gives `c = (q_l + q_m·k)·a + q_r·k + q_c`, substituting a = k gives `c = (q_r + q_m·k)·b + q_l·k + q_c`
+    fn build_with_constant<F: PrimeField>(self, k: F, constant_left: bool) -> Gate<F> {
+        let [q_l, q_r, q_m, q_c] = self.selectors::<F>();
+        let (wire, constant) = match constant_left {
+            false => (q_l, q_r),
+            true => (q_r, q_l),
+        };
+        Gate::Arith {
+            q_l: wire + q_m * k,
+            q_r: F::zero(),
+            q_o: F::one(),
+            q_m: F::zero(),
+            q_c: constant * k + q_c,
         }
     }
 }
 
-impl Add for Variable {
+impl<F: PrimeField> Add for Variable<F> {
     type Output = Self;
     fn add(self, rhs: Self) -> Self::Output {
         self.binary_operation(rhs, GateOperation::Sum)
     }
 }
-impl Mul for Variable {
+impl<F: PrimeField> Mul for Variable<F> {
     type Output = Self;
     fn mul(self, rhs: Self) -> Self::Output {
         self.binary_operation(rhs, GateOperation::Mul)
     }
 }
 
-impl Gate {
-    /// init gate to row, Mul -> `[0(ql), 0(qr), 1(qo), 1(qm), 0(qc)]`, Add -> `[1(ql), 1(qr), 1(qo), 0(qm), 0(qc)]`, Dummy -> `[0, 0, 0, 0, 0]`
-    fn to_row(self) -> [Fr; 5] {
-        match self {
-            Gate::Mul => [Fr::zero(), Fr::zero(), Fr::one(), Fr::one(), Fr::zero()],
-            Gate::Add => [Fr::one(), Fr::one(), Fr::one(), Fr::zero(), Fr::zero()],
-            Gate::Dummy => [Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero()],
+impl<F: PrimeField> Gate<F> {
+    /// init gate to row, Mul -> `[0(ql), 0(qr), 1(qo), 1(qm), 0(qc)]`, Add -> `[1(ql), 1(qr), 1(qo), 0(qm), 0(qc)]`, PublicInput -> `[1, 0, 0, 0, 0]`, Dummy -> `[0, 0, 0, 0, 0]`
+    fn to_row(&self) -> [F; 5] {
+        match *self {
+            Gate::Mul => [F::zero(), F::zero(), F::one(), F::one(), F::zero()],
+            Gate::Add => [F::one(), F::one(), F::one(), F::zero(), F::zero()],
+            Gate::PublicInput => [F::one(), F::zero(), F::zero(), F::zero(), F::zero()],
+            Gate::Arith {
+                q_l,
+                q_r,
+                q_o,
+                q_m,
+                q_c,
+            } => [q_l, q_r, q_o, q_m, q_c],
+            Gate::Custom { output, .. } => {
+                let q_o = if output { F::one() } else { F::zero() };
+                [F::zero(), F::zero(), q_o, F::zero(), F::zero()]
+            }
+            Gate::Linear {
+                ref coefficients,
+                constant,
+            } => {
+                let coefficient = |i: usize| coefficients.get(i).copied().unwrap_or_else(F::zero);
+                [
+                    coefficient(0),
+                    coefficient(1),
+                    F::one(),
+                    F::zero(),
+                    constant,
+                ]
+            }
+            Gate::Lookup { .. } | Gate::Hint => {
+                [F::zero(), F::zero(), F::zero(), F::zero(), F::zero()]
+            }
+            Gate::Dummy => [F::zero(), F::zero(), F::zero(), F::zero(), F::zero()],
         }
     }
+    /// the selectors of the wires after c in a row of `wires` wires, only linear combinations
+    /// use them
+    fn wire_selectors(&self, wires: usize) -> Vec<F> {
+        let mut selectors = match self {
+            Gate::Linear { coefficients, .. } => coefficients.iter().skip(2).copied().collect(),
+            _ => vec![],
+        };
+        selectors.resize(wires - 3, F::zero());
+        selectors
+    }
+}
+
+/// the column of the i-th input of a linear combination row, c holds the output
+fn input_column(i: usize) -> usize {
+    match i {
+        0 | 1 => i,
+        _ => i + 1,
+    }
+}
+
+/// splits the terms of a linear combination in the rows of a circuit with `W` wires: the first
+/// row takes `W - 1` terms and the next ones `W - 2`, their first input being the output of the
+/// row before
+fn linear_rows<T, const W: usize>(terms: Vec<T>) -> Vec<Vec<T>> {
+    let mut terms = terms.into_iter().peekable();
+    let mut rows = vec![];
+    while terms.peek().is_some() {
+        let take = if rows.is_empty() { W - 1 } else { W - 2 };
+        rows.push(terms.by_ref().take(take).collect());
+    }
+    rows
 }
 
 #[derive(Clone)]
-pub(crate) struct BuildVar {
-    context: Context,
-    /// unique ID for the variable, suppose input variables are [a, b, c], then a.id = 0, b.id = 1, c.id = 2
-    id: VarId,
+pub(crate) enum BuildVar<F: PrimeField, const W: usize = 3> {
+    Wire {
+        context: Context<F, W>,
+        /// unique ID for the variable, suppose input variables are [a, b, c], then a.id = 0, b.id = 1, c.id = 2
+        id: VarId,
+    },
+    /// known when building the circuit, it never gets a wire and is folded into the `q_c` of the gates using it
+    Constant(F),
 }
 #[derive(Clone)]
-pub(crate) struct ComputeVar {
-    pub(crate) value: Fr,
-    /// [self, right, result] value
-    pub(crate) advice_values: Rc<Mutex<[Vec<Fr>; 3]>>,
+pub(crate) enum ComputeVar<F, const W: usize = 3> {
+    /// its rows go to the arena of the proof running the description
+    Wire { value: F },
+    /// mirrors `BuildVar::Constant`, it does not take a row of its own
+    Constant(F),
 }
 
-impl BuildVar {
+impl<F: PrimeField, const W: usize> Context<F, W> {
+    /// places variable `id` in the cell `tag`, if it already has a cell the two cells are constrained to be equal
+    fn place(&self, id: VarId, tag: Tag) {
+        match self.get_var(&id) {
+            Some(_) => {
+                let new_id = self.new_id();
+                self.add_var(new_id, tag);
+                self.add_eq(id, new_id);
+                self.inner.lock().unwrap().cells.insert(tag, id);
+            }
+            None => {
+                self.add_var(id, tag);
+            }
+        };
+    }
+}
+
+/// the output of a custom gate row whose inputs are all constants, the row is then folded into a
+/// constant like the operations on constants. Only rows with an output and at most 2 inputs fold
+fn fold_custom_gate<F: PrimeField, G: CustomGate<F>>(
+    gate: &G,
+    selectors: &[F],
+    constants: Option<Vec<F>>,
+    output: bool,
+) -> Option<F> {
+    match (output, &constants?[..]) {
+        (true, [a]) => Some(gate.output(selectors, *a, F::zero())),
+        (true, [a, b]) => Some(gate.output(selectors, *a, *b)),
+        _ => None,
+    }
+}
+
+/// a custom row with an output reads at most a and b, one without fills at most the W wires
+fn check_custom_inputs<const W: usize>(inputs: usize, output: bool) {
+    match output {
+        true => assert!(
+            inputs <= 2,
+            "the inputs of a custom gate go in columns a and b"
+        ),
+        false => assert!(inputs <= W, "a custom constraint reads at most {} wires", W),
+    }
+}
+
+impl<F: PrimeField, const W: usize> BuildVar<F, W> {
     fn binary_operation(&self, rhs: &Self, operation: GateOperation) -> Self {
-        let Self { context, id } = &self;
-        let right_id = &rhs.id;
+        let (context, id, right_id) = match (self, rhs) {
+            (Self::Wire { context, id }, Self::Wire { id: right_id, .. }) => {
+                (context, id, right_id)
+            }
+            (Self::Wire { context, id }, Self::Constant(k)) => {
+                return Self::unary_operation(context, id, operation.build_with_constant(*k, false))
+            }
+            (Self::Constant(k), Self::Wire { context, id }) => {
+                return Self::unary_operation(context, id, operation.build_with_constant(*k, true))
+            }
+            (Self::Constant(a), Self::Constant(b)) => {
+                return Self::Constant(operation.compute(*a, *b))
+            }
+        };
         // This line creates an array of tuples, where each tuple contains a variable ID and its respective column index in the circuit matrix.
         let ids = [(id, 0_usize), (right_id, 1)];
 
         // j is new added gate index also row index
         let j = context.add_gate(operation.build());
         /* For a binary operation (like addition or multiplication), there are usually two input wires.
-           The result of the gate operation is assigned to an output wire.
-           Column 0 (i: 0): First input.
-           Column 1 (i: 1): Second input.
-           Column 2 (i: 2): Output.
-         */
+          The result of the gate operation is assigned to an output wire.
+          Column 0 (i: 0): First input.
+          Column 1 (i: 1): Second input.
+          Column 2 (i: 2): Output.
+        */
         let output_tag = Tag { i: 2, j };
         // output id
         let id = context.new_id();
@@ -394,63 +828,149 @@ impl BuildVar {
         }
         // iter i(column): 0 for the first input and 1 for the second input
         ids.into_iter()
-            .map(|(id, i)| (id, context.clone().get_var(&id), i))
-            .for_each(|(id, tag, i)| {
-                let context = context.clone();
-                match tag {
-                    Some(_) => {
-                        let new_id = context.new_id();
-                        context.add_var(new_id, Tag { i, j });
-                        context.add_eq(*id, new_id);
-                    }
-                    None => {
-                        context.add_var(*id, Tag { i, j });
-                    }
-                };
-            });
+            .for_each(|(id, i)| context.place(*id, Tag { i, j }));
         // output variable(BuildVar)
-        Self {
+        Self::Wire {
             id,
             context: context.clone(),
         }
     }
 
-    fn input(context: &Context) -> Self {
+    /// adds a gate that only reads column a, column b is left unconstrained
+    fn unary_operation(context: &Context<F, W>, id: &VarId, gate: Gate<F>) -> Self {
+        let j = context.add_gate(gate);
+        let output = context.new_id();
+        context.add_var(output, Tag { i: 2, j });
+        context.place(*id, Tag { i: 0, j });
+        Self::Wire {
+            id: output,
+            context: context.clone(),
+        }
+    }
+
+    /// adds a custom gate row with the wires of `inputs` in the first columns, returns the output in column c
+    fn custom_row<G: CustomGate<F>>(
+        gate: &G,
+        selectors: &[F],
+        inputs: &[&Self],
+        output: bool,
+    ) -> Option<Self> {
+        assert_eq!(selectors.len(), gate.selectors());
+        check_custom_inputs::<W>(inputs.len(), output);
+        let constants = inputs.iter().map(|input| match input {
+            Self::Constant(value) => Some(*value),
+            Self::Wire { .. } => None,
+        });
+        if let Some(value) = fold_custom_gate(gate, selectors, constants.collect(), output) {
+            return Some(Self::Constant(value));
+        }
+        let wires = inputs
+            .iter()
+            .map(|input| match input {
+                Self::Wire { context, id } => (context, *id),
+                Self::Constant(_) => panic!("the inputs of a custom gate must be wires"),
+            })
+            .collect::<Vec<_>>();
+        let context = wires.first().expect("at least 1 input").0;
+        let j = context.add_custom_gate(gate.name(), selectors, output);
+        for (i, (_, id)) in wires.iter().enumerate() {
+            context.place(*id, Tag { i, j });
+        }
+        output.then(|| {
+            let id = context.new_id();
+            context.add_var(id, Tag { i: 2, j });
+            Self::Wire {
+                id,
+                context: context.clone(),
+            }
+        })
+    }
+
+    /// places the variable in column a of a new public input row
+    fn expose(&self) {
+        match self {
+            Self::Wire { context, id } => {
+                let j = context.add_public_input();
+                context.place(*id, Tag { i: 0, j });
+            }
+            Self::Constant(_) => panic!("a constant can not be exposed as a public input"),
+        }
+    }
+
+    fn input(context: &Context<F, W>) -> Self {
         let id = context.new_id();
-        Self {
+        Self::Wire {
             id,
             context: context.clone(),
         }
     }
 }
 
-impl ComputeVar {
+impl<F: PrimeField, const W: usize> ComputeVar<F, W> {
+    fn value(&self) -> F {
+        match self {
+            Self::Wire { value } | Self::Constant(value) => *value,
+        }
+    }
+
     fn binary_operation(&self, rhs: &Self, operation: GateOperation) -> Self {
-        let left = self.value;
-        let right = rhs.value;
+        let (left, right) = match (self, rhs) {
+            (Self::Constant(a), Self::Constant(b)) => {
+                return Self::Constant(operation.compute(*a, *b))
+            }
+            (Self::Wire { value }, Self::Constant(k)) => (*value, *k),
+            (Self::Constant(k), Self::Wire { value }) => (*k, *value),
+            (Self::Wire { value }, Self::Wire { value: right }) => (*value, *right),
+        };
         let value = operation.compute(left, right);
-        {
-            let mut advice = self.advice_values.lock().unwrap();
-            advice
-                .iter_mut()
-                .zip([left, right, value].into_iter())
-                .for_each(|(col, value)| col.push(value));
+        // same layout as the builder: constants have no cell and the wire goes in column a
+        let row = match (self, rhs) {
+            (Self::Wire { .. }, Self::Wire { .. }) => [left, right, value],
+            (Self::Wire { .. }, _) => [left, F::zero(), value],
+            _ => [right, F::zero(), value],
+        };
+        push_row::<F, W>(&row);
+        Self::Wire { value }
+    }
+
+    fn custom_row<G: CustomGate<F>>(
+        gate: &G,
+        selectors: &[F],
+        inputs: &[&Self],
+        output: bool,
+    ) -> Option<Self> {
+        assert!(!inputs.is_empty(), "at least 1 input");
+        check_custom_inputs::<W>(inputs.len(), output);
+        let constants = inputs.iter().map(|input| match input {
+            Self::Constant(value) => Some(*value),
+            Self::Wire { .. } => None,
+        });
+        if let Some(value) = fold_custom_gate(gate, selectors, constants.collect(), output) {
+            return Some(Self::Constant(value));
+        }
+        let mut row = vec![F::zero(); W];
+        for (cell, input) in row.iter_mut().zip(inputs) {
+            match input {
+                Self::Wire { value } => *cell = *value,
+                Self::Constant(_) => panic!("the inputs of a custom gate must be wires"),
+            }
         }
-        Self {
-            value,
-            advice_values: self.advice_values.clone(),
+        if output {
+            row[2] = gate.output(selectors, row[0], row[1]);
         }
+        push_row::<F, W>(&row);
+        output.then_some(Self::Wire { value: row[2] })
     }
 }
 
-impl Add for BuildVar {
+impl<F: PrimeField, const W: usize> Add for BuildVar<F, W> {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
         self.binary_operation(&rhs, GateOperation::Sum)
     }
 }
-impl Add for ComputeVar {
+impl<F: PrimeField, const W: usize> Add for ComputeVar<F, W> {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
@@ -458,44 +978,274 @@ impl Add for ComputeVar {
     }
 }
 
-impl Mul for BuildVar {
+impl<F: PrimeField, const W: usize> Sub for BuildVar<F, W> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.binary_operation(&rhs, GateOperation::Sub)
+    }
+}
+impl<F: PrimeField, const W: usize> Sub for ComputeVar<F, W> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.binary_operation(&rhs, GateOperation::Sub)
+    }
+}
+
+impl<F: PrimeField, const W: usize> Mul for BuildVar<F, W> {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
         self.binary_operation(&rhs, GateOperation::Mul)
     }
 }
-impl Mul for ComputeVar {
+impl<F: PrimeField, const W: usize> Mul for ComputeVar<F, W> {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
         self.binary_operation(&rhs, GateOperation::Mul)
     }
 }
-impl Var for BuildVar {
+
+/// scaling by a constant is a single `q_l = k` row
+impl<F: PrimeField, const W: usize> Mul<F> for BuildVar<F, W> {
+    type Output = Self;
+
+    fn mul(self, rhs: F) -> Self::Output {
+        self.binary_operation(&Self::Constant(rhs), GateOperation::Mul)
+    }
+}
+impl<F: PrimeField, const W: usize> Mul<F> for ComputeVar<F, W> {
+    type Output = Self;
+
+    fn mul(self, rhs: F) -> Self::Output {
+        self.binary_operation(&Self::Constant(rhs), GateOperation::Mul)
+    }
+}
+
+impl<F: PrimeField, const W: usize> Neg for BuildVar<F, W> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self * -F::one()
+    }
+}
+impl<F: PrimeField, const W: usize> Neg for ComputeVar<F, W> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self * -F::one()
+    }
+}
+
+impl<F: PrimeField, const W: usize> Var for BuildVar<F, W> {
+    type Field = F;
+
     fn assert_eq(&self, other: &Self) {
-        let Self { context, id } = self;
-        let left = id;
-        context.add_eq(*left, other.id);
+        match (self, other) {
+            (Self::Wire { context, id }, Self::Wire { id: other, .. }) => {
+                context.add_eq(*id, *other);
+            }
+            // a - k = 0
+            (Self::Wire { context, id }, Self::Constant(k))
+            | (Self::Constant(k), Self::Wire { context, id }) => {
+                let gate = Gate::Arith {
+                    q_l: F::one(),
+                    q_r: F::zero(),
+                    q_o: F::zero(),
+                    q_m: F::zero(),
+                    q_c: -*k,
+                };
+                let j = context.add_gate(gate);
+                context.place(*id, Tag { i: 0, j });
+            }
+            (Self::Constant(left), Self::Constant(right)) => {
+                assert_eq!(left, right, "the circuit asserts two different constants");
+            }
+        }
+    }
+    fn expose(&self) {
+        BuildVar::expose(self)
+    }
+    fn lookup(&self, table: &Table<F>) {
+        match self {
+            Self::Wire { context, id } => {
+                let j = context.add_lookup(table.name());
+                context.place(*id, Tag { i: 0, j });
+            }
+            Self::Constant(_) => panic!("a constant can not be looked up"),
+        }
+    }
+    fn constant(value: impl Into<F>) -> Self {
+        Self::Constant(value.into())
+    }
+    fn custom_gate<G: CustomGate<F>>(gate: &G, selectors: &[F], inputs: &[&Self]) -> Self {
+        Self::custom_row(gate, selectors, inputs, true).unwrap()
+    }
+    fn custom_constraint<G: CustomGate<F>>(gate: &G, selectors: &[F], inputs: &[&Self]) {
+        Self::custom_row(gate, selectors, inputs, false);
+    }
+    /// the hint gets its own row, so it has a cell even when it is only asserted equal to other
+    /// variables
+    fn hint(inputs: &[&Self], f: impl FnOnce(&[F]) -> F) -> Self {
+        let context = inputs.iter().find_map(|input| match input {
+            Self::Wire { context, .. } => Some(context),
+            Self::Constant(_) => None,
+        });
+        match context {
+            Some(context) => {
+                let id = context.new_id();
+                let j = context.add_gate(Gate::Hint);
+                context.place(id, Tag { i: 0, j });
+                Self::Wire {
+                    id,
+                    context: context.clone(),
+                }
+            }
+            None => {
+                let values = inputs
+                    .iter()
+                    .map(|input| match input {
+                        Self::Constant(value) => *value,
+                        Self::Wire { .. } => unreachable!(),
+                    })
+                    .collect::<Vec<_>>();
+                Self::Constant(f(&values))
+            }
+        }
+    }
+    fn linear_combination(terms: &[(F, &Self)]) -> Self {
+        let mut constant = F::zero();
+        let mut wires = vec![];
+        let mut context = None;
+        for (k, term) in terms {
+            match term {
+                Self::Constant(value) => constant += *k * value,
+                Self::Wire { context: wire, id } => {
+                    context = Some(wire);
+                    wires.push((*k, *id));
+                }
+            }
+        }
+        let Some(context) = context else {
+            return Self::Constant(constant);
+        };
+        let mut output: Option<VarId> = None;
+        for row in linear_rows::<_, W>(wires) {
+            let inputs = output
+                .map(|id| (F::one(), id))
+                .into_iter()
+                .chain(row)
+                .collect::<Vec<_>>();
+            let gate = Gate::Linear {
+                coefficients: inputs.iter().map(|input| input.0).collect(),
+                // the constant is added once, in the first row
+                constant: output.map_or(constant, |_| F::zero()),
+            };
+            let j = context.add_gate(gate);
+            let id = context.new_id();
+            context.add_var(id, Tag { i: 2, j });
+            for (i, (_, input)) in inputs.iter().enumerate() {
+                context.place(
+                    *input,
+                    Tag {
+                        i: input_column(i),
+                        j,
+                    },
+                );
+            }
+            output = Some(id);
+        }
+        Self::Wire {
+            id: output.expect("there is at least 1 wire"),
+            context: context.clone(),
+        }
     }
 }
-impl Var for ComputeVar {
+impl<F: PrimeField, const W: usize> Var for ComputeVar<F, W> {
+    type Field = F;
+
     ///would be better to handle the error
-    fn assert_eq(&self, _other: &Self) {
+    fn assert_eq(&self, other: &Self) {
         //this would prevent creation of invalid proofs, commented to be able to test invalid proofs
         //assert_eq!(self.value, other.value);
+        // the builder adds an `a - k = 0` row when one side is a constant
+        match (self, other) {
+            (Self::Wire { value }, Self::Constant(_))
+            | (Self::Constant(_), Self::Wire { value }) => push_row::<F, W>(&[*value]),
+            _ => {}
+        }
     }
-}
-
-// test template code
-// #[cfg(test)]
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    #[test]
-    fn test1() {
-        let context = Context::default();
-        println!("context: {:?}", &context);
+    /// the public input row is `[value, 0, …]`, the prover reads it back from column a
+    fn expose(&self) {
+        match self {
+            Self::Wire { value } => push_row::<F, W>(&[*value]),
+            Self::Constant(_) => panic!("a constant can not be exposed as a public input"),
+        }
+    }
+    fn constant(value: impl Into<F>) -> Self {
+        Self::Constant(value.into())
+    }
+    fn custom_gate<G: CustomGate<F>>(gate: &G, selectors: &[F], inputs: &[&Self]) -> Self {
+        Self::custom_row(gate, selectors, inputs, true).unwrap()
     }
-}
\ No newline at end of file
+    fn custom_constraint<G: CustomGate<F>>(gate: &G, selectors: &[F], inputs: &[&Self]) {
+        Self::custom_row(gate, selectors, inputs, false);
+    }
+    /// the hint row is `[value, 0, …]`, as in the builder
+    fn hint(inputs: &[&Self], f: impl FnOnce(&[F]) -> F) -> Self {
+        let values = inputs.iter().map(|input| input.value()).collect::<Vec<_>>();
+        let value = f(&values);
+        match inputs
+            .iter()
+            .any(|input| matches!(input, Self::Wire { .. }))
+        {
+            true => {
+                push_row::<F, W>(&[value]);
+                Self::Wire { value }
+            }
+            false => Self::Constant(value),
+        }
+    }
+    /// the lookup row is `[value, 0, …]`
+    fn lookup(&self, _table: &Table<F>) {
+        match self {
+            Self::Wire { value } => push_row::<F, W>(&[*value]),
+            Self::Constant(_) => panic!("a constant can not be looked up"),
+        }
+    }
+    /// the rows of the builder, with the inputs in a, b, d, e, … and the sum in c
+    fn linear_combination(terms: &[(F, &Self)]) -> Self {
+        let mut constant = F::zero();
+        let mut wires = vec![];
+        for (k, term) in terms {
+            match term {
+                Self::Constant(value) => constant += *k * value,
+                Self::Wire { value } => wires.push((*k, *value)),
+            }
+        }
+        if wires.is_empty() {
+            return Self::Constant(constant);
+        }
+        let mut output: Option<F> = None;
+        for row in linear_rows::<_, W>(wires) {
+            let inputs = output
+                .map(|value| (F::one(), value))
+                .into_iter()
+                .chain(row)
+                .collect::<Vec<_>>();
+            let mut cells = vec![F::zero(); W];
+            for (i, (_, value)) in inputs.iter().enumerate() {
+                cells[input_column(i)] = *value;
+            }
+            let sum = inputs.iter().map(|(k, value)| *k * value).sum::<F>();
+            cells[2] = sum + output.map_or(constant, |_| F::zero());
+            push_row::<F, W>(&cells);
+            output = Some(cells[2]);
+        }
+        Self::Wire {
+            value: output.expect("there is at least 1 wire"),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! The rows of the witness, written by the compute variables while a proof runs the description.
+//!
+//! Each proof installs its own arena on the thread running the description, so proofs made on
+//! different threads never share their rows and the variables only carry their value. The
+//! description must not move compute variables to other threads. The arena holds the columns
+//! over the field of the running proof, behind `Any` as thread locals can not be generic.
+use ark_ff::PrimeField;
+use std::{any::Any, cell::RefCell};
+
+thread_local! {
+    static ARENA: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+}
+
+/// puts back the arena of an enclosing run, also when the description panics
+struct Restore(Option<Box<dyn Any>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let outer = self.0.take();
+        ARENA.with(|arena| *arena.borrow_mut() = outer);
+    }
+}
+
+/// runs `f` with an empty arena and returns the W columns of the rows it added
+pub(crate) fn with_arena<F: PrimeField, const W: usize>(f: impl FnOnce()) -> [Vec<F>; W] {
+    let empty: Box<dyn Any> = Box::new([(); W].map(|_| <Vec<F>>::new()));
+    let restore = Restore(ARENA.with(|arena| arena.replace(Some(empty))));
+    f();
+    let rows = ARENA.with(|arena| arena.borrow_mut().take());
+    drop(restore);
+    let rows = rows.expect("the arena is installed until the run ends");
+    *rows
+        .downcast()
+        .expect("the variables are over the field of the proof")
+}
+
+/// adds a row to the arena of the running proof, the wires after `cells` are 0
+pub(crate) fn push_row<F: PrimeField, const W: usize>(cells: &[F]) {
+    assert!(cells.len() <= W, "a row has {} wires", W);
+    ARENA.with(|arena| {
+        let mut arena = arena.borrow_mut();
+        let columns = arena
+            .as_mut()
+            .expect("compute variables are only used while proving")
+            .downcast_mut::<[Vec<F>; W]>()
+            .expect("the variables are over the field and the wires of the proof");
+        let row = cells.iter().copied().chain(std::iter::repeat(F::zero()));
+        columns
+            .iter_mut()
+            .zip(row)
+            .for_each(|(col, value)| col.push(value));
+    });
+}
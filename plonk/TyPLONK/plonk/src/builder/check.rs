@@ -0,0 +1,168 @@
+//! Checks a witness against the rows of the circuit without proving it.
+//!
+//! The gate equation of every row and every copy constraint is evaluated directly over the
+//! columns of the wires, so a broken constraint is reported with its row and the variables of the
+//! description it involves instead of making the quotient fail to divide.
+use super::{CircuitBuilder, Gate, Layout, Tag, VarId};
+use crate::{description::Circuit, CompiledCircuit, PlonkError};
+use ark_ec::PairingEngine;
+use ark_ff::Zero;
+use num_bigint::BigUint;
+use std::collections::HashSet;
+
+/// a constraint the witness does not satisfy, found by [`CompiledCircuit::check`]. The values are
+/// kept as integers so the failures of circuits over any field fit in `PlonkError`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintFailure {
+    pub row: usize,
+    pub kind: ConstraintKind,
+    /// the values of the wires a, b, c, … in the row
+    pub wires: Vec<BigUint>,
+    /// the variables of the description in the wires, `None` for the cells no variable uses
+    pub vars: Vec<Option<VarId>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstraintKind {
+    /// `c = a + b`
+    Add,
+    /// `c = a·b`
+    Mul,
+    /// a public input row
+    PublicInput,
+    /// `q_l·a + q_r·b - q_o·c + q_m·ab + q_c = 0`, the rows of constants, subtractions and scalings
+    Arith,
+    /// `c = Σ k_i·w_i + q_c`, a row of a linear combination
+    Linear,
+    /// a row of the custom gate with this name
+    Custom(&'static str),
+    /// the value in column a is not an entry of the table with this name
+    Lookup(&'static str),
+    /// the cell in `column` must be equal to the cell `to`, which holds `value`
+    Copy {
+        column: usize,
+        to: Tag,
+        value: BigUint,
+    },
+}
+
+impl<E: PairingEngine, C: Circuit, const W: usize> CompiledCircuit<E, C, W> {
+    /// synthesizes the circuit on the inputs and checks every gate and copy constraint. Fails
+    /// with `PlonkError::Unsatisfied` and the broken ones sorted by row, or with
+    /// `PlonkError::Inputs` for the wrong number of inputs
+    pub fn check(
+        &self,
+        inputs: impl IntoIterator<Item = impl Into<E::Fr>>,
+    ) -> Result<(), PlonkError> {
+        let inputs = self.inputs(inputs)?;
+        self.check_advice(&self.advice(inputs))
+            .map_err(PlonkError::Unsatisfied)
+    }
+    /// checks the columns of the wires made by `advice`
+    pub(crate) fn check_advice(
+        &self,
+        advice: &[Vec<E::Fr>; W],
+    ) -> Result<(), Vec<ConstraintFailure>> {
+        let Layout {
+            gates,
+            permutation,
+            cells,
+            ..
+        } = CircuitBuilder::<E::Fr, W>::synthesize(&self.circuit);
+        // each table is hashed once, a lookup row is a single query
+        let tables = self
+            .circuit
+            .tables::<E::Fr>()
+            .into_iter()
+            .map(|table| {
+                let entries = table.values().iter().copied().collect::<HashSet<_>>();
+                (table.name(), entries)
+            })
+            .collect::<Vec<_>>();
+        let rows = gates.len();
+        let advice = advice.clone().map(|mut col| {
+            col.resize(rows, E::Fr::zero());
+            col
+        });
+        let wires = |row: usize| advice.each_ref().map(|col| col[row]);
+        let failure = |row: usize, kind| ConstraintFailure {
+            row,
+            kind,
+            wires: wires(row).into_iter().map(Into::into).collect(),
+            vars: (0..W)
+                .map(|i| cells.get(&Tag { i, j: row }).copied())
+                .collect(),
+        };
+
+        let mut failures = gates
+            .iter()
+            .enumerate()
+            .filter_map(|(row, gate)| {
+                let row_wires = wires(row);
+                let [a, b, c] = [row_wires[0], row_wires[1], row_wires[2]];
+                let [q_l, q_r, q_o, q_m, q_c] = gate.to_row();
+                let wide = gate
+                    .wire_selectors(W)
+                    .into_iter()
+                    .zip(&row_wires[3..])
+                    .map(|(q, wire)| q * wire)
+                    .sum::<E::Fr>();
+                let value = q_l * a + q_r * b - q_o * c + q_m * a * b + q_c + wide;
+                let (kind, holds) = match gate {
+                    Gate::Add => (ConstraintKind::Add, value.is_zero()),
+                    Gate::Mul => (ConstraintKind::Mul, value.is_zero()),
+                    // `PI(w^row) = -input` and the input is read from column a
+                    Gate::PublicInput => (ConstraintKind::PublicInput, (value - a).is_zero()),
+                    Gate::Arith { .. } => (ConstraintKind::Arith, value.is_zero()),
+                    Gate::Linear { .. } => (ConstraintKind::Linear, value.is_zero()),
+                    Gate::Custom {
+                        gate, selectors, ..
+                    } => {
+                        let gate = &self.gate_constrains.custom[*gate].gate;
+                        let terms = gate.terms(&row_wires, &wires((row + 1) % rows));
+                        let custom = selectors
+                            .iter()
+                            .zip(terms)
+                            .map(|(q, term)| *q * term)
+                            .sum::<E::Fr>();
+                        let kind = ConstraintKind::Custom(gate.name());
+                        (kind, (value + custom).is_zero())
+                    }
+                    Gate::Lookup { table } => {
+                        let (name, entries) = &tables[*table];
+                        (ConstraintKind::Lookup(name), entries.contains(&a))
+                    }
+                    Gate::Hint | Gate::Dummy => return None,
+                };
+                (!holds).then(|| failure(row, kind))
+            })
+            .collect::<Vec<_>>();
+
+        let mut copies = permutation
+            .constrains()
+            .filter_map(|(left, right)| {
+                let value = advice[right.i][right.j];
+                let column = left.i;
+                (advice[left.i][left.j] != value).then(|| {
+                    let kind = ConstraintKind::Copy {
+                        column,
+                        to: right,
+                        value: value.into(),
+                    };
+                    failure(left.j, kind)
+                })
+            })
+            .collect::<Vec<_>>();
+        // the constraints are kept in a map, the order of the copies is made deterministic
+        copies.sort_by_key(|failure| match failure.kind {
+            ConstraintKind::Copy { column, to, .. } => (failure.row, column, to.j, to.i),
+            _ => unreachable!(),
+        });
+        failures.extend(copies);
+        failures.sort_by_key(|failure| failure.row);
+        match failures.is_empty() {
+            true => Ok(()),
+            false => Err(failures),
+        }
+    }
+}
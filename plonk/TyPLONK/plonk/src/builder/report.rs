@@ -0,0 +1,239 @@
+//! Statistics and exports of the rows of a compiled circuit.
+//!
+//! The circuit is synthesized again with build variables, so the report sees the gates and copy
+//! constraints the description adds, before the permutation is compiled. The copies of an
+//! equivalence class become a single cycle of the permutation.
+use super::{CircuitBuilder, Gate, Layout, Tag, VarId};
+use crate::{description::Circuit, CompiledCircuit};
+use ark_ec::PairingEngine;
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{Display, Write},
+};
+
+/// the equivalence classes a report lists
+pub const LARGEST_CLASSES: usize = 5;
+
+/// The shape of a compiled circuit, see [`CompiledCircuit::report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitReport {
+    pub wires: usize,
+    /// the rows of each kind of gate, custom gates by name and lookups by table
+    pub gates: BTreeMap<String, usize>,
+    /// the rows of the gates, before padding
+    pub used_rows: usize,
+    /// the rows of the domain, a power of two with room for the blinding and the tables
+    pub rows: usize,
+    /// the copy constraints added by the description
+    pub copies: usize,
+    /// the equivalence classes of 2 cells or more, each one a cycle of the permutation
+    pub classes: usize,
+    /// the cells of these classes
+    pub copied_cells: usize,
+    /// the largest classes, largest first
+    pub largest_classes: Vec<EquivalenceClass>,
+}
+
+/// Cells constrained to be equal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EquivalenceClass {
+    /// sorted by row then column
+    pub cells: Vec<Tag>,
+    /// the variables of the description in the cells
+    pub vars: Vec<VarId>,
+}
+
+/// The gates of a circuit and the copy constraints between their cells, see
+/// [`CompiledCircuit::graph`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitGraph {
+    pub wires: usize,
+    /// the row and the kind of each gate, padding excluded
+    pub gates: Vec<(usize, String)>,
+    /// each equivalence class as a chain of its cells, in row order
+    pub copies: Vec<(Tag, Tag)>,
+}
+
+impl<E: PairingEngine, C: Circuit, const W: usize> CompiledCircuit<E, C, W> {
+    /// counts the gates by kind, the padding and the cycles of the copy constraints
+    pub fn report(&self) -> CircuitReport {
+        let Layout {
+            gates,
+            permutation,
+            cells,
+            ..
+        } = CircuitBuilder::<E::Fr, W>::synthesize(&self.circuit);
+        let tables = self.table_names();
+        let mut counts = BTreeMap::new();
+        for gate in &gates {
+            if let Some(kind) = self.gate_kind(gate, &tables) {
+                *counts.entry(kind).or_insert(0) += 1;
+            }
+        }
+        let mut classes = permutation.classes();
+        // largest first, the earliest first among the classes of a size
+        classes.sort_by_key(|class| std::cmp::Reverse(class.len()));
+        let largest_classes = classes
+            .iter()
+            .take(LARGEST_CLASSES)
+            .map(|class| EquivalenceClass {
+                cells: class.clone(),
+                vars: class
+                    .iter()
+                    .filter_map(|cell| cells.get(cell).copied())
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect(),
+            })
+            .collect();
+        CircuitReport {
+            wires: W,
+            used_rows: counts.values().sum(),
+            gates: counts,
+            rows: gates.len(),
+            copies: permutation.constrains().count(),
+            classes: classes.len(),
+            copied_cells: classes.iter().map(Vec::len).sum(),
+            largest_classes,
+        }
+    }
+    /// the gates as nodes and the copy constraints as edges between them
+    pub fn graph(&self) -> CircuitGraph {
+        let Layout {
+            gates, permutation, ..
+        } = CircuitBuilder::<E::Fr, W>::synthesize(&self.circuit);
+        let tables = self.table_names();
+        let gates = gates
+            .iter()
+            .enumerate()
+            .filter_map(|(row, gate)| Some((row, self.gate_kind(gate, &tables)?)))
+            .collect();
+        let copies = permutation
+            .classes()
+            .iter()
+            .flat_map(|class| class.windows(2).map(|pair| (pair[0], pair[1])))
+            .collect();
+        CircuitGraph {
+            wires: W,
+            gates,
+            copies,
+        }
+    }
+    /// the names of the tables of the circuit, by index
+    fn table_names(&self) -> Vec<&'static str> {
+        let tables = self.circuit.tables::<E::Fr>();
+        tables.iter().map(|table| table.name()).collect()
+    }
+    /// `None` for the padding
+    fn gate_kind(&self, gate: &Gate<E::Fr>, tables: &[&'static str]) -> Option<String> {
+        let kind = match gate {
+            Gate::Mul => "mul".to_string(),
+            Gate::Add => "add".to_string(),
+            Gate::PublicInput => "public input".to_string(),
+            Gate::Hint => "hint".to_string(),
+            Gate::Arith { .. } => "arith".to_string(),
+            Gate::Linear { .. } => "linear".to_string(),
+            Gate::Custom { gate, .. } => self.gate_constrains.custom[*gate].gate.name().to_string(),
+            Gate::Lookup { table } => format!("lookup {}", tables[*table]),
+            Gate::Dummy => return None,
+        };
+        Some(kind)
+    }
+}
+
+impl CircuitReport {
+    /// the rows added to reach a power of two
+    pub fn padding(&self) -> usize {
+        self.rows - self.used_rows
+    }
+    /// the padding over the rows of the gates
+    pub fn padding_overhead(&self) -> f64 {
+        self.padding() as f64 / self.used_rows.max(1) as f64
+    }
+    /// the mean number of cells of the classes
+    pub fn mean_class_size(&self) -> f64 {
+        self.copied_cells as f64 / self.classes.max(1) as f64
+    }
+}
+
+impl Display for CircuitReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} wires, {} gates in {} rows, {} of padding ({:.1}%)",
+            self.wires,
+            self.used_rows,
+            self.rows,
+            self.padding(),
+            self.padding_overhead() * 100.0
+        )?;
+        let gates = self
+            .gates
+            .iter()
+            .map(|(kind, count)| format!("{} {}", kind, count))
+            .collect::<Vec<_>>();
+        writeln!(f, "gates: {}", gates.join(", "))?;
+        write!(
+            f,
+            "copies: {} constraints, {} cycles over {} cells, {:.2} cells per cycle",
+            self.copies,
+            self.classes,
+            self.copied_cells,
+            self.mean_class_size()
+        )?;
+        for class in &self.largest_classes {
+            let cells = class.cells.iter().map(cell_name).collect::<Vec<_>>();
+            write!(f, "\n  {} cells: {}", class.cells.len(), cells.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl CircuitGraph {
+    /// a Graphviz digraph, a node for each gate and an edge labelled with the wires for each copy
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph circuit {\n    node [shape=box];\n".to_string();
+        for (row, kind) in &self.gates {
+            writeln!(dot, "    r{} [label=\"{}: {}\"];", row, row, kind).unwrap();
+        }
+        for (from, to) in &self.copies {
+            writeln!(
+                dot,
+                "    r{} -> r{} [label=\"{} = {}\"];",
+                from.j,
+                to.j,
+                wire_name(from.i),
+                wire_name(to.i)
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+    /// `{"wires", "gates": [{"row", "kind"}], "copies": [{"from": {"row", "wire"}, "to"}]}`
+    pub fn to_json(&self) -> String {
+        let cell = |tag: &Tag| json!({ "row": tag.j, "wire": wire_name(tag.i).to_string() });
+        let gates = self
+            .gates
+            .iter()
+            .map(|(row, kind)| json!({ "row": row, "kind": kind }))
+            .collect::<Vec<_>>();
+        let copies = self
+            .copies
+            .iter()
+            .map(|(from, to)| json!({ "from": cell(from), "to": cell(to) }))
+            .collect::<Vec<_>>();
+        let graph = json!({ "wires": self.wires, "gates": gates, "copies": copies });
+        serde_json::to_string_pretty(&graph).unwrap()
+    }
+}
+
+/// a, b, c, d, …
+fn wire_name(i: usize) -> char {
+    (b'a' + i as u8) as char
+}
+/// the row and the wire, like `3c`
+fn cell_name(tag: &Tag) -> String {
+    format!("{}{}", tag.j, wire_name(tag.i))
+}
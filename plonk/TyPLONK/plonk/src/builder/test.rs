@@ -1,4 +1,16 @@
-use crate::description::{CircuitDescription, Var};
+use super::{CircuitBuilder, ConstraintFailure, ConstraintKind, Gate, Tag};
+use crate::{
+    curve_tests,
+    description::{Circuit, CircuitDescription, ConstraintSystem, DescriptionCircuit, Var},
+    gate::{BoolGate, CustomGate, Pow5Gate},
+    lookup::Table,
+    transcript::{Blake2bHash, KeccakHash, PoseidonHash},
+    Bls12_381, PlonkError, Proof, ProvingKey, Srs, VerifyingKey,
+};
+use ark_ec::PairingEngine;
+use ark_ff::{FftField, Field, One, PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use num_bigint::BigUint;
 
 struct Circuit1;
 impl CircuitDescription<5> for Circuit1 {
@@ -22,23 +34,851 @@ impl CircuitDescription<3> for Circuit2 {
     }
 }
 
-#[test]
-fn circuit2_test() {
-    let circuit = Circuit2::build();
-    let proof = circuit.prove([3, 4, 5], vec![0]);
-    assert!(circuit.verify(proof));
+fn circuit2_test<E: PairingEngine>() {
+    let circuit = Circuit2::build::<E>().unwrap();
+    let proof = circuit.prove([3_u64, 4, 5]).unwrap();
+    assert!(circuit.verify(proof).is_ok());
 }
-#[test]
-#[should_panic]
-fn circuit2_test_bad_inputs() {
-    let circuit = Circuit2::build();
-    let proof = circuit.prove([3, 4, 6], vec![0]);
-    assert!(circuit.verify(proof));
+fn circuit2_test_bad_inputs<E: PairingEngine>() {
+    let circuit = Circuit2::build::<E>().unwrap();
+    // the failed proof reports the broken copy constraint found by check
+    let failures = match circuit.prove([3_u64, 4, 6]) {
+        Err(PlonkError::Unsatisfied(failures)) => failures,
+        other => panic!("the witness is not unsatisfied: {:?}", other.err()),
+    };
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].row, 3);
+}
+
+fn shared_srs_test<E: PairingEngine>() {
+    let srs = Srs::<E>::from_secret(E::Fr::from(1234_u64), 256);
+    let prover = Circuit2::build_with_srs(&srs).unwrap();
+    let verifier = Circuit2::build_with_srs(&srs).unwrap();
+    let proof = prover.prove([3_u64, 4, 5]).unwrap();
+    assert!(verifier.verify(proof).is_ok());
+}
+fn shared_srs_test_too_small<E: PairingEngine>() {
+    let srs = Srs::<E>::from_secret(E::Fr::from(1234_u64), 4);
+    assert!(matches!(
+        Circuit2::build_with_srs(&srs),
+        Err(PlonkError::SrsTooSmall { .. })
+    ));
+}
+
+fn circuit1_test<E: PairingEngine>() {
+    let circuit = Circuit1::build::<E>().unwrap();
+    let proof = circuit.prove([2_u64, 7, 2, 3, 4]).unwrap();
+    assert!(circuit.verify(proof).is_ok());
+}
+
+struct Circuit3;
+impl CircuitDescription<3> for Circuit3 {
+    fn run<V: Var>(inputs: [V; 3]) {
+        let [a, b, c] = inputs;
+        a.expose();
+        let d = a * b;
+        c.expose();
+        d.assert_eq(&c);
+    }
+}
+
+fn public_inputs_test<E: PairingEngine>() {
+    let circuit = Circuit3::build::<E>().unwrap();
+    let proof = circuit.prove([3_u64, 4, 12]).unwrap();
+    assert_eq!(
+        proof.public_inputs,
+        vec![E::Fr::from(3_u64), E::Fr::from(12_u64)]
+    );
+    assert!(circuit.verify(proof).is_ok());
+}
+fn public_inputs_test_tampered<E: PairingEngine>() {
+    let circuit = Circuit3::build::<E>().unwrap();
+    let mut proof = circuit.prove([3_u64, 4, 12]).unwrap();
+    proof.public_inputs[1] = E::Fr::from(13_u64);
+    assert_eq!(circuit.verify(proof), Err(PlonkError::InvalidProof));
+}
+fn public_inputs_test_wrong_length<E: PairingEngine>() {
+    let circuit = Circuit3::build::<E>().unwrap();
+    let mut proof = circuit.prove([3_u64, 4, 12]).unwrap();
+    proof.public_inputs.pop();
+    assert_eq!(
+        circuit.verify(proof),
+        Err(PlonkError::PublicInputs {
+            expected: 2,
+            got: 1
+        })
+    );
 }
 
+struct Circuit4;
+impl CircuitDescription<3> for Circuit4 {
+    fn run<V: Var>(inputs: [V; 3]) {
+        let [a, b, c] = inputs;
+        let d = a * V::Field::from(3_u64) - b + V::constant(7_u64);
+        d.assert_eq(&c);
+        let e = -c + V::constant(2_u64) * V::constant(5_u64);
+        e.assert_eq(&V::constant(1_u64));
+    }
+}
+
+fn constants_test<E: PairingEngine>() {
+    let circuit = Circuit4::build::<E>().unwrap();
+    let proof = circuit.prove([2_u64, 4, 9]).unwrap();
+    assert!(circuit.verify(proof).is_ok());
+}
+fn constants_test_bad_inputs<E: PairingEngine>() {
+    let circuit = Circuit4::build::<E>().unwrap();
+    assert!(matches!(
+        circuit.prove([2_u64, 4, 10]),
+        Err(PlonkError::Unsatisfied(_))
+    ));
+}
+
+/// `q·(a_next - a - b)`, the sum of a row is in column a of the next one
+#[derive(Debug)]
+struct NextSumGate;
+impl<F: PrimeField> CustomGate<F> for NextSumGate {
+    fn name(&self) -> &'static str {
+        "next sum"
+    }
+    fn selectors(&self) -> usize {
+        1
+    }
+    fn degree(&self) -> usize {
+        1
+    }
+    fn terms(&self, wires: &[F], next: &[F]) -> Vec<F> {
+        vec![next[0] - wires[0] - wires[1]]
+    }
+}
+
+struct Circuit5;
+impl CircuitDescription<4> for Circuit5 {
+    fn run<V: Var>(inputs: [V; 4]) {
+        let [a, b, c, d] = inputs;
+        let a5 = V::custom_gate(&Pow5Gate, &[V::Field::one()], &[&a]);
+        b.expose();
+        a5.assert_eq(&b);
+        V::custom_constraint(&BoolGate, &[V::Field::one()], &[&c]);
+        V::custom_constraint(&NextSumGate, &[V::Field::one()], &[&a, &c]);
+        let d = d * V::Field::from(2_u64);
+        d.expose();
+    }
+    fn custom_gates<F: PrimeField>() -> Vec<Box<dyn CustomGate<F>>> {
+        vec![
+            Box::new(Pow5Gate),
+            Box::new(BoolGate),
+            Box::new(NextSumGate),
+        ]
+    }
+}
+
+fn custom_gates_test<E: PairingEngine>() {
+    let circuit = Circuit5::build::<E>().unwrap();
+    let proof = circuit.prove([3_u64, 243, 1, 4]).unwrap();
+    assert_eq!(
+        proof.public_inputs,
+        vec![E::Fr::from(243_u64), E::Fr::from(8_u64)]
+    );
+    assert!(circuit.verify(proof).is_ok());
+}
+fn custom_gates_test_not_bool<E: PairingEngine>() {
+    let circuit = Circuit5::build::<E>().unwrap();
+    assert!(matches!(
+        circuit.prove([3_u64, 243, 2, 5]),
+        Err(PlonkError::Unsatisfied(_))
+    ));
+}
+fn custom_gates_test_bad_next_row<E: PairingEngine>() {
+    let circuit = Circuit5::build::<E>().unwrap();
+    assert!(matches!(
+        circuit.prove([3_u64, 243, 1, 5]),
+        Err(PlonkError::Unsatisfied(_))
+    ));
+}
+
+struct Circuit6;
+impl Circuit6 {
+    fn nibble<F: PrimeField>() -> Table<F> {
+        Table::range("nibble", 4)
+    }
+    fn primes<F: PrimeField>() -> Table<F> {
+        Table::new("primes", [2_u64, 3, 5, 7, 11, 13])
+    }
+}
+impl CircuitDescription<3> for Circuit6 {
+    fn run<V: Var>(inputs: [V; 3]) {
+        let [a, b, c] = inputs;
+        a.lookup(&Self::nibble());
+        b.lookup(&Self::nibble());
+        let sum = a + b.clone();
+        sum.lookup(&Self::nibble());
+        c.lookup(&Self::primes());
+        (c * b).expose();
+    }
+    fn tables<F: PrimeField>() -> Vec<Table<F>> {
+        vec![Self::nibble(), Self::primes()]
+    }
+}
+
+fn lookup_test<E: PairingEngine>() {
+    let circuit = Circuit6::build::<E>().unwrap();
+    let proof = circuit.prove([9_u64, 5, 13]).unwrap();
+    assert_eq!(proof.public_inputs, vec![E::Fr::from(65_u64)]);
+    assert!(circuit.verify(proof).is_ok());
+}
+fn lookup_test_out_of_range<E: PairingEngine>() {
+    let circuit = Circuit6::build::<E>().unwrap();
+    assert!(matches!(
+        circuit.prove([9_u64, 7, 13]),
+        Err(PlonkError::Unsatisfied(_))
+    ));
+}
+fn lookup_test_wrong_table<E: PairingEngine>() {
+    // 4 is a nibble but not in the primes table
+    let circuit = Circuit6::build::<E>().unwrap();
+    assert!(matches!(
+        circuit.prove([9_u64, 5, 4]),
+        Err(PlonkError::Unsatisfied(_))
+    ));
+}
+
+fn split_keys_test<E: PairingEngine>() {
+    let (proving_key, verifying_key) = Circuit3::build::<E>().unwrap().split();
+    let proof = proving_key.prove([3_u64, 4, 12]).unwrap();
+    assert!(verifying_key.verify(proof).is_ok());
+}
+fn keys_serialization_test<E: PairingEngine>() {
+    let (proving_key, verifying_key) = Circuit5::build::<E>().unwrap().split();
+    let mut bytes = vec![];
+    proving_key.serialize(&mut bytes).unwrap();
+    let proving_key =
+        ProvingKey::<E, _>::deserialize(&bytes[..], DescriptionCircuit::<4, Circuit5>::default())
+            .unwrap();
+    for key in malformed_layouts(&bytes) {
+        let circuit = DescriptionCircuit::<4, Circuit5>::default();
+        assert!(ProvingKey::<E, _>::deserialize(&key[..], circuit).is_err());
+    }
+    let mut bytes = vec![];
+    verifying_key.serialize(&mut bytes).unwrap();
+    assert_eq!(&bytes[..4], b"TPVK");
+    for key in malformed_layouts(&bytes) {
+        let gates = Circuit5::custom_gates::<E::Fr>();
+        assert!(VerifyingKey::<E>::deserialize(&key[..], gates).is_err());
+    }
+    assert!(VerifyingKey::<E>::deserialize(&bytes[..], vec![]).is_err());
+    let verifying_key =
+        VerifyingKey::<E>::deserialize(&bytes[..], Circuit5::custom_gates::<E::Fr>()).unwrap();
+    let proof = proving_key.prove([3_u64, 243, 1, 4]).unwrap();
+    assert!(verifying_key.verify(proof).is_ok());
+    // other versions are rejected
+    bytes[4] += 1;
+    assert!(VerifyingKey::<E>::deserialize(&bytes[..], Circuit5::custom_gates::<E::Fr>()).is_err());
+}
+/// the key of Circuit5, with its 2 public rows, with a public row out of the domain and with
+/// slices too small for its quotient. The rows follow the header and the domain size
+fn malformed_layouts(key: &[u8]) -> [Vec<u8>; 2] {
+    let mut public_row = key.to_vec();
+    public_row[24..32].copy_from_slice(&(1_u64 << 40).to_le_bytes());
+    let mut slice_degree = key.to_vec();
+    slice_degree[40..48].copy_from_slice(&4_u64.to_le_bytes());
+    [public_row, slice_degree]
+}
+fn lookup_keys_serialization_test<E: PairingEngine>() {
+    let (proving_key, verifying_key) = Circuit6::build::<E>().unwrap().split();
+    let mut bytes = vec![];
+    proving_key.serialize(&mut bytes).unwrap();
+    let proving_key =
+        ProvingKey::<E, _>::deserialize(&bytes[..], DescriptionCircuit::<3, Circuit6>::default())
+            .unwrap();
+    assert!(ProvingKey::<E, _>::deserialize(
+        &bytes[..bytes.len() - 1],
+        DescriptionCircuit::<3, Circuit6>::default()
+    )
+    .is_err());
+    let mut bytes = vec![];
+    verifying_key.serialize(&mut bytes).unwrap();
+    let verifying_key = VerifyingKey::<E>::deserialize(&bytes[..], vec![]).unwrap();
+    let proof = proving_key.prove([9_u64, 5, 13]).unwrap();
+    assert!(verifying_key.verify(proof).is_ok());
+}
+
+fn proof_serialization_test<E: PairingEngine>() {
+    let circuit = Circuit5::build::<E>().unwrap();
+    let proof = circuit.prove([3_u64, 243, 1, 4]).unwrap();
+    let mut compressed = vec![];
+    proof.serialize(&mut compressed).unwrap();
+    assert_eq!(compressed.len(), proof.serialized_size());
+    assert_eq!(&compressed[..8], b"TPPF\x02\0\0\0");
+    let mut uncompressed = vec![];
+    proof.serialize_uncompressed(&mut uncompressed).unwrap();
+    assert_eq!(uncompressed.len(), proof.uncompressed_size());
+    assert!(uncompressed.len() > compressed.len());
+
+    assert!(circuit
+        .verify(Proof::<E>::deserialize(&compressed[..]).unwrap())
+        .is_ok());
+    assert!(circuit
+        .verify(Proof::<E>::deserialize_uncompressed(&uncompressed[..]).unwrap())
+        .is_ok());
+    assert!(circuit
+        .verify(Proof::<E>::deserialize_unchecked(&uncompressed[..]).unwrap())
+        .is_ok());
+    assert!(Proof::<E>::deserialize(&compressed[..compressed.len() - 1]).is_err());
+    compressed[4] = 1;
+    assert!(Proof::<E>::deserialize(&compressed[..]).is_err());
+}
+fn lookup_proof_serialization_test<E: PairingEngine>() {
+    let circuit = Circuit6::build::<E>().unwrap();
+    let proof = circuit.prove([9_u64, 5, 13]).unwrap();
+    let mut bytes = vec![];
+    proof.serialize(&mut bytes).unwrap();
+    let proof = Proof::<E>::deserialize(&bytes[..]).unwrap();
+    assert_eq!(proof.public_inputs, vec![E::Fr::from(65_u64)]);
+    assert!(circuit.verify(proof).is_ok());
+}
+fn proof_serialization_invalid_points_test<E: PairingEngine>() {
+    let circuit = Circuit3::build::<E>().unwrap();
+    let proof = circuit.prove([3_u64, 4, 12]).unwrap();
+    let mut compressed = vec![];
+    proof.serialize(&mut compressed).unwrap();
+    let mut uncompressed = vec![];
+    proof.serialize_uncompressed(&mut uncompressed).unwrap();
+
+    // the commitment to a is the first point, right after the header
+    let mut off_curve = uncompressed.clone();
+    off_curve[8] ^= 1;
+    assert!(Proof::<E>::deserialize_uncompressed(&off_curve[..]).is_err());
+}
+/// a point of the curve outside of the prime order subgroup, g1 of BN254 has none
 #[test]
-fn circuit1_test() {
-    let circuit = Circuit1::build();
-    let proof = circuit.prove([2, 7, 2, 3, 4], vec![0]);
-    assert!(circuit.verify(proof));
+fn proof_serialization_outside_subgroup_test() {
+    use ark_bls12_381::{Fq, G1Affine};
+    let circuit = Circuit3::build::<Bls12_381>().unwrap();
+    let proof = circuit.prove([3, 4, 12]).unwrap();
+    let mut compressed = vec![];
+    proof.serialize(&mut compressed).unwrap();
+    let mut uncompressed = vec![];
+    proof.serialize_uncompressed(&mut uncompressed).unwrap();
+    let point = (1_u64..)
+        .filter_map(|x| G1Affine::get_point_from_x(Fq::from(x), false))
+        .find(|point| !point.is_in_correct_subgroup_assuming_on_curve())
+        .unwrap();
+    let mut outside = compressed.clone();
+    point
+        .serialize(&mut outside[8..8 + point.serialized_size()])
+        .unwrap();
+    assert!(Proof::<Bls12_381>::deserialize(&outside[..]).is_err());
+    let mut outside = uncompressed.clone();
+    let size = point.uncompressed_size();
+    point
+        .serialize_uncompressed(&mut outside[8..8 + size])
+        .unwrap();
+    assert!(Proof::<Bls12_381>::deserialize_uncompressed(&outside[..]).is_err());
+    assert!(Proof::<Bls12_381>::deserialize_unchecked(&outside[..]).is_ok());
 }
+
+fn transcript_hash_test<E: PairingEngine>() {
+    let (proving_key, verifying_key) = Circuit3::build::<E>().unwrap().split();
+    let proof = proving_key
+        .prove_with_hash::<KeccakHash>([3_u64, 4, 12])
+        .unwrap();
+    assert!(verifying_key.verify_with_hash::<KeccakHash>(proof).is_ok());
+    let proof = proving_key
+        .prove_with_hash::<KeccakHash>([3_u64, 4, 12])
+        .unwrap();
+    assert!(verifying_key
+        .verify_with_hash::<Blake2bHash>(proof)
+        .is_err());
+    let proof = proving_key
+        .prove_with_hash::<PoseidonHash>([3_u64, 4, 12])
+        .unwrap();
+    assert!(verifying_key
+        .verify_with_hash::<PoseidonHash>(proof)
+        .is_ok());
+}
+fn transcript_binds_verifying_key_test<E: PairingEngine>() {
+    // same shape, but the public rows differ, so the keys differ
+    struct Circuit3b;
+    impl CircuitDescription<3> for Circuit3b {
+        fn run<V: Var>(inputs: [V; 3]) {
+            let [a, b, c] = inputs;
+            c.expose();
+            let d = a.clone() * b;
+            a.expose();
+            d.assert_eq(&c);
+        }
+    }
+    let proof = Circuit3::build::<E>()
+        .unwrap()
+        .prove([3_u64, 4, 12])
+        .unwrap();
+    let other = Circuit3b::build::<E>().unwrap();
+    assert_ne!(
+        other.verifying_key().digest::<Blake2bHash>(),
+        Circuit3::build::<E>()
+            .unwrap()
+            .verifying_key()
+            .digest::<Blake2bHash>()
+    );
+    assert!(other.verify(proof).is_err());
+}
+
+fn verify_batch_test<E: PairingEngine>() {
+    let (proving_key, verifying_key) = Circuit3::build::<E>().unwrap().split();
+    let inputs = [[3_u64, 4, 12], [2, 5, 10], [1, 1, 1]];
+    let proofs = inputs.map(|inputs| proving_key.prove(inputs).unwrap());
+    assert!(verifying_key.verify_batch(&proofs).is_ok());
+    assert!(verifying_key.invalid_proofs(&proofs).is_empty());
+    assert!(verifying_key.verify_batch(&[]).is_ok());
+}
+fn concurrent_proofs_test<E: PairingEngine>() {
+    fn shared<T: Send + Sync>(value: T) -> T {
+        value
+    }
+    let circuit = &shared(Circuit3::build::<E>().unwrap());
+    let inputs = [[3_u64, 4, 12], [2, 5, 10], [1, 1, 1], [6, 2, 12]];
+    let proofs = std::thread::scope(|scope| {
+        let handles = inputs.map(|inputs| scope.spawn(move || circuit.prove(inputs).unwrap()));
+        handles.map(|handle| handle.join().unwrap())
+    });
+    assert!(circuit.verify_batch(&proofs).is_ok());
+    let (proving_key, verifying_key) = shared(Circuit5::build::<E>().unwrap().split());
+    let proof = std::thread::scope(|scope| {
+        scope
+            .spawn(|| proving_key.prove([3_u64, 243, 1, 4]).unwrap())
+            .join()
+            .unwrap()
+    });
+    assert!(shared(verifying_key).verify(proof).is_ok());
+}
+fn verify_batch_test_invalid<E: PairingEngine>() {
+    let (proving_key, verifying_key) = Circuit3::build::<E>().unwrap().split();
+    let inputs = [[3_u64, 4, 12], [2, 5, 10], [1, 1, 1], [6, 2, 12]];
+    let mut proofs = inputs.map(|inputs| proving_key.prove(inputs).unwrap());
+    proofs[1].public_inputs[1] = E::Fr::from(11_u64);
+    proofs[3].public_inputs[0] = E::Fr::from(4_u64);
+    assert_eq!(
+        verifying_key.verify_batch(&proofs),
+        Err(PlonkError::InvalidProof)
+    );
+    assert_eq!(verifying_key.invalid_proofs(&proofs), vec![1, 3]);
+}
+
+/// proves the knowledge of a square root of the exposed input
+struct SquareRoot;
+impl CircuitDescription<1> for SquareRoot {
+    fn run<V: Var>(inputs: [V; 1]) {
+        let [x] = inputs;
+        x.expose();
+        // a root among the small values, as in the tests
+        let root = V::hint(&[&x], |values| {
+            (0_u64..16)
+                .map(V::Field::from)
+                .find(|root| root.square() == values[0])
+                .unwrap_or_default()
+        });
+        (root.clone() * root).assert_eq(&x);
+        let nine = V::hint(&[&V::constant(3_u64)], |values| values[0].square());
+        nine.assert_eq(&V::constant(9_u64));
+    }
+}
+
+/// a hint of wires only asserted equal to the product it stands for
+struct HintedProduct;
+impl CircuitDescription<2> for HintedProduct {
+    fn run<V: Var>(inputs: [V; 2]) {
+        let [a, b] = inputs;
+        let product = V::hint(&[&a, &b], |values| values[0] * values[1]);
+        product.assert_eq(&(a * b));
+    }
+}
+
+fn hint_test<E: PairingEngine>() {
+    // the hint has a cell of its own for the copy constraint
+    let circuit = HintedProduct::build::<E>().unwrap();
+    assert_eq!(circuit.check([3_u64, 5]), Ok(()));
+    let proof = circuit.prove([3_u64, 5]).unwrap();
+    assert!(circuit.verify(proof).is_ok());
+
+    let circuit = SquareRoot::build::<E>().unwrap();
+    let proof = circuit.prove([9_u64]).unwrap();
+    assert!(circuit.verify(proof).is_ok());
+    // the generator of the multiplicative group is not a square
+    assert!(matches!(
+        circuit.prove([E::Fr::multiplicative_generator()]),
+        Err(PlonkError::Unsatisfied(_))
+    ));
+}
+
+/// exposes the product of its inputs, as many as `length`
+struct Product {
+    length: usize,
+}
+impl Circuit for Product {
+    fn inputs(&self) -> usize {
+        self.length
+    }
+    fn synthesize<V: Var>(&self, cs: &mut ConstraintSystem<V>) {
+        let first = cs.input();
+        let product = cs
+            .inputs(self.length - 1)
+            .into_iter()
+            .fold(first, |product, x| product * x);
+        assert_eq!(cs.remaining(), 0);
+        product.expose();
+    }
+}
+
+fn runtime_circuit_test<E: PairingEngine>() {
+    for length in [2, 5] {
+        let circuit = Product { length }.build::<E>().unwrap();
+        let inputs = (1..=length as u64).collect::<Vec<_>>();
+        let product = inputs.iter().product::<u64>();
+        let proof = circuit.prove(inputs).unwrap();
+        assert_eq!(proof.public_inputs, vec![E::Fr::from(product)]);
+        assert!(circuit.verify(proof).is_ok());
+    }
+    let circuit = Product { length: 3 }.build::<E>().unwrap();
+    assert_eq!(
+        circuit.prove([1_u64, 2]).unwrap_err(),
+        PlonkError::Inputs {
+            expected: 3,
+            got: 2
+        }
+    );
+}
+fn description_is_runtime_circuit_test<E: PairingEngine>() {
+    // a description compiles to the same keys as the runtime circuit adding the same rows
+    struct Multiplication;
+    impl Circuit for Multiplication {
+        fn inputs(&self) -> usize {
+            3
+        }
+        fn synthesize<V: Var>(&self, cs: &mut ConstraintSystem<V>) {
+            let [a, b, c] = [(); 3].map(|_| cs.input());
+            a.expose();
+            let d = a * b;
+            c.expose();
+            d.assert_eq(&c);
+        }
+    }
+    let srs = Srs::<E>::from_secret(E::Fr::from(1234_u64), 64);
+    let (proving_key, verifying_key) = Circuit3::build_with_srs(&srs).unwrap().split();
+    let runtime = Multiplication.build_with_srs(&srs).unwrap();
+    let mut bytes = vec![];
+    verifying_key.serialize(&mut bytes).unwrap();
+    let mut runtime_bytes = vec![];
+    runtime
+        .verifying_key()
+        .serialize(&mut runtime_bytes)
+        .unwrap();
+    assert_eq!(bytes, runtime_bytes);
+    let proof = proving_key.prove([3_u64, 4, 12]).unwrap();
+    assert!(runtime.verify(proof).is_ok());
+}
+
+/// the failures of an unsatisfied witness
+fn unsatisfied(result: Result<(), PlonkError>) -> Vec<ConstraintFailure> {
+    match result {
+        Err(PlonkError::Unsatisfied(failures)) => failures,
+        other => panic!("the witness is not unsatisfied: {:?}", other),
+    }
+}
+fn check_test<E: PairingEngine>() {
+    assert_eq!(Circuit2::build::<E>().unwrap().check([3_u64, 4, 5]), Ok(()));
+    assert_eq!(Circuit4::build::<E>().unwrap().check([2_u64, 4, 9]), Ok(()));
+    assert_eq!(
+        Circuit5::build::<E>().unwrap().check([3_u64, 243, 1, 4]),
+        Ok(())
+    );
+    assert_eq!(
+        Circuit6::build::<E>().unwrap().check([9_u64, 5, 13]),
+        Ok(())
+    );
+    // the wrong number of inputs is an error, not a panic
+    assert_eq!(
+        Circuit2::build::<E>().unwrap().check([3_u64, 4]),
+        Err(PlonkError::Inputs {
+            expected: 3,
+            got: 2
+        })
+    );
+}
+fn check_test_copy<E: PairingEngine>() {
+    // d = a² + b² = 25 in row 3 is asserted equal to c² = 36 in row 2
+    let failures = unsatisfied(Circuit2::build::<E>().unwrap().check([3_u64, 4, 6]));
+    assert_eq!(failures.len(), 1);
+    let ConstraintFailure {
+        row,
+        kind,
+        wires,
+        vars,
+    } = &failures[0];
+    assert_eq!(*row, 3);
+    let to = Tag { i: 2, j: 2 };
+    let value = BigUint::from(36_u64);
+    assert_eq!(
+        *kind,
+        ConstraintKind::Copy {
+            column: 2,
+            to,
+            value
+        }
+    );
+    assert_eq!(*wires, [9_u64, 16, 25].map(BigUint::from));
+    assert!(vars.iter().all(Option::is_some));
+}
+fn check_test_gates<E: PairingEngine>() {
+    // -c + 10 = 1 does not hold in the `a - 1 = 0` row
+    let failures = unsatisfied(Circuit4::build::<E>().unwrap().check([2_u64, 4, 10]));
+    let failure = failures.last().unwrap();
+    assert_eq!(failure.kind, ConstraintKind::Arith);
+    assert_eq!(failure.wires[0], BigUint::from(0_u64));
+    assert_eq!(failure.vars[1], None);
+
+    let failures = unsatisfied(Circuit5::build::<E>().unwrap().check([3_u64, 243, 2, 5]));
+    let kinds = failures
+        .iter()
+        .map(|failure| &failure.kind)
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, vec![&ConstraintKind::Custom("bool")]);
+    assert_eq!(failures[0].wires[0], BigUint::from(2_u64));
+    // the input c
+    assert_eq!(failures[0].vars[0].map(|var| var.index()), Some(2));
+
+    let failures = unsatisfied(Circuit6::build::<E>().unwrap().check([9_u64, 7, 4]));
+    let failed = failures
+        .iter()
+        .map(|failure| (failure.kind.clone(), failure.wires[0].clone()))
+        .collect::<Vec<_>>();
+    let expected = vec![
+        (ConstraintKind::Lookup("nibble"), BigUint::from(16_u64)),
+        (ConstraintKind::Lookup("primes"), BigUint::from(4_u64)),
+    ];
+    assert_eq!(failed, expected);
+}
+
+/// 12 weighted inputs and a constant in a single linear combination, equal to the last input
+struct WideSum;
+impl CircuitDescription<13> for WideSum {
+    fn run<V: Var>(inputs: [V; 13]) {
+        let (total, inputs) = inputs.split_last().unwrap();
+        let constant = V::constant(7_u64);
+        let mut terms = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| (V::Field::from(i as u64 + 1), input))
+            .collect::<Vec<_>>();
+        terms.push((V::Field::from(2_u64), &constant));
+        let sum = V::linear_combination(&terms);
+        sum.assert_eq(total);
+        total.expose();
+    }
+}
+/// the linear combination rows of `WideSum` with W wires
+fn linear_rows<F: PrimeField, const W: usize>() -> usize {
+    let layout = CircuitBuilder::<F, W>::synthesize(&DescriptionCircuit::<13, WideSum>::default());
+    let linear = layout
+        .gates
+        .iter()
+        .filter(|gate| matches!(gate, Gate::Linear { .. }));
+    linear.count()
+}
+fn wide_sum<E: PairingEngine, const W: usize>() {
+    let circuit = WideSum::build_wide::<E, W>().unwrap();
+    // Σ i·i for i in 1..=12, and 2·7
+    let proof = circuit.prove((1_u64..=12).chain([664])).unwrap();
+    assert_eq!(proof.public_inputs, vec![E::Fr::from(664_u64)]);
+    assert!(circuit.verify(proof).is_ok());
+    assert!(matches!(
+        circuit.prove((1_u64..=12).chain([665])),
+        Err(PlonkError::Unsatisfied(_))
+    ));
+}
+fn wide_circuit_test<E: PairingEngine>() {
+    wide_sum::<E, 3>();
+    wide_sum::<E, 4>();
+    wide_sum::<E, 5>();
+    // the first row sums W - 1 terms and the next ones W - 2 more
+    let rows = [
+        linear_rows::<E::Fr, 3>(),
+        linear_rows::<E::Fr, 4>(),
+        linear_rows::<E::Fr, 5>(),
+    ];
+    assert_eq!(rows, [11, 6, 4]);
+}
+
+/// `q·(d_next - d - a)`, the wire d carries a running sum to the next row
+#[derive(Debug)]
+struct CarryGate;
+impl<F: PrimeField> CustomGate<F> for CarryGate {
+    fn name(&self) -> &'static str {
+        "carry"
+    }
+    fn selectors(&self) -> usize {
+        1
+    }
+    fn degree(&self) -> usize {
+        1
+    }
+    fn terms(&self, wires: &[F], next: &[F]) -> Vec<F> {
+        vec![next[3] - wires[3] - wires[0]]
+    }
+}
+
+struct Carry;
+impl CircuitDescription<4> for Carry {
+    fn run<V: Var>(inputs: [V; 4]) {
+        let [x, y, start, end] = inputs;
+        V::custom_constraint(&CarryGate, &[V::Field::one()], &[&x, &y, &y, &start]);
+        V::custom_constraint(&CarryGate, &[V::Field::zero()], &[&y, &y, &y, &end]);
+        end.expose();
+    }
+    fn custom_gates<F: PrimeField>() -> Vec<Box<dyn CustomGate<F>>> {
+        vec![Box::new(CarryGate)]
+    }
+}
+
+fn wide_custom_gate_test<E: PairingEngine>() {
+    let circuit = Carry::build_wide::<E, 4>().unwrap();
+    let proof = circuit.prove([3_u64, 1, 5, 8]).unwrap();
+    assert!(circuit.verify(proof).is_ok());
+    assert!(matches!(
+        circuit.prove([3_u64, 1, 5, 9]),
+        Err(PlonkError::Unsatisfied(_))
+    ));
+    // the wire e is opened at the next row too
+    let circuit = Carry::build_wide::<E, 5>().unwrap();
+    let proof = circuit.prove([3_u64, 1, 5, 8]).unwrap();
+    assert!(circuit.verify(proof).is_ok());
+}
+fn wide_serialization_test<E: PairingEngine>() {
+    let (proving_key, verifying_key) = Carry::build_wide::<E, 4>().unwrap().split();
+    let mut bytes = vec![];
+    proving_key.serialize(&mut bytes).unwrap();
+    let circuit = DescriptionCircuit::<4, Carry>::default();
+    let proving_key = ProvingKey::<E, _, 4>::deserialize(&bytes[..], circuit).unwrap();
+    let mut bytes = vec![];
+    verifying_key.serialize(&mut bytes).unwrap();
+    // keys are read with the number of wires of their circuit
+    let gates = Carry::custom_gates::<E::Fr>;
+    assert!(VerifyingKey::<E, 5>::deserialize(&bytes[..], gates()).is_err());
+    let verifying_key = VerifyingKey::<E, 4>::deserialize(&bytes[..], gates()).unwrap();
+    let proof = proving_key.prove([3_u64, 1, 5, 8]).unwrap();
+    let mut bytes = vec![];
+    proof.serialize(&mut bytes).unwrap();
+    let proof = Proof::<E, 4>::deserialize(&bytes[..]).unwrap();
+    assert!(verifying_key.verify(proof).is_ok());
+}
+fn wide_check_test<E: PairingEngine>() {
+    let circuit = WideSum::build_wide::<E, 4>().unwrap();
+    assert_eq!(circuit.check((1_u64..=12).chain([664])), Ok(()));
+    let failures = unsatisfied(circuit.check((1_u64..=12).chain([665])));
+    assert_eq!(failures.len(), 1);
+    assert!(matches!(failures[0].kind, ConstraintKind::Copy { .. }));
+    assert_eq!(failures[0].wires.len(), 4);
+    assert_eq!(failures[0].vars.len(), 4);
+}
+
+/// `x²·x + x = out`
+struct Cube;
+impl CircuitDescription<2> for Cube {
+    fn run<V: Var>(inputs: [V; 2]) {
+        let [x, out] = inputs;
+        out.expose();
+        let square = x.clone() * x.clone();
+        let cube = square * x.clone();
+        (cube + x).assert_eq(&out);
+    }
+}
+
+fn report_test<E: PairingEngine>() {
+    let report = Cube::build::<E>().unwrap().report();
+    let gates = [("add", 1), ("mul", 2), ("public input", 1)]
+        .map(|(kind, count)| (kind.to_string(), count))
+        .into();
+    assert_eq!(report.gates, gates);
+    assert_eq!([report.used_rows, report.rows, report.padding()], [4, 8, 4]);
+    assert_eq!(report.padding_overhead(), 1.0);
+    // x in 4 cells, x², x³ and out in 2
+    assert_eq!([report.classes, report.copied_cells], [4, 10]);
+    assert_eq!(report.mean_class_size(), 2.5);
+    let largest = &report.largest_classes[0];
+    let cells = [(0, 1), (1, 1), (1, 2), (1, 3)].map(|(i, j)| Tag { i, j });
+    assert_eq!(largest.cells, cells);
+    assert_eq!(largest.vars.len(), 1);
+    // out and the sum are two variables in the same cells
+    let out = &report.largest_classes[1];
+    assert_eq!(out.cells, [Tag { i: 0, j: 0 }, Tag { i: 2, j: 3 }]);
+    assert_eq!(out.vars.len(), 2);
+    let text = report.to_string();
+    assert!(text.starts_with("3 wires, 4 gates in 8 rows, 4 of padding (100.0%)"));
+    assert!(text.contains("\n  4 cells: 1a 1b 2b 3b"));
+
+    // custom gates are counted by name
+    let report = Carry::build_wide::<E, 4>().unwrap().report();
+    assert_eq!(report.wires, 4);
+    assert_eq!(report.gates.get("carry"), Some(&2));
+}
+fn graph_test<E: PairingEngine>() {
+    let graph = Cube::build::<E>().unwrap().graph();
+    assert_eq!(graph.gates.len(), 4);
+    assert_eq!(graph.gates[1], (1, "mul".to_string()));
+    // a chain over each class
+    assert_eq!(graph.copies.len(), 3 + 1 + 1 + 1);
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph circuit {"));
+    assert!(dot.contains("    r0 [label=\"0: public input\"];"));
+    assert!(dot.contains("    r1 -> r1 [label=\"a = b\"];"));
+    assert!(dot.contains("    r0 -> r3 [label=\"a = c\"];"));
+    let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+    assert_eq!(json["wires"], 3);
+    assert_eq!(json["gates"][3]["kind"], "add");
+    assert_eq!(json["copies"].as_array().unwrap().len(), 6);
+    let copy =
+        serde_json::json!({ "from": { "row": 0, "wire": "a" }, "to": { "row": 3, "wire": "c" } });
+    assert!(json["copies"].as_array().unwrap().contains(&copy));
+}
+
+curve_tests!(
+    circuit2_test,
+    circuit2_test_bad_inputs,
+    shared_srs_test,
+    shared_srs_test_too_small,
+    circuit1_test,
+    public_inputs_test,
+    public_inputs_test_tampered,
+    public_inputs_test_wrong_length,
+    constants_test,
+    constants_test_bad_inputs,
+    custom_gates_test,
+    custom_gates_test_not_bool,
+    custom_gates_test_bad_next_row,
+    lookup_test,
+    lookup_test_out_of_range,
+    lookup_test_wrong_table,
+    split_keys_test,
+    keys_serialization_test,
+    lookup_keys_serialization_test,
+    proof_serialization_test,
+    lookup_proof_serialization_test,
+    proof_serialization_invalid_points_test,
+    transcript_hash_test,
+    transcript_binds_verifying_key_test,
+    verify_batch_test,
+    concurrent_proofs_test,
+    verify_batch_test_invalid,
+    hint_test,
+    runtime_circuit_test,
+    description_is_runtime_circuit_test,
+    check_test,
+    check_test_copy,
+    check_test_gates,
+    wide_circuit_test,
+    wide_custom_gate_test,
+    wide_serialization_test,
+    wide_check_test,
+    report_test,
+    graph_test,
+);
@@ -1,18 +1,209 @@
-use crate::{builder::CircuitBuilder, CompiledCircuit};
-use std::ops::{Add, Mul};
+use crate::{
+    builder::CircuitBuilder, gate::CustomGate, lookup::Table, CompiledCircuit, PlonkError, Srs,
+};
+use ark_ec::PairingEngine;
+use ark_ff::PrimeField;
+use std::{
+    marker::PhantomData,
+    ops::{Add, Mul, Neg, Sub},
+};
 
+/// A circuit whose shape is only known at runtime, like a merkle path of a configured depth.
+///
+/// `synthesize` runs once with build variables when compiling and once with compute variables
+/// for each proof, the circuit is kept in the compiled circuit so both runs see the same
+/// configuration. It must add the same rows every time.
+pub trait Circuit {
+    /// the number of inputs `synthesize` takes from the constraint system
+    fn inputs(&self) -> usize;
+    fn synthesize<V: Var>(&self, cs: &mut ConstraintSystem<V>);
+    /// the custom gates `synthesize` uses, each one adds its selector columns to the circuit
+    fn custom_gates<F: PrimeField>(&self) -> Vec<Box<dyn CustomGate<F>>> {
+        vec![]
+    }
+    /// the tables `synthesize` looks values up in
+    fn tables<F: PrimeField>(&self) -> Vec<Table<F>> {
+        vec![]
+    }
+    /// compiles the circuit for the curve `E` with a fresh srs
+    fn build<E: PairingEngine>(self) -> Result<CompiledCircuit<E, Self>, PlonkError>
+    where
+        Self: Sized,
+    {
+        CircuitBuilder::compile(self)
+    }
+    /// like `build` with an srs shared by the prover and the verifier
+    fn build_with_srs<E: PairingEngine>(
+        self,
+        srs: &Srs<E>,
+    ) -> Result<CompiledCircuit<E, Self>, PlonkError>
+    where
+        Self: Sized,
+    {
+        CircuitBuilder::compile_with_srs(self, srs)
+    }
+    /// like `build` with `W` wires instead of 3, at least 3
+    fn build_wide<E: PairingEngine, const W: usize>(
+        self,
+    ) -> Result<CompiledCircuit<E, Self, W>, PlonkError>
+    where
+        Self: Sized,
+    {
+        CircuitBuilder::compile(self)
+    }
+    /// like `build_with_srs` with `W` wires instead of 3
+    fn build_wide_with_srs<E: PairingEngine, const W: usize>(
+        self,
+        srs: &Srs<E>,
+    ) -> Result<CompiledCircuit<E, Self, W>, PlonkError>
+    where
+        Self: Sized,
+    {
+        CircuitBuilder::compile_with_srs(self, srs)
+    }
+}
+
+/// Hands the inputs of the proof to [`Circuit::synthesize`], in order.
+#[derive(Debug)]
+pub struct ConstraintSystem<V> {
+    inputs: std::vec::IntoIter<V>,
+}
+
+impl<V: Var> ConstraintSystem<V> {
+    pub(crate) fn new(inputs: Vec<V>) -> Self {
+        Self {
+            inputs: inputs.into_iter(),
+        }
+    }
+    /// the next input, panics when `Circuit::inputs` were already taken
+    pub fn input(&mut self) -> V {
+        self.inputs
+            .next()
+            .expect("the circuit takes more inputs than it declares")
+    }
+    /// the next n inputs
+    pub fn inputs(&mut self, n: usize) -> Vec<V> {
+        (0..n).map(|_| self.input()).collect()
+    }
+    /// the inputs not taken yet
+    pub fn remaining(&self) -> usize {
+        self.inputs.len()
+    }
+}
+
+/// A circuit with a number of inputs fixed at compile time, described by the type alone.
 pub trait CircuitDescription<const INPUTS: usize>: Sized {
     fn run<V: Var>(inputs: [V; INPUTS]);
-    fn build() -> CompiledCircuit<INPUTS, Self> {
-        CircuitBuilder::compile::<INPUTS, Self>()
+    /// the custom gates `run` uses, each one adds its selector columns to the circuit
+    fn custom_gates<F: PrimeField>() -> Vec<Box<dyn CustomGate<F>>> {
+        vec![]
+    }
+    /// the tables `run` looks values up in
+    fn tables<F: PrimeField>() -> Vec<Table<F>> {
+        vec![]
+    }
+    /// compiles the circuit for the curve `E` with a fresh srs
+    fn build<E: PairingEngine>(
+    ) -> Result<CompiledCircuit<E, DescriptionCircuit<INPUTS, Self>>, PlonkError> {
+        DescriptionCircuit::default().build()
+    }
+    /// like `build` with an srs shared by the prover and the verifier
+    fn build_with_srs<E: PairingEngine>(
+        srs: &Srs<E>,
+    ) -> Result<CompiledCircuit<E, DescriptionCircuit<INPUTS, Self>>, PlonkError> {
+        DescriptionCircuit::default().build_with_srs(srs)
+    }
+    /// like `build` with `W` wires instead of 3, at least 3
+    fn build_wide<E: PairingEngine, const W: usize>(
+    ) -> Result<CompiledCircuit<E, DescriptionCircuit<INPUTS, Self>, W>, PlonkError> {
+        DescriptionCircuit::default().build_wide()
+    }
+    /// like `build_with_srs` with `W` wires instead of 3
+    fn build_wide_with_srs<E: PairingEngine, const W: usize>(
+        srs: &Srs<E>,
+    ) -> Result<CompiledCircuit<E, DescriptionCircuit<INPUTS, Self>, W>, PlonkError> {
+        DescriptionCircuit::default().build_wide_with_srs(srs)
+    }
+}
+
+/// The [`Circuit`] of a description, it holds nothing so the compiled circuit is `Send + Sync`
+/// whatever the description.
+pub struct DescriptionCircuit<const INPUTS: usize, D>(PhantomData<fn() -> D>);
+
+impl<const INPUTS: usize, D> Default for DescriptionCircuit<INPUTS, D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<const INPUTS: usize, D> std::fmt::Debug for DescriptionCircuit<INPUTS, D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "DescriptionCircuit<{}, {}>",
+            INPUTS,
+            std::any::type_name::<D>()
+        )
+    }
+}
+
+impl<const INPUTS: usize, D: CircuitDescription<INPUTS>> Circuit for DescriptionCircuit<INPUTS, D> {
+    fn inputs(&self) -> usize {
+        INPUTS
+    }
+    fn synthesize<V: Var>(&self, cs: &mut ConstraintSystem<V>) {
+        D::run(std::array::from_fn(|_| cs.input()));
+    }
+    fn custom_gates<F: PrimeField>(&self) -> Vec<Box<dyn CustomGate<F>>> {
+        D::custom_gates()
+    }
+    fn tables<F: PrimeField>(&self) -> Vec<Table<F>> {
+        D::tables()
     }
 }
 
 pub trait Var
 where
-    Self: Sized + Add<Output = Self> + Mul<Output = Self> + Clone,
+    Self: Sized
+        + Add<Output = Self>
+        + Sub<Output = Self>
+        + Mul<Output = Self>
+        + Mul<<Self as Var>::Field, Output = Self>
+        + Neg<Output = Self>
+        + Clone,
 {
+    /// the field of the circuit, the scalar field of the curve it is compiled for
+    type Field: PrimeField;
     fn assert_eq(&self, other: &Self);
+    /// makes the variable a public input, its value ends up in `Proof::public_inputs`
+    /// in the order the variables were exposed
+    fn expose(&self);
+    /// a value fixed in the circuit, it is compiled into the `q_c` selector of the
+    /// gates using it instead of being a witness
+    fn constant(value: impl Into<Self::Field>) -> Self;
+    /// adds a row of a registered custom gate with `q_o = 1`, `inputs` go in columns a and b
+    /// and the output in column c is given by `CustomGate::output`. When the inputs are all
+    /// constants the output is a constant and no row is added
+    fn custom_gate<G: CustomGate<Self::Field>>(
+        gate: &G,
+        selectors: &[Self::Field],
+        inputs: &[&Self],
+    ) -> Self;
+    /// adds a row of a registered custom gate with `q_o = 0`, `inputs` fill columns a, b and c
+    fn custom_constraint<G: CustomGate<Self::Field>>(
+        gate: &G,
+        selectors: &[Self::Field],
+        inputs: &[&Self],
+    );
+    /// constrains the value to be an entry of `table`, which must be declared in `CircuitDescription::tables`
+    fn lookup(&self, table: &Table<Self::Field>);
+    /// a new witness computed by `f` from the values of `inputs` when proving, in a row of its own,
+    /// the hint of constants is a constant. Nothing constrains its value, the circuit has to do it
+    /// with the other methods
+    fn hint(inputs: &[&Self], f: impl FnOnce(&[Self::Field]) -> Self::Field) -> Self;
+    /// `Σ k_i·x_i` over the terms `(k_i, x_i)`, constant terms are folded in. In a circuit with W
+    /// wires the first row sums `W - 1` wires and each next row adds `W - 2` more to the sum of the
+    /// row before, instead of a row for each addition and scaling
+    fn linear_combination(terms: &[(Self::Field, &Self)]) -> Self;
 }
 
 pub trait VariableTrait
@@ -29,19 +220,20 @@ mod tests {
         _maker: std::marker::PhantomData<T>,
         // There is no field of type `T` here
     }
-    
+
     impl<T> MyStruct<T> {
         fn new(data: i32) -> Self {
-            MyStruct { data, 
-            _maker: std::marker::PhantomData }
+            MyStruct {
+                data,
+                _maker: std::marker::PhantomData,
+            }
         }
     }
-    
+
     #[test]
     fn main() {
         // We can create MyStruct with any type, but the type is not stored
         let my_struct: MyStruct<String> = MyStruct::new(10);
         println!("Data: {}", my_struct.data);
     }
-    
 }
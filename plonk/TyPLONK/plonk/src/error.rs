@@ -0,0 +1,73 @@
+use crate::builder::ConstraintFailure;
+use kgz::KzgError;
+use std::fmt::Display;
+
+/// why a circuit could not be compiled, or a proof made or verified
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlonkError {
+    /// the srs has `size` points and the circuit needs `needed`
+    SrsTooSmall { needed: usize, size: usize },
+    /// the circuit takes `expected` inputs and `got` were given
+    Inputs { expected: usize, got: usize },
+    /// the inputs break the constraints found by `CompiledCircuit::check`, none are listed when
+    /// only the degree of the quotient shows it
+    Unsatisfied(Vec<ConstraintFailure>),
+    /// the proof does not have the shape of the circuit, its evaluations and commitments do not
+    /// match the ones the circuit opens
+    MalformedProof,
+    /// the proof has `got` public inputs and the circuit exposes `expected`
+    PublicInputs { expected: usize, got: usize },
+    /// a well formed proof that does not verify
+    InvalidProof,
+    /// the EVM verifier does not support the named part of the circuit
+    UnsupportedOnEvm(&'static str),
+}
+
+impl Display for PlonkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SrsTooSmall { needed, size } => {
+                write!(
+                    f,
+                    "the srs has {} points, the circuit needs {}",
+                    size, needed
+                )
+            }
+            Self::Inputs { expected, got } => {
+                write!(
+                    f,
+                    "the circuit takes {} inputs, {} were given",
+                    expected, got
+                )
+            }
+            Self::Unsatisfied(failures) if failures.is_empty() => {
+                write!(f, "the inputs do not satisfy the circuit")
+            }
+            Self::Unsatisfied(failures) => {
+                write!(f, "the inputs break {} constraints", failures.len())?;
+                for failure in failures {
+                    write!(f, "\n  row {}: {:?}", failure.row, failure.kind)?;
+                }
+                Ok(())
+            }
+            Self::MalformedProof => write!(f, "the proof does not match the circuit"),
+            Self::PublicInputs { expected, got } => write!(
+                f,
+                "the proof has {} public inputs, the circuit exposes {}",
+                got, expected
+            ),
+            Self::InvalidProof => write!(f, "the proof is not valid"),
+            Self::UnsupportedOnEvm(part) => write!(f, "the evm verifier does not support {}", part),
+        }
+    }
+}
+impl std::error::Error for PlonkError {}
+
+impl From<KzgError> for PlonkError {
+    fn from(error: KzgError) -> Self {
+        match error {
+            KzgError::SrsTooSmall { needed, size } => Self::SrsTooSmall { needed, size },
+            KzgError::EvaluationCount { .. } => Self::MalformedProof,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Verifying proofs made over BN254 on EVM chains.
+//!
+//! [`VerifyingKey::solidity_verifier`] writes a contract with a single function,
+//! `verify(bytes calldata proof) returns (bool)`, whose body is an assembly block doing the checks
+//! of [`VerifyingKey::verify_with_hash`] with
+//! [`KeccakHash`](crate::transcript::KeccakHash): proofs must be made with
+//! `prove_with_hash::<KeccakHash>`. [`VerifyingKey::evm_bytecode`] assembles the same program to
+//! deployable bytecode, for when no Solidity compiler is at hand.
+//!
+//! The curve operations use the BN254 precompiles, so circuits with custom gates or lookups, whose
+//! openings the contract does not know, are not supported, nor circuits with more than 3 wires.
+//!
+//! [`Proof::calldata`] encodes a proof as the argument of `verify`: the public inputs, the points
+//! a, b, c, z, t_lo, t_mid, t_hi, and the two opening witnesses as `(x, y)`, then the evaluations
+//! of a, b, c, σ_a, σ_b, z and z at the next row, every value as a 32 byte big endian word.
+use crate::{PlonkError, Proof, VerifyingKey};
+use ark_bn254::Bn254;
+
+mod program;
+#[cfg(test)]
+mod test;
+mod verifier;
+
+/// the names of the points of the proof in the calldata
+const PROOF_POINTS: [&str; 9] = ["A", "B", "C", "Z", "T0", "T1", "T2", "W1", "W2"];
+/// the names of the evaluations of the proof in the calldata
+const PROOF_SCALARS: [&str; 7] = [
+    "A_EVAL",
+    "B_EVAL",
+    "C_EVAL",
+    "SIGMA_A_EVAL",
+    "SIGMA_B_EVAL",
+    "Z_EVAL",
+    "ZW_EVAL",
+];
+/// the first 4 bytes of the Keccak-256 of `verify(bytes)`
+const SELECTOR: [u8; 4] = [0x8e, 0x76, 0x0a, 0xfe];
+
+impl VerifyingKey<Bn254> {
+    /// the source of a Solidity contract verifying the proofs of the circuit
+    pub fn solidity_verifier(&self) -> Result<String, PlonkError> {
+        let program = self.evm_program()?;
+        let mut source = String::new();
+        source.push_str("// SPDX-License-Identifier: MIT\n");
+        source.push_str("pragma solidity >=0.8.0;\n\n");
+        source.push_str("/// verifies the TyPLONK proofs of a circuit, made over BN254 with the Keccak transcript\n");
+        source.push_str("contract TyPlonkVerifier {\n");
+        source.push_str(&program.solidity_constants("    "));
+        source.push('\n');
+        source.push_str("    /// the proof is encoded by `plonk::Proof::calldata`\n");
+        source.push_str("    function verify(bytes calldata) external view returns (bool) {\n");
+        source.push_str("        assembly {\n");
+        source.push_str(&program.yul("            "));
+        source.push_str("        }\n");
+        source.push_str("    }\n");
+        source.push_str("}\n");
+        Ok(source)
+    }
+    /// the code deploying the verifier, runs the program of [`Self::solidity_verifier`]
+    pub fn evm_bytecode(&self) -> Result<Vec<u8>, PlonkError> {
+        let runtime = self.evm_program()?.bytecode();
+        Ok(program::deployment(&runtime))
+    }
+    fn evm_program(&self) -> Result<program::Program, PlonkError> {
+        if !self.custom.is_empty() {
+            return Err(PlonkError::UnsupportedOnEvm("custom gates"));
+        }
+        if self.lookup.is_some() {
+            return Err(PlonkError::UnsupportedOnEvm("lookups"));
+        }
+        Ok(verifier::program(self))
+    }
+}
+
+impl Proof<Bn254> {
+    /// the call of `verify(bytes)` with this proof
+    pub fn calldata(&self) -> Result<Vec<u8>, PlonkError> {
+        if self.next_row.is_some() {
+            return Err(PlonkError::UnsupportedOnEvm("custom gates"));
+        }
+        if self.lookup.is_some() {
+            return Err(PlonkError::UnsupportedOnEvm("lookups"));
+        }
+        let mut words = self
+            .public_inputs
+            .iter()
+            .map(|input| verifier::fr_word(*input))
+            .collect::<Vec<_>>();
+        let points = [
+            self.wires[0].commitment.0,
+            self.wires[1].commitment.0,
+            self.wires[2].commitment.0,
+            self.permutation.commitment.0,
+            self.t[0].0,
+            self.t[1].0,
+            self.t[2].0,
+            self.openings[0],
+            self.openings[1],
+        ];
+        words.extend(points.iter().flat_map(verifier::g1_words));
+        let [evals, next_row] = self.evaluations();
+        words.extend(evals.into_iter().chain(next_row).map(verifier::fr_word));
+
+        let mut calldata = SELECTOR.to_vec();
+        calldata.extend(program::word(0x20));
+        calldata.extend(program::word(32 * words.len() as u64));
+        calldata.extend(words.into_iter().flatten());
+        Ok(calldata)
+    }
+}
@@ -0,0 +1,404 @@
+//! A straight-line EVM program, printed as the Yul of the Solidity contract or assembled to bytecode.
+//!
+//! Statements are Yul builtins applied to literals, named constants and other builtins, which are
+//! exactly the opcodes of the EVM, so the two outputs run the same instructions. There are no
+//! jumps, and the only variables are a few large constants declared first, which the bytecode keeps
+//! at the bottom of the stack and copies with `DUPn`: every other value is kept in a named memory
+//! slot, which also keeps the Yul clear of the stack limit of the Solidity compiler.
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Op {
+    Add,
+    Sub,
+    Mod,
+    AddMod,
+    MulMod,
+    Lt,
+    Gt,
+    Eq,
+    IsZero,
+    And,
+    Or,
+    Not,
+    Shl,
+    Shr,
+    Keccak256,
+    CallDataLoad,
+    CallDataCopy,
+    MLoad,
+    MStore,
+    MStore8,
+    Gas,
+    Return,
+    StaticCall,
+}
+
+impl Op {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Sub => "sub",
+            Self::Mod => "mod",
+            Self::AddMod => "addmod",
+            Self::MulMod => "mulmod",
+            Self::Lt => "lt",
+            Self::Gt => "gt",
+            Self::Eq => "eq",
+            Self::IsZero => "iszero",
+            Self::And => "and",
+            Self::Or => "or",
+            Self::Not => "not",
+            Self::Shl => "shl",
+            Self::Shr => "shr",
+            Self::Keccak256 => "keccak256",
+            Self::CallDataLoad => "calldataload",
+            Self::CallDataCopy => "calldatacopy",
+            Self::MLoad => "mload",
+            Self::MStore => "mstore",
+            Self::MStore8 => "mstore8",
+            Self::Gas => "gas",
+            Self::Return => "return",
+            Self::StaticCall => "staticcall",
+        }
+    }
+    fn opcode(self) -> u8 {
+        match self {
+            Self::Add => 0x01,
+            Self::Sub => 0x03,
+            Self::Mod => 0x06,
+            Self::AddMod => 0x08,
+            Self::MulMod => 0x09,
+            Self::Lt => 0x10,
+            Self::Gt => 0x11,
+            Self::Eq => 0x14,
+            Self::IsZero => 0x15,
+            Self::And => 0x16,
+            Self::Or => 0x17,
+            Self::Not => 0x19,
+            Self::Shl => 0x1b,
+            Self::Shr => 0x1c,
+            Self::Keccak256 => 0x20,
+            Self::CallDataLoad => 0x35,
+            Self::CallDataCopy => 0x37,
+            Self::MLoad => 0x51,
+            Self::MStore => 0x52,
+            Self::MStore8 => 0x53,
+            Self::Gas => 0x5a,
+            Self::Return => 0xf3,
+            Self::StaticCall => 0xfa,
+        }
+    }
+}
+
+/// a 256 bit word, big endian
+pub(super) type Word = [u8; 32];
+
+pub(super) fn word(value: u64) -> Word {
+    let mut word = [0; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Expr {
+    Literal(Word),
+    /// a constant of the contract, by index
+    Constant(usize),
+    /// a variable holding a constant, by index
+    Variable(usize),
+    Call(Op, Vec<Expr>),
+}
+
+impl Expr {
+    pub fn call<const N: usize>(op: Op, args: [Expr; N]) -> Self {
+        Self::Call(op, args.to_vec())
+    }
+    pub fn literal(value: u64) -> Self {
+        Self::Literal(word(value))
+    }
+}
+
+/// a line of the program, statements leave nothing on the stack
+#[derive(Debug, Clone)]
+enum Line {
+    Comment(String),
+    Statement(Expr),
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Program {
+    /// the named constants, declared in the contract and pushed as literals in the bytecode
+    constants: Vec<(String, Word)>,
+    /// the variables by name, each holds a constant
+    variables: Vec<(String, usize)>,
+    lines: Vec<Line>,
+}
+
+impl Program {
+    /// a named constant
+    pub fn constant(&mut self, name: impl Into<String>, value: Word) -> Expr {
+        let name = name.into();
+        debug_assert!(self.constants.iter().all(|(other, _)| *other != name));
+        self.constants.push((name, value));
+        Expr::Constant(self.constants.len() - 1)
+    }
+    /// a variable holding `constant`, cheaper to use than a large constant
+    pub fn variable(&mut self, name: impl Into<String>, constant: &Expr) -> Expr {
+        let Expr::Constant(index) = constant else {
+            panic!("not a constant")
+        };
+        self.variables.push((name.into(), *index));
+        Expr::Variable(self.variables.len() - 1)
+    }
+    /// the value of a constant, known once the rest of the program is
+    pub fn set_constant(&mut self, constant: &Expr, value: Word) {
+        match constant {
+            Expr::Constant(index) => self.constants[*index].1 = value,
+            _ => panic!("not a constant"),
+        }
+    }
+    pub fn comment(&mut self, comment: impl Into<String>) {
+        self.lines.push(Line::Comment(comment.into()));
+    }
+    /// `op` must not return a value, like `mstore`
+    pub fn statement<const N: usize>(&mut self, op: Op, args: [Expr; N]) {
+        self.lines.push(Line::Statement(Expr::call(op, args)));
+    }
+
+    /// the declarations of the constants, one per line
+    pub fn solidity_constants(&self, indent: &str) -> String {
+        let mut out = String::new();
+        for (name, value) in self.constants.iter() {
+            let value = hex(value);
+            writeln!(
+                out,
+                "{}uint256 internal constant {} = {};",
+                indent, name, value
+            )
+            .unwrap();
+        }
+        out
+    }
+    /// the statements as Yul, one per line
+    pub fn yul(&self, indent: &str) -> String {
+        let mut out = String::new();
+        for (name, constant) in self.variables.iter() {
+            let constant = &self.constants[*constant].0;
+            writeln!(out, "{}let {} := {}", indent, name, constant).unwrap();
+        }
+        for line in self.lines.iter() {
+            match line {
+                Line::Comment(comment) => writeln!(out, "{}// {}", indent, comment).unwrap(),
+                Line::Statement(expr) => {
+                    writeln!(out, "{}{}", indent, self.yul_expr(expr)).unwrap()
+                }
+            }
+        }
+        out
+    }
+    fn yul_expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Literal(value) => hex(value),
+            Expr::Constant(index) => self.constants[*index].0.clone(),
+            Expr::Variable(index) => self.variables[*index].0.clone(),
+            Expr::Call(op, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.yul_expr(arg))
+                    .collect::<Vec<_>>();
+                format!("{}({})", op.name(), args.join(", "))
+            }
+        }
+    }
+
+    /// the runtime bytecode
+    pub fn bytecode(&self) -> Vec<u8> {
+        let mut code = vec![];
+        // the first variable ends up on top
+        for (_, constant) in self.variables.iter().rev() {
+            push(&self.constants[*constant].1, &mut code);
+        }
+        for line in self.lines.iter() {
+            if let Line::Statement(expr) = line {
+                self.assemble(expr, 0, &mut code);
+            }
+        }
+        code
+    }
+    /// the arguments are pushed last to first, so the first one is on top of the stack. `depth`
+    /// values are above the variables
+    fn assemble(&self, expr: &Expr, depth: usize, code: &mut Vec<u8>) {
+        match expr {
+            Expr::Literal(value) => push(value, code),
+            Expr::Constant(index) => push(&self.constants[*index].1, code),
+            Expr::Variable(index) => {
+                let position = depth + 1 + index;
+                assert!(position <= 16, "variable out of reach of DUP16");
+                code.push(0x7f + position as u8);
+            }
+            Expr::Call(op, args) => {
+                for (pushed, arg) in args.iter().rev().enumerate() {
+                    self.assemble(arg, depth + pushed, code);
+                }
+                code.push(op.opcode());
+            }
+        }
+    }
+}
+
+/// `PUSHn` with the shortest n, at least 1
+fn push(value: &Word, code: &mut Vec<u8>) {
+    let start = value.iter().position(|byte| *byte != 0).unwrap_or(31);
+    let bytes = &value[start..];
+    code.push(0x5f + bytes.len() as u8);
+    code.extend_from_slice(bytes);
+}
+
+/// decimal below 10, hexadecimal without leading zeros otherwise
+fn hex(value: &Word) -> String {
+    let start = value.iter().position(|byte| *byte != 0).unwrap_or(31);
+    match (start, value[31]) {
+        (31, low) if low < 10 => low.to_string(),
+        _ => {
+            let digits = value[start..]
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>();
+            format!("0x{}", digits)
+        }
+    }
+}
+
+/// code deploying `runtime`: copies it to memory and returns it
+pub(super) fn deployment(runtime: &[u8]) -> Vec<u8> {
+    // PUSH2 size, PUSH2 offset, PUSH1 0, CODECOPY, PUSH2 size, PUSH1 0, RETURN
+    const LENGTH: u16 = 15;
+    let size = u16::try_from(runtime.len()).expect("the runtime fits in 64KB");
+    let mut code = vec![0x61];
+    code.extend(size.to_be_bytes());
+    code.push(0x61);
+    code.extend(LENGTH.to_be_bytes());
+    code.extend([0x60, 0x00, 0x39, 0x61]);
+    code.extend(size.to_be_bytes());
+    code.extend([0x60, 0x00, 0xf3]);
+    debug_assert_eq!(code.len(), LENGTH as usize);
+    code.extend_from_slice(runtime);
+    code
+}
+
+/// every builtin, to read them back by name
+#[cfg(test)]
+const OPS: [Op; 23] = [
+    Op::Add,
+    Op::Sub,
+    Op::Mod,
+    Op::AddMod,
+    Op::MulMod,
+    Op::Lt,
+    Op::Gt,
+    Op::Eq,
+    Op::IsZero,
+    Op::And,
+    Op::Or,
+    Op::Not,
+    Op::Shl,
+    Op::Shr,
+    Op::Keccak256,
+    Op::CallDataLoad,
+    Op::CallDataCopy,
+    Op::MLoad,
+    Op::MStore,
+    Op::MStore8,
+    Op::Gas,
+    Op::Return,
+    Op::StaticCall,
+];
+
+#[cfg(test)]
+impl Program {
+    /// reads a program back from the Solidity source of `VerifyingKey::solidity_verifier`: the
+    /// constants of the contract, then the variables and the statements of its assembly block
+    pub fn from_solidity(source: &str) -> Self {
+        let mut program = Self::default();
+        let mut lines = source.lines().map(str::trim);
+        for line in lines.by_ref().take_while(|line| *line != "assembly {") {
+            let declaration = line
+                .strip_prefix("uint256 internal constant ")
+                .and_then(|line| line.strip_suffix(';'));
+            if let Some((name, value)) = declaration.and_then(|line| line.split_once(" = ")) {
+                program.constant(name, parse_word(value));
+            }
+        }
+        for line in lines.take_while(|line| *line != "}") {
+            if let Some((name, constant)) = line
+                .strip_prefix("let ")
+                .and_then(|line| line.split_once(" := "))
+            {
+                let constant = program.parse_expr(&mut { constant });
+                program.variable(name, &constant);
+            } else if let Some(comment) = line.strip_prefix("// ") {
+                program.comment(comment);
+            } else {
+                let mut rest = line;
+                let statement = program.parse_expr(&mut rest);
+                assert!(rest.is_empty(), "one statement per line: {}", line);
+                program.lines.push(Line::Statement(statement));
+            }
+        }
+        program
+    }
+    /// a literal, a name or a builtin applied to expressions, advances `text` past it
+    fn parse_expr(&self, text: &mut &str) -> Expr {
+        let end = text.find(['(', ')', ',']).unwrap_or(text.len());
+        let token = text[..end].trim();
+        *text = &text[end..];
+        if let Some(rest) = text.strip_prefix('(') {
+            *text = rest;
+            let op = OPS
+                .into_iter()
+                .find(|op| op.name() == token)
+                .unwrap_or_else(|| panic!("unknown builtin {}", token));
+            let mut args = vec![];
+            loop {
+                if let Some(rest) = text.trim_start().strip_prefix(')') {
+                    *text = rest;
+                    return Expr::Call(op, args);
+                }
+                args.push(self.parse_expr(text));
+                if let Some(rest) = text.trim_start().strip_prefix(',') {
+                    *text = rest;
+                }
+            }
+        }
+        if token.starts_with(|c: char| c.is_ascii_digit()) {
+            return Expr::Literal(parse_word(token));
+        }
+        if let Some(index) = self.variables.iter().position(|(name, _)| name == token) {
+            return Expr::Variable(index);
+        }
+        let index = self
+            .constants
+            .iter()
+            .position(|(name, _)| name == token)
+            .unwrap_or_else(|| panic!("unknown name {}", token));
+        Expr::Constant(index)
+    }
+}
+
+/// the inverse of [`hex`]
+#[cfg(test)]
+fn parse_word(text: &str) -> Word {
+    match text.strip_prefix("0x") {
+        Some(digits) => {
+            let bytes = (0..digits.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+                .collect::<Vec<_>>();
+            let mut word = [0; 32];
+            word[32 - bytes.len()..].copy_from_slice(&bytes);
+            word
+        }
+        None => word(text.parse().unwrap()),
+    }
+}
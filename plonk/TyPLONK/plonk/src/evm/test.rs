@@ -0,0 +1,194 @@
+use super::{program::Program, SELECTOR};
+use crate::{
+    description::{CircuitDescription, Var},
+    gate::{BoolGate, CustomGate},
+    transcript::{Blake2bHash, KeccakHash},
+    Bn254, PlonkError, VerifyingKey,
+};
+use ark_bn254::Fr;
+use ark_ff::{One, PrimeField};
+use revm::{
+    db::InMemoryDB,
+    primitives::{Address, Bytes, ExecutionResult, Output, TxKind},
+    Evm,
+};
+use sha3::{Digest, Keccak256};
+
+struct Circuit;
+impl CircuitDescription<3> for Circuit {
+    fn run<V: Var>(inputs: [V; 3]) {
+        let [a, b, c] = inputs;
+        a.expose();
+        let d = a * b;
+        c.expose();
+        d.assert_eq(&c);
+    }
+}
+struct Private;
+impl CircuitDescription<3> for Private {
+    fn run<V: Var>(inputs: [V; 3]) {
+        let [a, b, c] = inputs;
+        let d = a.clone() * a + b.clone() * b;
+        d.assert_eq(&(c.clone() * c));
+    }
+}
+struct Custom;
+impl CircuitDescription<1> for Custom {
+    fn run<V: Var>(inputs: [V; 1]) {
+        let [a] = inputs;
+        V::custom_constraint(&BoolGate, &[V::Field::one()], &[&a]);
+    }
+    fn custom_gates<F: PrimeField>() -> Vec<Box<dyn CustomGate<F>>> {
+        vec![Box::new(BoolGate)]
+    }
+}
+
+/// deploys the verifier in an empty in-memory chain and calls it with each calldata
+fn run_verifier(vk: &VerifyingKey<Bn254>, calls: Vec<Vec<u8>>) -> Vec<bool> {
+    run_contract(vk.evm_bytecode().unwrap(), calls)
+}
+/// deploys the contract with the deployment code `bytecode` and calls it with each calldata
+fn run_contract(bytecode: Vec<u8>, calls: Vec<Vec<u8>>) -> Vec<bool> {
+    let mut evm = Evm::builder()
+        .with_db(InMemoryDB::default())
+        .modify_tx_env(|tx| {
+            tx.caller = Address::ZERO;
+            tx.gas_limit = 30_000_000;
+            tx.transact_to = TxKind::Create;
+            tx.data = Bytes::from(bytecode);
+        })
+        .build();
+    let address = match evm.transact_commit().unwrap() {
+        ExecutionResult::Success {
+            output: Output::Create(_, Some(address)),
+            ..
+        } => address,
+        result => panic!("deployment failed: {:?}", result),
+    };
+    calls
+        .into_iter()
+        .map(|calldata| {
+            evm.tx_mut().transact_to = TxKind::Call(address);
+            evm.tx_mut().data = Bytes::from(calldata);
+            match evm.transact_commit().unwrap() {
+                ExecutionResult::Success {
+                    output: Output::Call(output),
+                    ..
+                } => {
+                    assert_eq!(output.len(), 32);
+                    match output[31] {
+                        0 => false,
+                        1 => true,
+                        _ => panic!("not a bool: {:?}", output),
+                    }
+                }
+                result => panic!("call failed: {:?}", result),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn selector() {
+    assert_eq!(Keccak256::digest(b"verify(bytes)")[..4], SELECTOR);
+}
+
+#[test]
+fn evm_verifier_test() {
+    let circuit = Circuit::build::<Bn254>().unwrap();
+    let proof = circuit.prove_with_hash::<KeccakHash>([3, 4, 12]).unwrap();
+    let calldata = proof.calldata().unwrap();
+    assert!(circuit.verify_with_hash::<KeccakHash>(proof).is_ok());
+    assert_eq!(
+        run_verifier(circuit.verifying_key(), vec![calldata]),
+        [true]
+    );
+}
+#[test]
+fn evm_verifier_test_no_public_inputs() {
+    let circuit = Private::build::<Bn254>().unwrap();
+    let proof = circuit.prove_with_hash::<KeccakHash>([3, 4, 5]).unwrap();
+    let calldata = proof.calldata().unwrap();
+    assert_eq!(
+        run_verifier(circuit.verifying_key(), vec![calldata]),
+        [true]
+    );
+}
+#[test]
+fn evm_verifier_test_tampered() {
+    let circuit = Circuit::build::<Bn254>().unwrap();
+    let mut calls = vec![];
+    let mut proof = circuit.prove_with_hash::<KeccakHash>([3, 4, 12]).unwrap();
+    proof.public_inputs[1] = Fr::from(13);
+    calls.push(proof.calldata().unwrap());
+    let mut proof = circuit.prove_with_hash::<KeccakHash>([3, 4, 12]).unwrap();
+    proof.wires[0].eval += Fr::one();
+    calls.push(proof.calldata().unwrap());
+    // a scalar above r
+    let proof = circuit.prove_with_hash::<KeccakHash>([3, 4, 12]).unwrap();
+    let mut calldata = proof.calldata().unwrap();
+    let last = calldata.len() - 32;
+    calldata[last..].fill(0xff);
+    calls.push(calldata);
+    // a point off the curve
+    let mut calldata = proof.calldata().unwrap();
+    calldata[0x44 + 2 * 32 + 31] ^= 1;
+    calls.push(calldata);
+    // not the whole proof
+    let mut calldata = proof.calldata().unwrap();
+    calldata.truncate(calldata.len() - 32);
+    calldata[0x24 + 31] -= 32;
+    calls.push(calldata);
+    assert_eq!(run_verifier(circuit.verifying_key(), calls), [false; 5]);
+}
+#[test]
+fn evm_verifier_test_other_hash() {
+    let circuit = Circuit::build::<Bn254>().unwrap();
+    let proof = circuit.prove_with_hash::<Blake2bHash>([3, 4, 12]).unwrap();
+    let calldata = proof.calldata().unwrap();
+    assert_eq!(
+        run_verifier(circuit.verifying_key(), vec![calldata]),
+        [false]
+    );
+}
+
+#[test]
+fn solidity_verifier_test() {
+    let circuit = Circuit::build::<Bn254>().unwrap();
+    let vk = circuit.verifying_key();
+    let source = vk.solidity_verifier().unwrap();
+    assert!(source.contains("contract TyPlonkVerifier {"));
+    assert!(source.contains("function verify(bytes calldata) external view returns (bool)"));
+    // the verifying data is declared as constants
+    for name in [
+        "Q_L_X",
+        "SIGMA_C_Y",
+        "COSET_2",
+        "DOMAIN_SIZE",
+        "OMEGA",
+        "G2S_3",
+        "G2_0",
+    ] {
+        let declaration = format!("uint256 internal constant {} = ", name);
+        assert!(source.contains(&declaration), "{} is not declared", name);
+    }
+    // the assembly of the contract is the program of the bytecode, and runs as it does
+    let program = Program::from_solidity(&source);
+    let bytecode = super::program::deployment(&program.bytecode());
+    assert_eq!(bytecode, vk.evm_bytecode().unwrap());
+    let proof = circuit.prove_with_hash::<KeccakHash>([3, 4, 12]).unwrap();
+    let valid = proof.calldata().unwrap();
+    let mut tampered = proof;
+    tampered.public_inputs[0] = Fr::from(5);
+    let tampered = tampered.calldata().unwrap();
+    assert_eq!(run_contract(bytecode, vec![valid, tampered]), [true, false]);
+}
+#[test]
+fn evm_verifier_test_custom_gates() {
+    let circuit = Custom::build::<Bn254>().unwrap();
+    let unsupported = PlonkError::UnsupportedOnEvm("custom gates");
+    let source = circuit.verifying_key().solidity_verifier();
+    assert_eq!(source, Err(unsupported.clone()));
+    let proof = circuit.prove_with_hash::<KeccakHash>([1]).unwrap();
+    assert_eq!(proof.calldata(), Err(unsupported));
+}
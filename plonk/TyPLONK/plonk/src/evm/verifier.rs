@@ -0,0 +1,654 @@
+//! The verifier as a program: the checks of `VerifyingKey::verify_with_hash::<KeccakHash>`, with
+//! the transcript rebuilt byte for byte in memory and the curve operations done by the precompiles.
+use super::program::{word, Expr, Op, Program, Word};
+use super::{PROOF_POINTS, PROOF_SCALARS, SELECTOR};
+use crate::{transcript::KeccakHash, VerifyingKey};
+use ark_bn254::{Bn254, Fq, Fr, G1Affine, G2Affine};
+use ark_ec::AffineCurve;
+use ark_ff::{BigInteger, Field, FpParameters, PrimeField};
+use ark_poly::EvaluationDomain;
+use std::collections::HashMap;
+
+/// where the proof starts in the calldata: selector, offset and length of `bytes`
+const CALLDATA_START: u64 = 0x44;
+/// the precompiles
+const EC_ADD: u64 = 0x06;
+const EC_MUL: u64 = 0x07;
+const EC_PAIRING: u64 = 0x08;
+const MOD_EXP: u64 = 0x05;
+
+pub(super) fn fr_word(value: Fr) -> Word {
+    value.into_repr().to_bytes_be().try_into().unwrap()
+}
+fn fq_word(value: Fq) -> Word {
+    value.into_repr().to_bytes_be().try_into().unwrap()
+}
+/// the coordinates of a point, (0, 0) for the point at infinity
+pub(super) fn g1_words(point: &G1Affine) -> [Word; 2] {
+    match point.infinity {
+        true => [[0; 32]; 2],
+        false => [fq_word(point.x), fq_word(point.y)],
+    }
+}
+/// the imaginary part of each coordinate first, as the pairing precompile reads them
+fn g2_words(point: &G2Affine) -> [Word; 4] {
+    [point.x.c1, point.x.c0, point.y.c1, point.y.c0].map(fq_word)
+}
+
+fn call<const N: usize>(op: Op, args: [Expr; N]) -> Expr {
+    Expr::call(op, args)
+}
+fn lit(value: u64) -> Expr {
+    Expr::literal(value)
+}
+
+struct Builder {
+    program: Program,
+    /// the memory slots by name, and the next free address
+    slots: HashMap<String, Expr>,
+    free: u64,
+    r: Expr,
+    q: Expr,
+    /// the inverse of x is x^(r-2)
+    r_minus_2: Expr,
+    /// 2^256 mod r, the weight of the second hash of a challenge
+    wide: Expr,
+    success: Expr,
+    scratch: Expr,
+    /// the transcript is placed after every slot, its address is set last
+    transcript: Expr,
+    /// bytes of the transcript written to memory, and constant bytes not written yet
+    written: u64,
+    pending: Vec<u8>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        let mut program = Program::default();
+        let r = program.constant("R", modulus::<Fr>());
+        let r = program.variable("r", &r);
+        let q = program.constant("Q", modulus::<Fq>());
+        let q = program.variable("q", &q);
+        let r_minus_2 = program.constant("R_MINUS_2", fr_word(-Fr::from(2_u64)));
+        let wide = program.constant("WIDE", fr_word(Fr::from(2_u64).pow([256])));
+        let transcript = program.constant("TRANSCRIPT", [0; 32]);
+        let mut builder = Self {
+            program,
+            slots: HashMap::new(),
+            free: 0x80,
+            r,
+            q,
+            r_minus_2,
+            wide,
+            success: lit(0),
+            scratch: lit(0),
+            transcript,
+            written: 0,
+            pending: vec![],
+        };
+        builder.success = builder.alloc("SUCCESS", 1);
+        builder.scratch = builder.alloc("SCRATCH", 12);
+        builder
+    }
+
+    /// `words` consecutive memory words
+    fn alloc(&mut self, name: &str, words: u64) -> Expr {
+        let slot = self.program.constant(name, word(self.free));
+        self.free += 32 * words;
+        self.slots.insert(name.to_string(), slot.clone());
+        slot
+    }
+    fn slot(&mut self, name: &str) -> Expr {
+        match self.slots.get(name) {
+            Some(slot) => slot.clone(),
+            None => self.alloc(name, 1),
+        }
+    }
+    /// stores `value` in the slot `name` and returns its load
+    fn set(&mut self, name: &str, value: Expr) -> Expr {
+        let slot = self.slot(name);
+        self.program.statement(Op::MStore, [slot.clone(), value]);
+        call(Op::MLoad, [slot])
+    }
+    fn offset(base: &Expr, offset: u64) -> Expr {
+        match offset {
+            0 => base.clone(),
+            _ => call(Op::Add, [base.clone(), lit(offset)]),
+        }
+    }
+    /// the proof is valid only if every `condition` is 1
+    fn require(&mut self, condition: Expr) {
+        let success = call(Op::MLoad, [self.success.clone()]);
+        let success = call(Op::And, [success, condition]);
+        self.program
+            .statement(Op::MStore, [self.success.clone(), success]);
+    }
+
+    fn add(&self, a: Expr, b: Expr) -> Expr {
+        call(Op::AddMod, [a, b, self.r.clone()])
+    }
+    fn mul(&self, a: Expr, b: Expr) -> Expr {
+        call(Op::MulMod, [a, b, self.r.clone()])
+    }
+    fn neg(&self, a: Expr) -> Expr {
+        call(
+            Op::AddMod,
+            [lit(0), call(Op::Sub, [self.r.clone(), a]), self.r.clone()],
+        )
+    }
+    fn sub(&self, a: Expr, b: Expr) -> Expr {
+        call(
+            Op::AddMod,
+            [a, call(Op::Sub, [self.r.clone(), b]), self.r.clone()],
+        )
+    }
+    fn constant(&mut self, name: &str, value: Fr) -> Expr {
+        self.program.constant(name, fr_word(value))
+    }
+    /// `base^exponent mod r` with the modexp precompile, stored in `name`
+    fn pow(&mut self, name: &str, base: Expr, exponent: Expr) -> Expr {
+        let inputs = [lit(32), lit(32), lit(32), base, exponent, self.r.clone()];
+        for (index, input) in inputs.into_iter().enumerate() {
+            let at = Self::offset(&self.scratch, 32 * index as u64);
+            self.program.statement(Op::MStore, [at, input]);
+        }
+        let call = self.static_call(MOD_EXP, 0xc0, 0x20);
+        self.require(call);
+        let result = call_load(&self.scratch);
+        self.set(name, result)
+    }
+    fn inverse(&mut self, name: &str, value: Expr) -> Expr {
+        let exponent = self.r_minus_2.clone();
+        self.pow(name, value, exponent)
+    }
+    /// a precompile reading `input` bytes of the scratch and writing `output` bytes back
+    fn static_call(&self, address: u64, input: u64, output: u64) -> Expr {
+        call(
+            Op::StaticCall,
+            [
+                call(Op::Gas, []),
+                lit(address),
+                self.scratch.clone(),
+                lit(input),
+                self.scratch.clone(),
+                lit(output),
+            ],
+        )
+    }
+
+    /// adds `scalar·point` to the point at `acc`, which is followed by 2 free words
+    fn add_multiple(&mut self, acc: &Expr, point: [Expr; 2], scalar: Expr) {
+        let [x, y] = point;
+        for (index, input) in [x, y, scalar].into_iter().enumerate() {
+            let at = Self::offset(&self.scratch, 32 * index as u64);
+            self.program.statement(Op::MStore, [at, input]);
+        }
+        let multiple = call(
+            Op::StaticCall,
+            [
+                call(Op::Gas, []),
+                lit(EC_MUL),
+                self.scratch.clone(),
+                lit(0x60),
+                Self::offset(acc, 0x40),
+                lit(0x40),
+            ],
+        );
+        self.require(multiple);
+        let sum = call(
+            Op::StaticCall,
+            [
+                call(Op::Gas, []),
+                lit(EC_ADD),
+                acc.clone(),
+                lit(0x80),
+                acc.clone(),
+                lit(0x40),
+            ],
+        );
+        self.require(sum);
+    }
+
+    /// reverses the bytes of a word, the transcript holds little endian integers
+    fn bswap(&mut self, value: Expr) -> Expr {
+        let mut swapped = self.set("SWAP", value);
+        for shift in [8_u64, 16, 32, 64, 128] {
+            let name = format!("MASK_{}", shift);
+            let mask = match self.slots.get(&name) {
+                Some(mask) => mask.clone(),
+                None => {
+                    // the high half of every 2·shift bits
+                    let mut mask = [0; 32];
+                    let bytes = shift as usize / 8;
+                    for (index, byte) in mask.iter_mut().enumerate() {
+                        if index % (2 * bytes) < bytes {
+                            *byte = 0xff;
+                        }
+                    }
+                    let constant = self.program.constant(name.clone(), mask);
+                    let variable = self.program.variable(name.to_lowercase(), &constant);
+                    self.slots.insert(name, variable.clone());
+                    variable
+                }
+            };
+            let high = call(Op::And, [swapped.clone(), mask.clone()]);
+            let low = call(Op::And, [swapped, call(Op::Not, [mask])]);
+            let value = call(
+                Op::Or,
+                [
+                    call(Op::Shr, [lit(shift), high]),
+                    call(Op::Shl, [lit(shift), low]),
+                ],
+            );
+            swapped = self.set("SWAP", value);
+        }
+        swapped
+    }
+
+    /// writes the pending constant bytes, the last word is padded with zeros
+    fn flush(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        for chunk in pending.chunks(32) {
+            let mut value = [0; 32];
+            value[..chunk.len()].copy_from_slice(chunk);
+            let at = Self::offset(&self.transcript, self.written);
+            self.program
+                .statement(Op::MStore, [at, Expr::Literal(value)]);
+            self.written += chunk.len() as u64;
+        }
+    }
+    fn absorb_word(&mut self, value: Expr) {
+        self.flush();
+        let at = Self::offset(&self.transcript, self.written);
+        self.program.statement(Op::MStore, [at, value]);
+        self.written += 32;
+    }
+    /// the length prefixed label and the length of the message, as `Transcript::append_message`
+    fn absorb_label(&mut self, label: &[u8], length: u64) {
+        self.pending
+            .extend_from_slice(&(label.len() as u64).to_le_bytes());
+        self.pending.extend_from_slice(label);
+        self.pending.extend_from_slice(&length.to_le_bytes());
+    }
+    fn append_scalar(&mut self, label: &[u8], scalar: Expr) {
+        self.absorb_label(label, 32);
+        let bytes = self.bswap(scalar);
+        self.absorb_word(bytes);
+    }
+    /// the compressed point: x little endian with the flags of ark-serialize in the last byte,
+    /// 0x80 when y is the larger root and 0x40 at infinity
+    fn append_point(&mut self, label: &[u8], point: [Expr; 2]) {
+        let [x, y] = point;
+        self.absorb_label(label, 32);
+        let larger = call(
+            Op::Gt,
+            [y.clone(), call(Op::Sub, [self.q.clone(), y.clone()])],
+        );
+        let infinity = call(Op::IsZero, [call(Op::Or, [x.clone(), y])]);
+        let flags = call(
+            Op::Or,
+            [
+                call(Op::Shl, [lit(7), larger]),
+                call(Op::Shl, [lit(6), infinity]),
+            ],
+        );
+        let x = self.bswap(x);
+        self.absorb_word(call(Op::Or, [x, flags]));
+    }
+    /// the `KeccakHash` of the transcript, absorbed as the Rust transcript does
+    fn challenge(&mut self, label: &[u8], name: &str) -> Expr {
+        self.program
+            .comment(format!("{} challenge", String::from_utf8_lossy(label)));
+        self.absorb_label(label, 0);
+        self.flush();
+        let end = Self::offset(&self.transcript, self.written);
+        let mut hashes = vec![];
+        for suffix in [0, 1] {
+            self.program
+                .statement(Op::MStore8, [end.clone(), lit(suffix)]);
+            let hash = call(
+                Op::Keccak256,
+                [self.transcript.clone(), lit(self.written + 1)],
+            );
+            hashes.push(self.set(&format!("HASH_{}", suffix), hash));
+        }
+        let low = self.bswap(hashes[0].clone());
+        let low = self.set("HASH_0", low);
+        let high = self.bswap(hashes[1].clone());
+        let high = call(Op::MulMod, [high, self.wide.clone(), self.r.clone()]);
+        let challenge = self.add(low, high);
+        let challenge = self.set(name, challenge);
+        self.append_scalar(b"challenge", challenge.clone());
+        challenge
+    }
+}
+
+fn call_load(at: &Expr) -> Expr {
+    call(Op::MLoad, [at.clone()])
+}
+
+/// the modulus of a prime field, which does not fit in the field
+fn modulus<F: PrimeField>() -> Word {
+    F::Params::MODULUS.to_bytes_be().try_into().unwrap()
+}
+
+/// the verifier of `vk`, which must have no custom gates and no lookups
+pub(super) fn program(vk: &VerifyingKey<Bn254>) -> Program {
+    debug_assert!(vk.custom.is_empty() && vk.lookup.is_none());
+    let mut builder = Builder::new();
+    let inputs = vk.public_rows.len();
+    let length = 32 * (inputs + 2 * PROOF_POINTS.len() + PROOF_SCALARS.len()) as u64;
+
+    builder.program.comment("the calldata of verify(bytes)");
+    let selector = builder.program.constant("SELECTOR", {
+        let mut selector = [0; 32];
+        selector[28..].copy_from_slice(&SELECTOR);
+        selector
+    });
+    let proof_length = builder.program.constant("PROOF_LENGTH", word(length));
+    let checks = [
+        call(
+            Op::Eq,
+            [
+                call(Op::Shr, [lit(224), call(Op::CallDataLoad, [lit(0)])]),
+                selector,
+            ],
+        ),
+        call(Op::Eq, [call(Op::CallDataLoad, [lit(4)]), lit(0x20)]),
+        call(
+            Op::Eq,
+            [call(Op::CallDataLoad, [lit(0x24)]), proof_length.clone()],
+        ),
+    ];
+    let checks = checks
+        .into_iter()
+        .reduce(|a, b| call(Op::And, [a, b]))
+        .unwrap();
+    let success = builder.success.clone();
+    builder.program.statement(Op::MStore, [success, checks]);
+
+    // the words of the proof, copied to consecutive slots
+    let proof = builder.free;
+    let inputs = (0..inputs)
+        .map(|index| builder.alloc(&format!("INPUT_{}", index), 1))
+        .collect::<Vec<_>>();
+    let points = PROOF_POINTS.map(|name| {
+        let x = builder.alloc(&format!("{}_X", name), 1);
+        let y = builder.alloc(&format!("{}_Y", name), 1);
+        [x, y]
+    });
+    let scalars = PROOF_SCALARS.map(|name| builder.alloc(name, 1));
+    debug_assert_eq!(builder.free, proof + length);
+    builder.program.statement(
+        Op::CallDataCopy,
+        [
+            inputs.first().unwrap_or(&points[0][0]).clone(),
+            lit(CALLDATA_START),
+            proof_length,
+        ],
+    );
+    let inputs = inputs.iter().map(call_load).collect::<Vec<_>>();
+    let points = points.map(|[x, y]| [call_load(&x), call_load(&y)]);
+    let scalars = scalars.map(|scalar| call_load(&scalar));
+    builder.program.comment("the scalars are below r");
+    for scalar in inputs.iter().chain(scalars.iter()) {
+        let below = call(Op::Lt, [scalar.clone(), builder.r.clone()]);
+        builder.require(below);
+    }
+    let [a, b, c, z, t0, t1, t2, w1, w2] = points;
+    let [a_eval, b_eval, c_eval, sigma_a, sigma_b, z_eval, zw_eval] = scalars;
+
+    builder.program.comment("the transcript");
+    builder.absorb_label(b"protocol", 7);
+    builder.pending.extend_from_slice(b"TyPLONK");
+    let digest = vk.digest::<KeccakHash>();
+    builder.absorb_label(b"verifying key", 32);
+    builder.pending.extend(fr_word(digest).into_iter().rev());
+    for input in inputs.iter() {
+        builder.append_scalar(b"public input", input.clone());
+    }
+    for (label, point) in [b"a", b"b", b"c"].iter().zip([&a, &b, &c]) {
+        builder.append_point(*label, point.clone());
+    }
+    let beta = builder.challenge(b"beta", "BETA");
+    let gamma = builder.challenge(b"gamma", "GAMMA");
+    builder.challenge(b"zeta", "ZETA");
+    builder.append_point(b"permutation", z.clone());
+    let alpha = builder.challenge(b"alpha", "ALPHA");
+    for point in [&t0, &t1, &t2] {
+        builder.append_point(b"t", point.clone());
+    }
+    let x = builder.challenge(b"evaluation point", "X");
+    for scalar in [
+        &a_eval, &b_eval, &c_eval, &sigma_a, &sigma_b, &z_eval, &zw_eval,
+    ] {
+        builder.append_scalar(b"evaluation", scalar.clone());
+    }
+    let v = builder.challenge(b"opening", "V");
+    for point in [&w1, &w2] {
+        builder.append_point(b"opening", point.clone());
+    }
+    let u = builder.challenge(b"separator", "U");
+
+    builder
+        .program
+        .comment("the vanishing polynomial and the lagrange basis at x");
+    let domain = vk.domain;
+    let size = builder.constant("DOMAIN_SIZE", Fr::from(domain.size() as u64));
+    let xn = builder.pow("XN", x.clone(), size.clone());
+    let vanishing = builder.sub(xn, lit(1));
+    let vanishing = builder.set("VANISHING", vanishing);
+    // L_i(x) = w^i·Z_H(x) / (n·(x - w^i))
+    let lagrange = |builder: &mut Builder, name: &str, row: usize| {
+        let root = builder.constant(&format!("{}_ROOT", name), domain.element(row));
+        let denominator = builder.mul(size.clone(), builder.sub(x.clone(), root.clone()));
+        let inverse = builder.inverse(name, denominator);
+        let value = builder.mul(builder.mul(root, vanishing.clone()), inverse);
+        builder.set(name, value)
+    };
+    let l0 = lagrange(&mut builder, "L0", 0);
+    let mut public_eval = None;
+    for (index, (input, row)) in inputs.iter().zip(vk.public_rows.iter()).enumerate() {
+        let basis = lagrange(&mut builder, &format!("L_INPUT_{}", index), *row);
+        let term = builder.mul(input.clone(), basis);
+        let sum = match public_eval {
+            None => builder.neg(term),
+            Some(sum) => builder.sub(sum, term),
+        };
+        public_eval = Some(builder.set("PUBLIC_EVAL", sum));
+    }
+
+    builder.program.comment("the scalars of the commitments");
+    let v2 = builder.set("V2", builder.mul(v.clone(), v.clone()));
+    let v3 = builder.set("V3", builder.mul(v2.clone(), v.clone()));
+    let v4 = builder.set("V4", builder.mul(v3.clone(), v.clone()));
+    let v5 = builder.set("V5", builder.mul(v4.clone(), v.clone()));
+    let v6 = builder.set("V6", builder.mul(v5.clone(), v.clone()));
+    let alpha2 = builder.set("ALPHA2", builder.mul(alpha.clone(), alpha.clone()));
+    // (a + β·σ_a + γ)·(b + β·σ_b + γ)
+    let sigma_ab = {
+        let pa = builder.add(
+            builder.add(a_eval.clone(), builder.mul(beta.clone(), sigma_a.clone())),
+            gamma.clone(),
+        );
+        let pb = builder.add(
+            builder.add(b_eval.clone(), builder.mul(beta.clone(), sigma_b.clone())),
+            gamma.clone(),
+        );
+        builder.set("SIGMA_AB", builder.mul(pa, pb))
+    };
+    // Π (eval + β·coset·x + γ)
+    let mut perm = None;
+    for (index, (coset, eval)) in vk
+        .cosets
+        .iter()
+        .zip([&a_eval, &b_eval, &c_eval])
+        .enumerate()
+    {
+        let coset = builder.constant(&format!("COSET_{}", index), *coset);
+        let factor = builder.mul(builder.mul(beta.clone(), coset), x.clone());
+        let factor = builder.add(builder.add(eval.clone(), factor), gamma.clone());
+        let product = match perm {
+            None => factor,
+            Some(perm) => builder.mul(perm, factor),
+        };
+        perm = Some(builder.set("PERM", product));
+    }
+    let perm = perm.unwrap();
+    // α·σ_ab·(c + γ)·z(xw) + L0·α² - PI(x)
+    let constant = {
+        let perm = builder.mul(
+            builder.mul(alpha.clone(), sigma_ab.clone()),
+            builder.mul(builder.add(c_eval.clone(), gamma.clone()), zw_eval.clone()),
+        );
+        let constant = builder.add(perm, builder.mul(l0.clone(), alpha2.clone()));
+        let constant = match public_eval {
+            Some(public_eval) => builder.sub(constant, public_eval),
+            None => constant,
+        };
+        builder.set("CONSTANT", constant)
+    };
+    let xd = builder.pow("XD", x.clone(), lit(vk.slice_degree as u64));
+    let x2d = builder.set("X2D", builder.mul(xd.clone(), xd.clone()));
+
+    let z_scalar = {
+        let acc = builder.add(builder.mul(perm, alpha.clone()), builder.mul(l0, alpha2));
+        let acc = builder.add(v5.clone(), builder.mul(v6.clone(), acc));
+        builder.set("Z_SCALAR", builder.add(acc, u.clone()))
+    };
+    let t0_scalar = builder.set("T0_SCALAR", builder.neg(builder.mul(v6.clone(), vanishing)));
+    let t1_scalar = builder.mul(t0_scalar.clone(), xd);
+    let t2_scalar = builder.mul(t0_scalar.clone(), x2d);
+    let sigma_c_scalar = {
+        let scalar = builder.mul(builder.mul(v6.clone(), sigma_ab), alpha);
+        let scalar = builder.mul(builder.mul(scalar, beta), zw_eval.clone());
+        builder.set("SIGMA_C_SCALAR", builder.neg(scalar))
+    };
+    // the evaluations opened at the point and the next row, the constant of r
+    let generator_scalar = {
+        let evals = [&b_eval, &c_eval, &sigma_a, &sigma_b, &z_eval, &constant];
+        let powers = [&v, &v2, &v3, &v4, &v5, &v6];
+        let mut sum = a_eval.clone();
+        for (eval, power) in evals.into_iter().zip(powers) {
+            let term = builder.mul(power.clone(), eval.clone());
+            sum = builder.set("G_SCALAR", builder.add(sum, term));
+        }
+        let sum = builder.add(sum, builder.mul(u.clone(), zw_eval));
+        builder.set("G_SCALAR", builder.neg(sum))
+    };
+    let w = builder.constant("OMEGA", domain.element(1));
+    let w2_scalar = builder.mul(builder.mul(u.clone(), x.clone()), w);
+
+    builder
+        .program
+        .comment("the right side of the pairing check");
+    let acc = builder.alloc("ACC", 4);
+    for offset in [0, 0x20] {
+        let at = Builder::offset(&acc, offset);
+        builder.program.statement(Op::MStore, [at, lit(0)]);
+    }
+    let vk_point = |builder: &mut Builder, name: &str, point: &G1Affine| {
+        let [x, y] = g1_words(point);
+        let x = builder.program.constant(format!("{}_X", name), x);
+        let y = builder.program.constant(format!("{}_Y", name), y);
+        [x, y]
+    };
+    let [q_l, q_r, q_o, q_m, q_c] = ["Q_L", "Q_R", "Q_O", "Q_M", "Q_C"]
+        .into_iter()
+        .zip(vk.fixed_commitments.iter())
+        .map(|(name, commitment)| vk_point(&mut builder, name, &commitment.0))
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+    let [s_a, s_b, s_c] = ["SIGMA_A", "SIGMA_B", "SIGMA_C"]
+        .into_iter()
+        .zip(vk.sigma_commitments.iter())
+        .map(|(name, commitment)| vk_point(&mut builder, name, &commitment.0))
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+    // r is committed with the first power of the srs, the evaluations with the generator
+    let identity = vk.srs.g1_ref()[0];
+    let generator = G1Affine::prime_subgroup_generator();
+    assert_eq!(identity, generator, "the srs starts with the generator");
+    let g = vk_point(&mut builder, "G1", &generator);
+    let terms = [
+        (a, lit(1)),
+        (b, v.clone()),
+        (c, v2.clone()),
+        (s_a, v3),
+        (s_b, v4),
+        (z, z_scalar),
+        (q_l, builder.mul(v6.clone(), a_eval.clone())),
+        (q_r, builder.mul(v6.clone(), b_eval.clone())),
+        (q_o, builder.neg(builder.mul(v6.clone(), c_eval))),
+        (q_m, builder.mul(v6.clone(), builder.mul(a_eval, b_eval))),
+        (q_c, v6),
+        (s_c, sigma_c_scalar),
+        (t0, t0_scalar),
+        (t1, t1_scalar),
+        (t2, t2_scalar),
+        (g, generator_scalar),
+        (w1.clone(), x),
+        (w2.clone(), w2_scalar),
+    ];
+    for (point, scalar) in terms {
+        builder.add_multiple(&acc, point, scalar);
+    }
+
+    builder.program.comment("the witnesses, W1 + u·W2");
+    let witnesses = builder.alloc("WITNESSES", 4);
+    builder
+        .program
+        .statement(Op::MStore, [witnesses.clone(), w1[0].clone()]);
+    builder.program.statement(
+        Op::MStore,
+        [Builder::offset(&witnesses, 0x20), w1[1].clone()],
+    );
+    builder.add_multiple(&witnesses, w2, u);
+
+    builder.program.comment("e(W, [s]) = e(rhs, [1])");
+    let g2s = g2_words(vk.srs.g2s_ref());
+    let g2 = g2_words(vk.srs.g2_ref());
+    let negated_y = call(
+        Op::Mod,
+        [
+            call(
+                Op::Sub,
+                [builder.q.clone(), call_load(&Builder::offset(&acc, 0x20))],
+            ),
+            builder.q.clone(),
+        ],
+    );
+    let mut pairing = vec![
+        call_load(&witnesses),
+        call_load(&Builder::offset(&witnesses, 0x20)),
+    ];
+    for (index, value) in g2s.into_iter().enumerate() {
+        pairing.push(builder.program.constant(format!("G2S_{}", index), value));
+    }
+    pairing.push(call_load(&acc));
+    pairing.push(negated_y);
+    for (index, value) in g2.into_iter().enumerate() {
+        pairing.push(builder.program.constant(format!("G2_{}", index), value));
+    }
+    for (index, value) in pairing.into_iter().enumerate() {
+        let at = Builder::offset(&builder.scratch, 32 * index as u64);
+        builder.program.statement(Op::MStore, [at, value]);
+    }
+    let pairing = builder.static_call(EC_PAIRING, 0x180, 0x20);
+    builder.require(pairing);
+    let one = call(Op::Eq, [call_load(&builder.scratch), lit(1)]);
+    builder.require(one);
+
+    let success = call_load(&builder.success);
+    builder.program.statement(Op::MStore, [lit(0), success]);
+    builder.program.statement(Op::Return, [lit(0), lit(32)]);
+
+    // the keccak input ends one byte after the transcript
+    let transcript = builder.transcript.clone();
+    builder
+        .program
+        .set_constant(&transcript, word(builder.free));
+    builder.program
+}
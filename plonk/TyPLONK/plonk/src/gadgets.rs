@@ -0,0 +1,112 @@
+//! Common constraints built from the operations of [`Var`].
+//!
+//! The gadgets work with any variable, the values the gates can not compute are given by hints
+//! ([`Var::hint`]) and then constrained. The comments give the constraints of each gadget.
+use crate::description::Var;
+use ark_ff::{BigInteger, Field, FpParameters, One, PrimeField, Zero};
+
+pub mod poseidon;
+
+/// constrains x to be 0 or 1, `x·x = x`
+pub fn assert_bool<V: Var>(x: &V) {
+    (x.clone() * x.clone()).assert_eq(x);
+}
+
+/// the n lowest bits of x, lowest first. Each bit is boolean and `from_bits` of the bits is x, so
+/// x must fit in n bits. n is below the size of the field so the sum can not wrap around
+pub fn to_bits<V: Var>(x: &V, n: usize) -> Vec<V> {
+    let size = <V::Field as PrimeField>::Params::MODULUS_BITS as usize;
+    assert!(n < size, "{} bits do not fit in the field", n);
+    let bits = (0..n)
+        .map(|i| {
+            let bit = V::hint(&[x], |values| {
+                V::Field::from(values[0].into_repr().get_bit(i) as u64)
+            });
+            assert_bool(&bit);
+            bit
+        })
+        .collect::<Vec<_>>();
+    // a subtraction rather than a copy, x may not have a cell yet
+    (from_bits(&bits) - x.clone()).assert_eq(&V::constant(0_u64));
+    bits
+}
+
+/// `Σ bit_i·2^i`, lowest bit first, the bits are not constrained to be boolean. A single linear
+/// combination, so wider circuits take fewer rows
+pub fn from_bits<V: Var>(bits: &[V]) -> V {
+    let mut power = V::Field::one();
+    let terms = bits
+        .iter()
+        .map(|bit| {
+            let term = (power, bit);
+            power.double_in_place();
+            term
+        })
+        .collect::<Vec<_>>();
+    V::linear_combination(&terms)
+}
+
+/// constrains x to be below `2^n`
+pub fn range_check<V: Var>(x: &V, n: usize) {
+    to_bits(x, n);
+}
+
+/// 1 if x is 0 and 0 otherwise. With the hint `inv`, the inverse of x or 0, the result is
+/// `1 - x·inv` and `x·result = 0`
+pub fn is_zero<V: Var>(x: &V) -> V {
+    let inv = V::hint(&[x], |values| {
+        values[0].inverse().unwrap_or_else(V::Field::zero)
+    });
+    let result = V::constant(1_u64) - x.clone() * inv;
+    (x.clone() * result.clone()).assert_eq(&V::constant(0_u64));
+    result
+}
+
+/// 1 if a and b are equal and 0 otherwise
+pub fn is_equal<V: Var>(a: &V, b: &V) -> V {
+    is_zero(&(a.clone() - b.clone()))
+}
+
+/// 1 if a < b and 0 otherwise, a and b must already be constrained to n bits. `a - b + 2^n` is
+/// then below `2^(n+1)`, and its bit n is set exactly when a >= b
+pub fn less_than<V: Var>(a: &V, b: &V, n: usize) -> V {
+    let offset = V::Field::from(2_u64).pow([n as u64]);
+    let shifted = a.clone() - b.clone() + V::constant(offset);
+    let bits = to_bits(&shifted, n + 1);
+    V::constant(1_u64) - bits[n].clone()
+}
+
+/// a if cond is 1 and b if it is 0, `b + cond·(a - b)`. cond must be constrained to be boolean,
+/// see [`assert_bool`]
+pub fn select<V: Var>(cond: &V, a: &V, b: &V) -> V {
+    b.clone() + cond.clone() * (a.clone() - b.clone())
+}
+
+/// the inverse of x, `x·inv = 1` can not be satisfied when x is 0
+pub fn inverse<V: Var>(x: &V) -> V {
+    let inv = V::hint(&[x], |values| {
+        values[0].inverse().unwrap_or_else(V::Field::zero)
+    });
+    (x.clone() * inv.clone()).assert_eq(&V::constant(1_u64));
+    inv
+}
+
+/// constrains `root` to be the root of the poseidon merkle tree with `leaf` at the index given by
+/// `index_bits`, see [`crate::poseidon::merkle_root`]. The siblings and the bits go from the leaf
+/// to the root, bit i is 1 when the node at height i is a right child
+pub fn merkle_path_verify<V: Var>(leaf: &V, siblings: &[V], index_bits: &[V], root: &V) {
+    assert_eq!(siblings.len(), index_bits.len());
+    let node = siblings
+        .iter()
+        .zip(index_bits)
+        .fold(leaf.clone(), |node, (sibling, bit)| {
+            assert_bool(bit);
+            let left = select(bit, sibling, &node);
+            let right = select(bit, &node, sibling);
+            poseidon::hash2(&left, &right)
+        });
+    node.assert_eq(root);
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,25 @@
+//! The Poseidon permutation of [`crate::poseidon`] in a circuit.
+//!
+//! The S-box is a row of [`Pow5Gate`], which the circuit must register in
+//! `CircuitDescription::custom_gates`. A permutation takes about 1250 rows.
+use crate::{
+    description::Var,
+    gate::Pow5Gate,
+    poseidon::{rounds, sponge, WIDTH},
+};
+use ark_ff::One;
+
+pub fn permute<V: Var>(state: [V; WIDTH]) -> [V; WIDTH] {
+    rounds::<V::Field, _>(state, V::constant, |x| {
+        V::custom_gate(&Pow5Gate, &[V::Field::one()], &[x])
+    })
+}
+
+/// the same sponge as [`crate::poseidon::hash`]
+pub fn hash<V: Var>(inputs: &[V]) -> V {
+    sponge::<V::Field, _>(inputs, V::constant, permute)
+}
+
+pub fn hash2<V: Var>(left: &V, right: &V) -> V {
+    hash(&[left.clone(), right.clone()])
+}
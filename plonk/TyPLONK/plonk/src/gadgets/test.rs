@@ -0,0 +1,225 @@
+use super::{
+    assert_bool, from_bits, inverse, is_equal, is_zero, less_than, merkle_path_verify, poseidon,
+    range_check, select, to_bits,
+};
+use crate::{
+    curve_tests,
+    description::{Circuit, CircuitDescription, Var},
+    gate::{CustomGate, Pow5Gate},
+    poseidon as native, CompiledCircuit, PlonkError,
+};
+use ark_ec::PairingEngine;
+use ark_ff::{Field, PrimeField};
+
+/// proves and verifies, and returns the public inputs of the proof
+fn prove<const I: usize, E: PairingEngine, C: Circuit>(
+    circuit: &CompiledCircuit<E, C>,
+    inputs: [u64; I],
+) -> Result<Vec<E::Fr>, PlonkError> {
+    let proof = circuit.prove(inputs)?;
+    let public_inputs = proof.public_inputs.clone();
+    circuit.verify(proof)?;
+    Ok(public_inputs)
+}
+fn unsatisfied<T: std::fmt::Debug>(result: Result<T, PlonkError>) -> bool {
+    matches!(result, Err(PlonkError::Unsatisfied(_)))
+}
+fn fr<F: PrimeField>(values: &[u64]) -> Vec<F> {
+    values.iter().map(|value| F::from(*value)).collect()
+}
+
+struct Bool;
+impl CircuitDescription<1> for Bool {
+    fn run<V: Var>(inputs: [V; 1]) {
+        let [x] = inputs;
+        assert_bool(&x);
+    }
+}
+
+fn assert_bool_test<E: PairingEngine>() {
+    let circuit = Bool::build::<E>().unwrap();
+    assert!(prove(&circuit, [0]).is_ok());
+    assert!(prove(&circuit, [1]).is_ok());
+    assert!(unsatisfied(prove(&circuit, [2])));
+}
+
+struct Bits;
+impl CircuitDescription<1> for Bits {
+    fn run<V: Var>(inputs: [V; 1]) {
+        let [x] = inputs;
+        let bits = to_bits(&x, 8);
+        bits[0].expose();
+        bits[7].expose();
+        from_bits(&bits[..4]).expose();
+    }
+}
+
+fn to_bits_test<E: PairingEngine>() {
+    let circuit = Bits::build::<E>().unwrap();
+    assert_eq!(prove(&circuit, [0b1010_0110]).unwrap(), fr(&[0, 1, 0b0110]));
+    assert_eq!(prove(&circuit, [0b0111_1111]).unwrap(), fr(&[1, 0, 0b1111]));
+    assert!(unsatisfied(prove(&circuit, [256])));
+}
+
+struct Range;
+impl CircuitDescription<1> for Range {
+    fn run<V: Var>(inputs: [V; 1]) {
+        let [x] = inputs;
+        range_check(&x, 4);
+    }
+}
+
+fn range_check_test<E: PairingEngine>() {
+    let circuit = Range::build::<E>().unwrap();
+    assert!(prove(&circuit, [0]).is_ok());
+    assert!(prove(&circuit, [15]).is_ok());
+    assert!(unsatisfied(prove(&circuit, [16])));
+}
+
+struct Zero;
+impl CircuitDescription<2> for Zero {
+    fn run<V: Var>(inputs: [V; 2]) {
+        let [a, b] = inputs;
+        is_zero(&a).expose();
+        is_equal(&a, &b).expose();
+        // a hint of constants is a constant
+        is_zero(&V::constant(0_u64)).assert_eq(&V::constant(1_u64));
+    }
+}
+
+fn is_zero_test<E: PairingEngine>() {
+    let circuit = Zero::build::<E>().unwrap();
+    assert_eq!(prove(&circuit, [0, 0]).unwrap(), fr(&[1, 1]));
+    assert_eq!(prove(&circuit, [3, 3]).unwrap(), fr(&[0, 1]));
+    assert_eq!(prove(&circuit, [3, 4]).unwrap(), fr(&[0, 0]));
+    assert_eq!(prove(&circuit, [0, 4]).unwrap(), fr(&[1, 0]));
+}
+
+struct LessThan;
+impl CircuitDescription<2> for LessThan {
+    fn run<V: Var>(inputs: [V; 2]) {
+        let [a, b] = inputs;
+        range_check(&a, 8);
+        range_check(&b, 8);
+        less_than(&a, &b, 8).expose();
+    }
+}
+
+fn less_than_test<E: PairingEngine>() {
+    let circuit = LessThan::build::<E>().unwrap();
+    let cases = [
+        ([3, 5], 1),
+        ([5, 3], 0),
+        ([4, 4], 0),
+        ([0, 255], 1),
+        ([255, 0], 0),
+    ];
+    for (inputs, result) in cases {
+        assert_eq!(prove(&circuit, inputs).unwrap(), fr(&[result]));
+    }
+    assert!(unsatisfied(prove(&circuit, [256, 0])));
+}
+
+struct Select;
+impl CircuitDescription<3> for Select {
+    fn run<V: Var>(inputs: [V; 3]) {
+        let [cond, a, b] = inputs;
+        assert_bool(&cond);
+        select(&cond, &a, &b).expose();
+    }
+}
+
+fn select_test<E: PairingEngine>() {
+    let circuit = Select::build::<E>().unwrap();
+    assert_eq!(prove(&circuit, [1, 7, 9]).unwrap(), fr(&[7]));
+    assert_eq!(prove(&circuit, [0, 7, 9]).unwrap(), fr(&[9]));
+    assert!(unsatisfied(prove(&circuit, [2, 7, 9])));
+}
+
+struct Inverse;
+impl CircuitDescription<1> for Inverse {
+    fn run<V: Var>(inputs: [V; 1]) {
+        let [x] = inputs;
+        inverse(&x).expose();
+    }
+}
+
+fn inverse_test<E: PairingEngine>() {
+    let circuit = Inverse::build::<E>().unwrap();
+    let inverse = E::Fr::from(4_u64).inverse().unwrap();
+    assert_eq!(prove(&circuit, [4]).unwrap(), vec![inverse]);
+    assert!(unsatisfied(prove(&circuit, [0])));
+}
+
+struct Poseidon;
+impl CircuitDescription<3> for Poseidon {
+    fn run<V: Var>(inputs: [V; 3]) {
+        for x in poseidon::permute(inputs.clone()) {
+            x.expose();
+        }
+        // the capacity and the padding are constants
+        let [a, b, _] = inputs;
+        poseidon::hash(&[a.clone(), V::constant(5_u64), b]).expose();
+        poseidon::hash2(&a, &V::constant(7_u64)).expose();
+    }
+    fn custom_gates<F: PrimeField>() -> Vec<Box<dyn CustomGate<F>>> {
+        vec![Box::new(Pow5Gate)]
+    }
+}
+
+fn poseidon_test<E: PairingEngine>() {
+    let circuit = Poseidon::build::<E>().unwrap();
+    for inputs in [[0_u64, 1, 2], [7, 11, 13]] {
+        let [a, b, c] = inputs.map(E::Fr::from);
+        let mut expected = native::permute([a, b, c]).to_vec();
+        expected.push(native::hash(&[a, E::Fr::from(5_u64), b]));
+        expected.push(native::hash2(a, E::Fr::from(7_u64)));
+        assert_eq!(prove(&circuit, inputs).unwrap(), expected);
+    }
+}
+
+/// a path of depth 3, the inputs are the leaf, the siblings, the bits of the index and the root
+struct Merkle;
+impl CircuitDescription<8> for Merkle {
+    fn run<V: Var>(inputs: [V; 8]) {
+        let [leaf, s0, s1, s2, b0, b1, b2, root] = inputs;
+        root.expose();
+        merkle_path_verify(&leaf, &[s0, s1, s2], &[b0, b1, b2], &root);
+    }
+    fn custom_gates<F: PrimeField>() -> Vec<Box<dyn CustomGate<F>>> {
+        vec![Box::new(Pow5Gate)]
+    }
+}
+
+fn merkle_path_verify_test<E: PairingEngine>() {
+    let circuit = Merkle::build::<E>().unwrap();
+    let (leaf, siblings) = (9_u64, [5_u64, 21, 34]);
+    let root = native::merkle_root(E::Fr::from(leaf), &siblings.map(E::Fr::from), 0b101);
+    let inputs = |bits: [u64; 3], root: E::Fr| {
+        let [s0, s1, s2] = siblings.map(E::Fr::from);
+        let [b0, b1, b2] = bits.map(E::Fr::from);
+        [E::Fr::from(leaf), s0, s1, s2, b0, b1, b2, root]
+    };
+    let proof = circuit.prove(inputs([1, 0, 1], root)).unwrap();
+    assert_eq!(proof.public_inputs, vec![root]);
+    assert!(circuit.verify(proof).is_ok());
+    // another index, or a bit that is not boolean
+    for bits in [[0, 0, 1], [1, 0, 2]] {
+        assert!(unsatisfied(circuit.prove(inputs(bits, root))));
+    }
+    assert!(unsatisfied(
+        circuit.prove(inputs([1, 0, 1], root + E::Fr::from(1_u64)))
+    ));
+}
+
+curve_tests!(
+    assert_bool_test,
+    to_bits_test,
+    range_check_test,
+    is_zero_test,
+    less_than_test,
+    select_test,
+    inverse_test,
+    poseidon_test,
+    merkle_path_verify_test,
+);
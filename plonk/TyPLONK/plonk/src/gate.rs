@@ -0,0 +1,70 @@
+use ark_ff::{Field, PrimeField};
+use std::fmt::Debug;
+
+/// A gate that brings its own selector columns.
+///
+/// Each selector `q_k` multiplies the term `f_k` returned by [`CustomGate::terms`], and the products are
+/// added to the equation of the row: `q_l·a + q_r·b - q_o·c + q_m·ab + q_c + PI + Σ q_k·f_k = 0`.
+/// The terms can read all the wires of the current row and of the next one. Gates are shared by the
+/// threads proving with the same circuit, so they must be `Send + Sync`. The field `F` is the scalar
+/// field of the curve the circuit is compiled for.
+pub trait CustomGate<F: Field>: Debug + Send + Sync {
+    /// identifies the gate, it must be unique among the gates of a circuit
+    fn name(&self) -> &'static str;
+    /// the number of selector columns
+    fn selectors(&self) -> usize;
+    /// the max degree of the terms in the wires, it sizes the domain the quotient is computed on
+    fn degree(&self) -> usize;
+    /// the term multiplied by each selector, `wires` and `next` are the wires `[a, b, c, …]` of this
+    /// row and the next one, as many as the circuit has
+    fn terms(&self, wires: &[F], next: &[F]) -> Vec<F>;
+    /// the value of column c in a row with `q_o = 1`, the default only works for terms that do not read
+    /// column c, the wires after it or the next row
+    fn output(&self, selectors: &[F], a: F, b: F) -> F {
+        let zero = F::zero();
+        self.terms(&[a, b, zero], &[zero; 3])
+            .iter()
+            .zip(selectors)
+            .map(|(term, selector)| *term * selector)
+            .sum()
+    }
+}
+
+/// `q·a^5`, the S-box of Poseidon
+#[derive(Debug, Clone, Copy)]
+pub struct Pow5Gate;
+
+impl<F: PrimeField> CustomGate<F> for Pow5Gate {
+    fn name(&self) -> &'static str {
+        "pow5"
+    }
+    fn selectors(&self) -> usize {
+        1
+    }
+    fn degree(&self) -> usize {
+        5
+    }
+    fn terms(&self, wires: &[F], _next: &[F]) -> Vec<F> {
+        vec![wires[0].pow([5])]
+    }
+}
+
+/// `q·(a^2 - a)`, constrains a to be 0 or 1
+#[derive(Debug, Clone, Copy)]
+pub struct BoolGate;
+
+impl<F: PrimeField> CustomGate<F> for BoolGate {
+    fn name(&self) -> &'static str {
+        "bool"
+    }
+    fn selectors(&self) -> usize {
+        1
+    }
+    fn degree(&self) -> usize {
+        2
+    }
+    fn terms(&self, wires: &[F], _next: &[F]) -> Vec<F> {
+        let a = wires[0];
+        vec![a.square() - a]
+    }
+}
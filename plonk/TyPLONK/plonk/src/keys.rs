@@ -0,0 +1,404 @@
+//! Splitting a compiled circuit in a proving and a verifying key, and their binary format.
+//!
+//! A key is written as a 4 byte magic, `TPPK` for proving keys and `TPVK` for verifying keys, the
+//! [`KEY_VERSION`] as a little endian u32, and then its fields in the compressed `ark-serialize`
+//! encoding, fixed size arrays being their elements one after the other:
+//!
+//! - verifying key: domain size, public rows, slice degree, number of wires W, srs (generator of
+//!   g1 and g2 elements), the 5 fixed commitments, the `W - 3` commitments to the selectors of the
+//!   wires after c, the W sigma commitments, the W cosets, the custom gates as
+//!   `(name, selector commitments)` and the optional 4 lookup commitments.
+//! - proving key: domain size, public rows, slice degree, number of wires W, srs, q_l, q_r, q_o,
+//!   q_m, q_c, the `W - 3` selectors of the wires after c, the 5 fixed commitments and the `W - 3`
+//!   others, the custom gates as `(name, selector polys, selector commitments)`, the W sigma
+//!   columns and the optional lookup tables as q_lookup, q_table, values, ids, the 4 commitments,
+//!   the queries and the entries.
+//!
+//! A key is read with the number of wires of its circuit, keys of other widths are rejected.
+//!
+//! Custom gates are code, they are stored by name and matched with the gates given when reading.
+use crate::{
+    builder::{self, gates_degree},
+    description::Circuit,
+    gate::CustomGate,
+    proof::Proof,
+    transcript::TranscriptHash,
+    CompiledCircuit, CosetEvals, CustomCommitments, CustomSelectors, GateConstrains, LookupTables,
+    PlonkError, Poly, ProvingKey, Srs, VerifyingKey,
+};
+use ark_ec::PairingEngine;
+use ark_ff::{FftField, Field};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use kgz::{KzgCommitment, KzgScheme};
+use permutation::CompiledPermutation;
+use std::sync::OnceLock;
+
+const PROVING_MAGIC: &[u8; 4] = b"TPPK";
+const VERIFYING_MAGIC: &[u8; 4] = b"TPVK";
+/// the version of the layout of the keys, keys of other versions are rejected
+pub const KEY_VERSION: u32 = 2;
+
+impl<E: PairingEngine, C: Circuit, const N: usize> CompiledCircuit<E, C, N> {
+    /// built once and kept with the circuit
+    pub fn verifying_key(&self) -> &VerifyingKey<E, N> {
+        self.verifying_key
+            .get_or_init(|| self.build_verifying_key())
+    }
+    fn build_verifying_key(&self) -> VerifyingKey<E, N> {
+        let custom = self
+            .circuit
+            .custom_gates()
+            .into_iter()
+            .zip(self.gate_constrains.custom.iter())
+            .map(|(gate, selectors)| CustomCommitments {
+                gate,
+                commitments: selectors.commitments.clone(),
+            })
+            .collect();
+        VerifyingKey {
+            domain: self.domain,
+            public_rows: self.public_rows.clone(),
+            slice_degree: self.slice_degree,
+            srs: self.srs.verifier_srs(),
+            fixed_commitments: self.gate_constrains.fixed_commitments,
+            wire_commitments: self.gate_constrains.wire_commitments.clone(),
+            sigma_commitments: self.sigma_commitments,
+            cosets: self.copy_constrains.cosets,
+            custom,
+            lookup: self.lookup.as_ref().map(|lookup| lookup.commitments),
+            bytes: OnceLock::new(),
+        }
+    }
+    pub fn split(self) -> (ProvingKey<E, C, N>, VerifyingKey<E, N>) {
+        let verifying_key = self.build_verifying_key();
+        (ProvingKey { circuit: self }, verifying_key)
+    }
+}
+
+impl<E: PairingEngine, C: Circuit, const N: usize> ProvingKey<E, C, N> {
+    pub fn prove(
+        &self,
+        inputs: impl IntoIterator<Item = impl Into<E::Fr>>,
+    ) -> Result<Proof<E, N>, PlonkError> {
+        self.circuit.prove(inputs)
+    }
+    pub fn prove_with_hash<H: TranscriptHash>(
+        &self,
+        inputs: impl IntoIterator<Item = impl Into<E::Fr>>,
+    ) -> Result<Proof<E, N>, PlonkError> {
+        self.circuit.prove_with_hash::<H>(inputs)
+    }
+    pub fn check(
+        &self,
+        inputs: impl IntoIterator<Item = impl Into<E::Fr>>,
+    ) -> Result<(), PlonkError> {
+        self.circuit.check(inputs)
+    }
+    pub fn verifying_key(&self) -> &VerifyingKey<E, N> {
+        self.circuit.verifying_key()
+    }
+
+    pub fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        let circuit = &self.circuit;
+        let gates = &circuit.gate_constrains;
+        write_header(&mut writer, PROVING_MAGIC)?;
+        circuit.rows.serialize(&mut writer)?;
+        circuit.public_rows.serialize(&mut writer)?;
+        circuit.slice_degree.serialize(&mut writer)?;
+        N.serialize(&mut writer)?;
+        circuit.srs.serialize(&mut writer)?;
+        write_array(
+            [&gates.q_l, &gates.q_r, &gates.q_o, &gates.q_m, &gates.q_c],
+            &mut writer,
+        )?;
+        write_array(&gates.wire_selectors, &mut writer)?;
+        write_array(&gates.fixed_commitments, &mut writer)?;
+        write_array(&gates.wire_commitments, &mut writer)?;
+        gates.custom.len().serialize(&mut writer)?;
+        for custom in gates.custom.iter() {
+            custom.gate.name().as_bytes().serialize(&mut writer)?;
+            custom.polys.serialize(&mut writer)?;
+            custom.commitments.serialize(&mut writer)?;
+        }
+        write_array(&circuit.copy_constrains.sigma_values(), &mut writer)?;
+        circuit.lookup.is_some().serialize(&mut writer)?;
+        if let Some(lookup) = &circuit.lookup {
+            let polys = [
+                &lookup.q_lookup,
+                &lookup.q_table,
+                &lookup.values,
+                &lookup.ids,
+            ];
+            write_array(polys, &mut writer)?;
+            write_array(&lookup.commitments, &mut writer)?;
+            lookup.queries.serialize(&mut writer)?;
+            lookup.entries.serialize(&mut writer)?;
+        }
+        Ok(())
+    }
+    /// `circuit` must be the circuit the key was made for, its custom gates are matched by name
+    pub fn deserialize<R: Read>(mut reader: R, circuit: C) -> Result<Self, SerializationError> {
+        read_header(&mut reader, PROVING_MAGIC)?;
+        let domain = read_domain(&mut reader)?;
+        let public_rows = <Vec<usize>>::deserialize(&mut reader)?;
+        let slice_degree = usize::deserialize(&mut reader)?;
+        read_wires::<N, _>(&mut reader)?;
+        let srs = Srs::deserialize(&mut reader)?;
+        let [q_l, q_r, q_o, q_m, q_c] = read_array::<Poly<E::Fr>, _, 5>(&mut reader)?;
+        let wire_selectors = read_vec(&mut reader, N - 3)?;
+        let fixed_commitments = read_array(&mut reader)?;
+        let wire_commitments = read_vec(&mut reader, N - 3)?;
+        let mut gates = circuit.custom_gates();
+        let custom_gates = usize::deserialize(&mut reader)?;
+        let custom = (0..custom_gates)
+            .map(|_| {
+                let gate = take_gate(&mut gates, &mut reader)?;
+                let polys = <Vec<Poly<E::Fr>>>::deserialize(&mut reader)?;
+                let commitments = <Vec<KzgCommitment<E>>>::deserialize(&mut reader)?;
+                match polys.len() == gate.selectors() && commitments.len() == gate.selectors() {
+                    true => Ok(CustomSelectors {
+                        gate,
+                        polys,
+                        commitments,
+                    }),
+                    false => Err(SerializationError::InvalidData),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let degree = gates_degree::<N>(custom.iter().map(|custom| custom.gate.degree()));
+        check_layout(domain, &public_rows, slice_degree, degree)?;
+        // the slices of the blinded quotient are committed
+        if srs.size() < slice_degree + 3 {
+            return Err(SerializationError::InvalidData);
+        }
+        let sigmas = read_array::<Vec<E::Fr>, _, N>(&mut reader)?;
+        if sigmas.iter().any(|sigma| sigma.len() != domain.size()) {
+            return Err(SerializationError::InvalidData);
+        }
+        let lookup = match bool::deserialize(&mut reader)? {
+            true => {
+                let [q_lookup, q_table, values, ids] =
+                    read_array::<Poly<E::Fr>, _, 4>(&mut reader)?;
+                Some(LookupTables {
+                    q_lookup,
+                    q_table,
+                    values,
+                    ids,
+                    commitments: read_array(&mut reader)?,
+                    queries: CanonicalDeserialize::deserialize(&mut reader)?,
+                    entries: CanonicalDeserialize::deserialize(&mut reader)?,
+                })
+            }
+            false => None,
+        };
+        let gate_constrains = GateConstrains {
+            q_l,
+            q_r,
+            q_o,
+            q_m,
+            q_c,
+            fixed_commitments,
+            wire_selectors,
+            wire_commitments,
+            custom,
+        };
+        let copy_constrains = CompiledPermutation::from_sigmas(sigmas);
+        let sigmas = copy_constrains.sigma_polys(domain);
+        let coset = CosetEvals::new(
+            &gate_constrains,
+            &sigmas,
+            lookup.as_ref(),
+            domain,
+            slice_degree,
+        );
+        // the srs is too small for the domain
+        let basis = srs
+            .lagrange_basis(domain)
+            .map_err(|_| SerializationError::InvalidData)?;
+        let sigma_commitments = copy_constrains
+            .sigma_commitments(&KzgScheme::new(&srs), domain)
+            .map_err(|_| SerializationError::InvalidData)?;
+        let circuit = CompiledCircuit {
+            circuit,
+            gate_constrains,
+            copy_constrains,
+            srs,
+            domain,
+            rows: domain.size(),
+            public_rows,
+            slice_degree,
+            lookup,
+            sigmas,
+            sigma_commitments,
+            coset,
+            basis,
+            verifying_key: OnceLock::new(),
+        };
+        Ok(Self { circuit })
+    }
+}
+
+impl<E: PairingEngine, const N: usize> VerifyingKey<E, N> {
+    /// the hash of the serialized key, it starts the transcript of every proof. The key is
+    /// serialized once
+    pub fn digest<H: TranscriptHash>(&self) -> E::Fr {
+        let bytes = self.bytes.get_or_init(|| {
+            let mut bytes = vec![];
+            self.serialize(&mut bytes)
+                .expect("writing to a vec does not fail");
+            bytes
+        });
+        H::hash_to_field(bytes)
+    }
+    pub fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        write_header(&mut writer, VERIFYING_MAGIC)?;
+        self.domain.size().serialize(&mut writer)?;
+        self.public_rows.serialize(&mut writer)?;
+        self.slice_degree.serialize(&mut writer)?;
+        N.serialize(&mut writer)?;
+        self.srs.serialize(&mut writer)?;
+        write_array(&self.fixed_commitments, &mut writer)?;
+        write_array(&self.wire_commitments, &mut writer)?;
+        write_array(&self.sigma_commitments, &mut writer)?;
+        write_array(&self.cosets, &mut writer)?;
+        self.custom.len().serialize(&mut writer)?;
+        for custom in self.custom.iter() {
+            custom.gate.name().as_bytes().serialize(&mut writer)?;
+            custom.commitments.serialize(&mut writer)?;
+        }
+        self.lookup.is_some().serialize(&mut writer)?;
+        if let Some(lookup) = &self.lookup {
+            write_array(lookup, &mut writer)?;
+        }
+        Ok(())
+    }
+    /// `gates` must contain the custom gates of the circuit, they are matched by name
+    pub fn deserialize<R: Read>(
+        mut reader: R,
+        mut gates: Vec<Box<dyn CustomGate<E::Fr>>>,
+    ) -> Result<Self, SerializationError> {
+        read_header(&mut reader, VERIFYING_MAGIC)?;
+        let domain = read_domain(&mut reader)?;
+        let public_rows = <Vec<usize>>::deserialize(&mut reader)?;
+        let slice_degree = usize::deserialize(&mut reader)?;
+        read_wires::<N, _>(&mut reader)?;
+        let srs = Srs::deserialize(&mut reader)?;
+        let fixed_commitments = read_array(&mut reader)?;
+        let wire_commitments = read_vec(&mut reader, N - 3)?;
+        let sigma_commitments = read_array(&mut reader)?;
+        let cosets = read_array(&mut reader)?;
+        let custom_gates = usize::deserialize(&mut reader)?;
+        let custom = (0..custom_gates)
+            .map(|_| {
+                let gate = take_gate(&mut gates, &mut reader)?;
+                let commitments = <Vec<KzgCommitment<E>>>::deserialize(&mut reader)?;
+                match commitments.len() == gate.selectors() {
+                    true => Ok(CustomCommitments { gate, commitments }),
+                    false => Err(SerializationError::InvalidData),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let lookup = match bool::deserialize(&mut reader)? {
+            true => Some(read_array(&mut reader)?),
+            false => None,
+        };
+        let degree = gates_degree::<N>(custom.iter().map(|custom| custom.gate.degree()));
+        check_layout(domain, &public_rows, slice_degree, degree)?;
+        Ok(Self {
+            domain,
+            public_rows,
+            slice_degree,
+            srs,
+            fixed_commitments,
+            wire_commitments,
+            sigma_commitments,
+            cosets,
+            custom,
+            lookup,
+            bytes: OnceLock::new(),
+        })
+    }
+}
+
+fn write_header<W: Write>(writer: &mut W, magic: &[u8; 4]) -> Result<(), SerializationError> {
+    writer.write_all(magic)?;
+    writer.write_all(&KEY_VERSION.to_le_bytes())?;
+    Ok(())
+}
+fn read_header<R: Read>(reader: &mut R, magic: &[u8; 4]) -> Result<(), SerializationError> {
+    let mut header = [0; 8];
+    reader.read_exact(&mut header)?;
+    match header[..4] == magic[..] && header[4..] == KEY_VERSION.to_le_bytes() {
+        true => Ok(()),
+        false => Err(SerializationError::InvalidData),
+    }
+}
+/// the public rows are rows of the domain and the slices fit the quotient of gates of `degree`
+fn check_layout<F: FftField>(
+    domain: GeneralEvaluationDomain<F>,
+    public_rows: &[usize],
+    slice_degree: usize,
+    degree: usize,
+) -> Result<(), SerializationError> {
+    let valid = public_rows.iter().all(|row| *row < domain.size())
+        && slice_degree >= builder::slice_degree(degree, domain.size());
+    match valid {
+        true => Ok(()),
+        false => Err(SerializationError::InvalidData),
+    }
+}
+fn read_domain<F: FftField, R: Read>(
+    reader: &mut R,
+) -> Result<GeneralEvaluationDomain<F>, SerializationError> {
+    let size = usize::deserialize(reader)?;
+    match <GeneralEvaluationDomain<F>>::new(size) {
+        Some(domain) if domain.size() == size => Ok(domain),
+        _ => Err(SerializationError::InvalidData),
+    }
+}
+fn write_array<'a, T: CanonicalSerialize + 'a, W: Write>(
+    items: impl IntoIterator<Item = &'a T>,
+    writer: &mut W,
+) -> Result<(), SerializationError> {
+    for item in items {
+        item.serialize(&mut *writer)?;
+    }
+    Ok(())
+}
+/// reads `len` items written by `write_array`
+fn read_vec<T: CanonicalDeserialize, R: Read>(
+    reader: &mut R,
+    len: usize,
+) -> Result<Vec<T>, SerializationError> {
+    (0..len).map(|_| T::deserialize(&mut *reader)).collect()
+}
+/// reads the number of wires of the key, it must be the one of the circuit
+fn read_wires<const N: usize, R: Read>(reader: &mut R) -> Result<(), SerializationError> {
+    match usize::deserialize(reader)? == N {
+        true => Ok(()),
+        false => Err(SerializationError::InvalidData),
+    }
+}
+fn read_array<T: CanonicalDeserialize, R: Read, const N: usize>(
+    reader: &mut R,
+) -> Result<[T; N], SerializationError> {
+    let items = (0..N)
+        .map(|_| T::deserialize(&mut *reader))
+        .collect::<Result<Vec<_>, _>>()?;
+    match items.try_into() {
+        Ok(items) => Ok(items),
+        Err(_) => unreachable!("N items were read"),
+    }
+}
+/// reads the name of a custom gate and takes the gate with that name out of `gates`
+fn take_gate<F: Field, R: Read>(
+    gates: &mut Vec<Box<dyn CustomGate<F>>>,
+    reader: &mut R,
+) -> Result<Box<dyn CustomGate<F>>, SerializationError> {
+    let name = <Vec<u8>>::deserialize(reader)?;
+    match gates.iter().position(|gate| gate.name().as_bytes() == name) {
+        Some(index) => Ok(gates.remove(index)),
+        None => Err(SerializationError::InvalidData),
+    }
+}
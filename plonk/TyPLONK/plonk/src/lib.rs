@@ -1,35 +1,180 @@
-use ark_bls12_381::Fr;
 use ark_ec::PairingEngine;
+use ark_ff::PrimeField;
 use ark_poly::{univariate::DensePolynomial, GeneralEvaluationDomain};
-use description::CircuitDescription;
-use kgz::{srs::Srs, KzgCommitment};
+use description::Circuit;
+use gate::CustomGate;
+use kgz::{srs::LagrangeBasis, KzgCommitment};
 use permutation::CompiledPermutation;
-use std::marker::PhantomData;
+use std::sync::OnceLock;
 
 pub mod builder;
 pub mod description;
+mod error;
+pub mod evm;
+pub mod gadgets;
+pub mod gate;
+mod keys;
+pub mod lookup;
+pub mod poseidon;
 mod proof;
+pub mod r1cs;
+pub mod transcript;
 mod utils;
 
-pub type G1Point = <ark_bls12_381::Bls12_381 as PairingEngine>::G1Affine;
-pub type G2Point = <ark_bls12_381::Bls12_381 as PairingEngine>::G2Affine;
-pub type Poly = DensePolynomial<Fr>;
+pub use ark_bls12_381::Bls12_381;
+pub use ark_bn254::Bn254;
+pub use error::PlonkError;
+pub use keys::KEY_VERSION;
+pub use kgz::srs::Srs;
+pub use proof::{LookupProof, PermutationProof, PolyProof, Proof, PROOF_VERSION};
 
+/// runs the examples in `README.md` as doc tests
+#[doc = include_str!("../../README.md")]
+#[cfg(doctest)]
+pub struct ReadmeDoctests;
+
+/// runs generic tests, taking the curve as their only type parameter, on every supported curve
+#[cfg(test)]
+macro_rules! curve_tests {
+    ($($test:ident),* $(,)?) => {
+        mod bls12_381 {
+            $(
+                #[test]
+                fn $test() {
+                    super::$test::<ark_bls12_381::Bls12_381>()
+                }
+            )*
+        }
+        mod bn254 {
+            $(
+                #[test]
+                fn $test() {
+                    super::$test::<ark_bn254::Bn254>()
+                }
+            )*
+        }
+    };
+}
+#[cfg(test)]
+pub(crate) use curve_tests;
+
+pub type Poly<F> = DensePolynomial<F>;
+
+/// A circuit compiled for the pairing friendly curve `E`, its field is the scalar field of `E`.
+/// Its rows have `W` wires, a, b, c and then d, e, …
 #[derive(Debug)]
-pub struct CompiledCircuit<const INPUTS: usize, DESC: CircuitDescription<INPUTS>> {
-    gate_constrains: GateConstrains,
-    copy_constrains: CompiledPermutation<3>,
-    srs: Srs,
-    domain: GeneralEvaluationDomain<Fr>,
-    circuit_definition: PhantomData<DESC>,
+pub struct CompiledCircuit<E: PairingEngine, C: Circuit, const W: usize = 3> {
+    gate_constrains: GateConstrains<E>,
+    copy_constrains: CompiledPermutation<E::Fr, W>,
+    srs: Srs<E>,
+    domain: GeneralEvaluationDomain<E::Fr>,
+    /// synthesized again with the inputs of each proof
+    circuit: C,
     pub rows: usize,
+    /// rows reserved for the public inputs, `PI(w^row) = -input`
+    public_rows: Vec<usize>,
+    /// the size of each of the 3 slices of the quotient, grows with the degree of the custom gates
+    slice_degree: usize,
+    /// only present when the circuit declares tables
+    lookup: Option<LookupTables<E>>,
+    /// σ_a, σ_b, σ_c, …, one for each wire
+    sigmas: [Poly<E::Fr>; W],
+    sigma_commitments: [KzgCommitment<E>; W],
+    coset: CosetEvals<E::Fr>,
+    /// the srs in evaluation form over the domain, commits to the witness columns
+    basis: LagrangeBasis<E>,
+    /// built on first use, every proof hashes it
+    verifying_key: OnceLock<VerifyingKey<E, W>>,
+}
+#[derive(Debug)]
+struct GateConstrains<E: PairingEngine> {
+    q_l: Poly<E::Fr>,
+    q_r: Poly<E::Fr>,
+    q_o: Poly<E::Fr>,
+    q_m: Poly<E::Fr>,
+    q_c: Poly<E::Fr>,
+    fixed_commitments: [KzgCommitment<E>; 5],
+    /// q_d, q_e, …, the selectors of the wires after c, none with 3 wires
+    wire_selectors: Vec<Poly<E::Fr>>,
+    wire_commitments: Vec<KzgCommitment<E>>,
+    custom: Vec<CustomSelectors<E>>,
+}
+/// the fixed columns of the lookup argument
+#[derive(Debug)]
+struct LookupTables<E: PairingEngine> {
+    /// 1 in the rows doing a lookup
+    q_lookup: Poly<E::Fr>,
+    /// the id of the table (index + 1) in the rows doing a lookup
+    q_table: Poly<E::Fr>,
+    /// all the tables one after the other, padded with the last entry
+    values: Poly<E::Fr>,
+    /// the id of the table of each entry in `values`
+    ids: Poly<E::Fr>,
+    /// commitments to q_lookup, q_table, values and ids
+    commitments: [KzgCommitment<E>; 4],
+    /// the rows doing a lookup with the id of their table
+    queries: Vec<(usize, E::Fr)>,
+    /// evaluations of `values` and `ids` over the domain
+    entries: Vec<(E::Fr, E::Fr)>,
+}
+/// the selector columns of a registered custom gate
+#[derive(Debug)]
+struct CustomSelectors<E: PairingEngine> {
+    gate: Box<dyn CustomGate<E::Fr>>,
+    polys: Vec<Poly<E::Fr>>,
+    commitments: Vec<KzgCommitment<E>>,
+}
+
+/// the fixed polynomials evaluated over the coset the quotient is computed on
+#[derive(Debug)]
+struct CosetEvals<F: PrimeField> {
+    /// the subgroup of size `extension·n`, shifted by the multiplicative generator
+    domain: GeneralEvaluationDomain<F>,
+    extension: usize,
+    /// q_l, q_r, q_o, q_m and q_c
+    selectors: [Vec<F>; 5],
+    /// the selectors of the wires after c
+    wire_selectors: Vec<Vec<F>>,
+    /// a sigma for each wire
+    sigmas: Vec<Vec<F>>,
+    /// the selectors of each custom gate
+    custom: Vec<Vec<Vec<F>>>,
+    l0: Vec<F>,
+    /// q_lookup, q_table, values, ids and `L_(n-1)`
+    lookup: Option<[Vec<F>; 5]>,
+}
+
+/// Everything the prover needs, made by `CompiledCircuit::split`.
+#[derive(Debug)]
+pub struct ProvingKey<E: PairingEngine, C: Circuit, const W: usize = 3> {
+    circuit: CompiledCircuit<E, C, W>,
+}
+/// The commitments and parameters a proof is checked against, made by `CompiledCircuit::split`.
+///
+/// It does not depend on the circuit description, so proofs can be verified without its code,
+/// apart from the custom gates whose terms the verifier evaluates.
+#[derive(Debug)]
+pub struct VerifyingKey<E: PairingEngine, const W: usize = 3> {
+    domain: GeneralEvaluationDomain<E::Fr>,
+    public_rows: Vec<usize>,
+    slice_degree: usize,
+    /// the generator of g1 and the elements of g2
+    srs: Srs<E>,
+    /// commitments to q_l, q_r, q_o, q_m and q_c
+    fixed_commitments: [KzgCommitment<E>; 5],
+    /// commitments to the selectors of the wires after c
+    wire_commitments: Vec<KzgCommitment<E>>,
+    sigma_commitments: [KzgCommitment<E>; W],
+    cosets: [E::Fr; W],
+    custom: Vec<CustomCommitments<E>>,
+    /// commitments to q_lookup, q_table, values and ids
+    lookup: Option<[KzgCommitment<E>; 4]>,
+    /// the serialized key, hashed by [`VerifyingKey::digest`]
+    bytes: OnceLock<Vec<u8>>,
 }
+/// the selector commitments of a registered custom gate
 #[derive(Debug)]
-struct GateConstrains {
-    q_l: Poly,
-    q_r: Poly,
-    q_o: Poly,
-    q_m: Poly,
-    q_c: Poly,
-    fixed_commitments: [KzgCommitment; 5],
+struct CustomCommitments<E: PairingEngine> {
+    gate: Box<dyn CustomGate<E::Fr>>,
+    commitments: Vec<KzgCommitment<E>>,
 }
@@ -0,0 +1,33 @@
+use ark_ff::PrimeField;
+
+/// A fixed table the circuit can look values up in with `Var::lookup`.
+///
+/// Tables are declared in `CircuitDescription::tables` and referred to by name.
+#[derive(Debug, Clone)]
+pub struct Table<F> {
+    name: &'static str,
+    values: Vec<F>,
+}
+
+impl<F: PrimeField> Table<F> {
+    /// repeated values are dropped, the table keeps the order of the first occurrences
+    pub fn new(name: &'static str, values: impl IntoIterator<Item = impl Into<F>>) -> Self {
+        let mut seen = std::collections::HashSet::new();
+        let values = values
+            .into_iter()
+            .map(Into::into)
+            .filter(|value| seen.insert(*value))
+            .collect();
+        Self { name, values }
+    }
+    /// the values `0..2^bits`, looking a value up in it is a range check
+    pub fn range(name: &'static str, bits: u32) -> Self {
+        Self::new(name, 0..1_u64 << bits)
+    }
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+    pub fn values(&self) -> &[F] {
+        &self.values
+    }
+}
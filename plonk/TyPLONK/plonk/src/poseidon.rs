@@ -0,0 +1,281 @@
+//! The Poseidon permutation over the scalar field of a curve, with a width of 3 and the S-box x^5.
+//!
+//! There are 8 full rounds, 4 before and 4 after 57 partial rounds, which apply the S-box to the
+//! first element only. Each round adds its constants, applies the S-box and multiplies by the MDS
+//! matrix. The parameters are the ones of the reference implementation of the Poseidon paper,
+//! `generate_parameters_grain.sage`: the round constants and then the MDS matrix, a Cauchy matrix
+//! `1/(x_i + y_j)`, are drawn from a Grain LFSR seeded with the field size, the width and the
+//! rounds. Over BLS12-381 this is the reference `poseidonperm_x5_255_3`, over BN254 the
+//! `poseidonperm_x5_254_3` of circomlib.
+//!
+//! The rounds are written once for any type with the operations they need, so this native
+//! permutation and the gadget of [`crate::gadgets::poseidon`] compute the same function.
+use ark_ff::{BigInteger, PrimeField};
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, VecDeque},
+    ops::{Add, Mul},
+    sync::{Mutex, OnceLock},
+};
+
+pub const WIDTH: usize = 3;
+/// the number of elements absorbed by each permutation of the sponge
+pub const RATE: usize = 2;
+pub const FULL_ROUNDS: usize = 8;
+pub const PARTIAL_ROUNDS: usize = 57;
+
+/// the round constants and the MDS matrix
+#[derive(Debug)]
+pub struct PoseidonParams<F> {
+    pub round_constants: Vec<[F; WIDTH]>,
+    pub mds: [[F; WIDTH]; WIDTH],
+}
+
+/// computed on first use for each field, statics can not be generic so they are kept by type
+pub fn params<F: PrimeField>() -> &'static PoseidonParams<F> {
+    type Cache = HashMap<TypeId, &'static (dyn Any + Send + Sync)>;
+    static PARAMS: OnceLock<Mutex<Cache>> = OnceLock::new();
+    let mut cache = PARAMS.get_or_init(Default::default).lock().unwrap();
+    let params = *cache.entry(TypeId::of::<F>()).or_insert_with(|| {
+        let bits = F::size_in_bits();
+        let mut grain = Grain::new(bits);
+        // rejection sampling, the values above the modulus are skipped
+        let mut constant = || loop {
+            if let Some(constant) = F::from_repr(grain.bigint::<F>(bits)) {
+                break constant;
+            }
+        };
+        let round_constants = (0..FULL_ROUNDS + PARTIAL_ROUNDS)
+            .map(|_| std::array::from_fn(|_| constant()))
+            .collect();
+        // the x_i then the y_j, reduced
+        let points: [F; 2 * WIDTH] = std::array::from_fn(|_| {
+            let bytes = grain.bigint::<F>(bits).to_bytes_le();
+            F::from_le_bytes_mod_order(&bytes)
+        });
+        let (xs, ys) = points.split_at(WIDTH);
+        let mds = std::array::from_fn(|i| {
+            std::array::from_fn(|j| (xs[i] + ys[j]).inverse().expect("distinct points"))
+        });
+        Box::leak(Box::new(PoseidonParams::<F> {
+            round_constants,
+            mds,
+        }))
+    });
+    params.downcast_ref().unwrap()
+}
+
+/// the LFSR of the reference script, its 80 bits of state are shifted left by one at each step
+struct Grain(VecDeque<bool>);
+
+impl Grain {
+    /// seeded with the parameters, most significant bit first: a prime field (1 on 2 bits), the
+    /// S-box x^alpha (0 on 4 bits), the bits of the field and the width on 12 bits, the full and
+    /// partial rounds on 10 bits, then 30 ones. The first 160 bits are discarded
+    fn new(field_bits: usize) -> Self {
+        let seed = [
+            (1, 2),
+            (0, 4),
+            (field_bits, 12),
+            (WIDTH, 12),
+            (FULL_ROUNDS, 10),
+            (PARTIAL_ROUNDS, 10),
+        ];
+        let state = seed
+            .into_iter()
+            .flat_map(|(value, bits)| (0..bits).rev().map(move |i| value >> i & 1 == 1))
+            .chain(std::iter::repeat_n(true, 30))
+            .collect();
+        let mut grain = Self(state);
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+    fn step(&mut self) -> bool {
+        let state = &self.0;
+        let bit = [62, 51, 38, 23, 13, 0]
+            .iter()
+            .fold(false, |bit, i| bit ^ state[*i]);
+        self.0.pop_front();
+        self.0.push_back(bit);
+        bit
+    }
+    /// the bits are drawn in pairs, the second one is kept when the first one is 1
+    fn bit(&mut self) -> bool {
+        loop {
+            let keep = self.step();
+            let bit = self.step();
+            if keep {
+                return bit;
+            }
+        }
+    }
+    /// an integer of `bits` bits, most significant first
+    fn bigint<F: PrimeField>(&mut self, bits: usize) -> F::BigInt {
+        let bits = (0..bits).map(|_| self.bit()).collect::<Vec<_>>();
+        F::BigInt::from_bits_be(&bits)
+    }
+}
+
+/// the rounds of the permutation, `constant` lifts the round constants and `sbox` is x^5
+pub(crate) fn rounds<F: PrimeField, T>(
+    mut state: [T; WIDTH],
+    constant: impl Fn(F) -> T,
+    sbox: impl Fn(&T) -> T,
+) -> [T; WIDTH]
+where
+    T: Clone + Add<Output = T> + Mul<F, Output = T>,
+{
+    let PoseidonParams {
+        round_constants,
+        mds,
+    } = params();
+    let half = FULL_ROUNDS / 2;
+    for (round, constants) in round_constants.iter().enumerate() {
+        state = std::array::from_fn(|i| state[i].clone() + constant(constants[i]));
+        let full = round < half || round >= half + PARTIAL_ROUNDS;
+        for (i, x) in state.iter_mut().enumerate() {
+            if full || i == 0 {
+                *x = sbox(x);
+            }
+        }
+        state = std::array::from_fn(|i| {
+            (1..WIDTH).fold(state[0].clone() * mds[i][0], |acc, j| {
+                acc + state[j].clone() * mds[i][j]
+            })
+        });
+    }
+    state
+}
+
+/// the sponge over the permutation `permute`. The capacity, the first element, starts at the
+/// number of inputs, so that inputs of different lengths do not collide. Returns the first
+/// element of the rate
+pub(crate) fn sponge<F: PrimeField, T>(
+    inputs: &[T],
+    constant: impl Fn(F) -> T,
+    permute: impl Fn([T; WIDTH]) -> [T; WIDTH],
+) -> T
+where
+    T: Clone + Add<Output = T>,
+{
+    let mut state = [
+        constant(F::from(inputs.len() as u64)),
+        constant(F::zero()),
+        constant(F::zero()),
+    ];
+    // an empty input is still permuted once
+    let chunks = match inputs.is_empty() {
+        true => vec![&[][..]],
+        false => inputs.chunks(RATE).collect(),
+    };
+    for chunk in chunks {
+        for (cell, input) in state[1..].iter_mut().zip(chunk) {
+            *cell = cell.clone() + input.clone();
+        }
+        state = permute(state);
+    }
+    state[1].clone()
+}
+
+pub fn permute<F: PrimeField>(state: [F; WIDTH]) -> [F; WIDTH] {
+    rounds(state, |constant| constant, |x| x.pow([5]))
+}
+
+/// hashes any number of elements with the sponge: the capacity starts at the number of inputs,
+/// and the first element of the rate is returned
+pub fn hash<F: PrimeField>(inputs: &[F]) -> F {
+    sponge(inputs, |constant| constant, permute)
+}
+
+/// the hash of the nodes of a merkle tree
+pub fn hash2<F: PrimeField>(left: F, right: F) -> F {
+    hash(&[left, right])
+}
+
+/// the root of the tree with `leaf` at `index`, `siblings` going from the leaf to the root. Bit i
+/// of the index is 1 when the node at height i is a right child
+pub fn merkle_root<F: PrimeField>(leaf: F, siblings: &[F], index: u64) -> F {
+    siblings
+        .iter()
+        .enumerate()
+        .fold(leaf, |node, (height, sibling)| match index >> height & 1 {
+            0 => hash2(node, *sibling),
+            _ => hash2(*sibling, node),
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hash, hash2, merkle_root, params, permute};
+    use crate::curve_tests;
+    use ark_ec::PairingEngine;
+    use ark_ff::{PrimeField, Zero};
+    use ark_serialize::CanonicalSerialize;
+
+    fn hex<F: PrimeField>(value: F) -> String {
+        let mut bytes = vec![];
+        value.into_repr().serialize(&mut bytes).unwrap();
+        bytes
+            .iter()
+            .rev()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// the permutation of `[0, 1, 2]`, big endian
+    fn vector<F: PrimeField>(permutation: [&str; 3]) {
+        let state = permute([0_u64, 1, 2].map(F::from));
+        assert_eq!(state.map(hex), permutation);
+    }
+    /// the test vector of `poseidonperm_x5_255_3` in the reference implementation
+    #[test]
+    fn vector_bls12_381() {
+        vector::<ark_bls12_381::Fr>([
+            "28ce19420fc246a05553ad1e8c98f5c9d67166be2c18e9e4cb4b4e317dd2a78a",
+            "51f3e312c95343a896cfd8945ea82ba956c1118ce9b9859b6ea56637b4b1ddc4",
+            "3b2b69139b235626a0bfb56c9527ae66a7bf486ad8c11c14d1da0c69bbe0f79a",
+        ]);
+    }
+    /// the test vector of `poseidonperm_x5_254_3`, the first element is the circomlib hash of 1, 2
+    #[test]
+    fn vector_bn254() {
+        vector::<ark_bn254::Fr>([
+            "115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a",
+            "0fca49b798923ab0239de1c9e7a4a9a2210312b6a2f616d18b5a87f9b628ae29",
+            "0e7ae82e40091e63cbd4f16a6d16310b3729d4b6e138fcf54110e2867045a30c",
+        ]);
+    }
+
+    fn mds_is_invertible<E: PairingEngine>() {
+        let [[a, b, c], [d, e, f], [g, h, i]] = params::<E::Fr>().mds;
+        let det = a * (e * i - f * h) - b * (d * i - f * g) + c * (d * h - e * g);
+        assert!(!det.is_zero());
+    }
+    fn sponge<E: PairingEngine>() {
+        let inputs = [1_u64, 2, 3, 4, 5].map(E::Fr::from);
+        // the length is absorbed in the capacity
+        assert_ne!(
+            hash(&inputs[..2]),
+            hash(&[inputs[0], inputs[1], E::Fr::zero()])
+        );
+        assert_ne!(hash(&inputs), hash(&inputs[..4]));
+        assert_eq!(hash(&inputs[..2]), hash2(inputs[0], inputs[1]));
+        // a single permutation with the capacity at 2
+        let state = permute([E::Fr::from(2_u64), inputs[0], inputs[1]]);
+        assert_eq!(hash2(inputs[0], inputs[1]), state[1]);
+    }
+    fn merkle<E: PairingEngine>() {
+        let leaves = [3_u64, 1, 4, 1, 5, 9, 2, 6].map(E::Fr::from);
+        let level1 = [0, 2, 4, 6].map(|i| hash2(leaves[i], leaves[i + 1]));
+        let level2 = [0, 2].map(|i| hash2(level1[i], level1[i + 1]));
+        let root = hash2(level2[0], level2[1]);
+        // the path of the leaf at 5
+        let siblings = [leaves[4], level1[3], level2[0]];
+        assert_eq!(merkle_root(leaves[5], &siblings, 5), root);
+        assert_ne!(merkle_root(leaves[5], &siblings, 4), root);
+    }
+
+    curve_tests!(mds_is_invertible, sponge, merkle);
+}
@@ -1,411 +1,657 @@
 use crate::{
-    builder::ComputeVar,
-    description::CircuitDescription,
-    utils::{add_to_poly, l0_poly, SlicedPoly},
-    CompiledCircuit, GateConstrains, Poly,
+    builder::{with_arena, ComputeVar},
+    description::{Circuit, ConstraintSystem},
+    transcript::{Blake2bHash, Transcript, TranscriptHash},
+    utils::{add_to_poly, blinder, l0_poly, SlicedPoly},
+    CompiledCircuit, GateConstrains, PlonkError, Poly, VerifyingKey,
 };
-use ark_bls12_381::Fr;
-use ark_ff::{Field, One, UniformRand, Zero};
-use ark_poly::{
-    univariate::DensePolynomial, EvaluationDomain, Evaluations, Polynomial, UVPolynomial,
-};
-use challenges::ChallengeGenerator;
-use kgz::{KzgCommitment, KzgOpening, KzgScheme};
-use permutation::CompiledPermutation;
-use std::{
-    convert::TryInto,
-    fmt::Display,
-    ops::{Add, Mul},
-    rc::Rc,
-    sync::Mutex,
-};
-
-mod challenges;
+use ark_ec::PairingEngine;
+use ark_ff::{FftField, Field, One, UniformRand, Zero};
+use ark_poly::{EvaluationDomain, Evaluations, GeneralEvaluationDomain, Polynomial};
+use kgz::{srs::LagrangeBasis, KzgBatchOpening, KzgCommitment, KzgError, KzgScheme, PairingCheck};
+use lookup::{LookupChallenges, LookupEvals};
+use quotient::quotient_polynomial;
+use std::{convert::TryInto, fmt::Display, ops::Mul};
+
+mod lookup;
+mod quotient;
+mod serialize;
+#[cfg(test)]
+mod test;
+
+pub use serialize::PROOF_VERSION;
+
+impl<E: PairingEngine, C: Circuit, const W: usize> CompiledCircuit<E, C, W> {
+    /// takes the inputs of the circuit in order, a vec or an array. Fails with
+    /// `PlonkError::Unsatisfied` when they do not pass `check`
+    pub fn prove(
+        &self,
+        inputs: impl IntoIterator<Item = impl Into<E::Fr>>,
+    ) -> Result<Proof<E, W>, PlonkError> {
+        self.prove_with_hash::<Blake2bHash>(inputs)
+    }
+    /// proves with `H` hashing the transcript, the proof must be verified with the same hash
+    pub fn prove_with_hash<H: TranscriptHash>(
+        &self,
+        inputs: impl IntoIterator<Item = impl Into<E::Fr>>,
+    ) -> Result<Proof<E, W>, PlonkError> {
+        let inputs = self.inputs(inputs)?;
+        let advice = self.advice(inputs.clone());
+        // the exposed values sit in column a of the public input rows
+        let public_inputs = self
+            .public_rows
+            .iter()
+            .map(|row| advice[0][*row])
+            .collect::<Vec<_>>();
+        // the padding rows are 0, the polynomials are blinded once interpolated
+        let advice = advice.map(|mut col| {
+            col.resize(self.rows, E::Fr::zero());
+            col
+        });
 
-impl<const I: usize, C: CircuitDescription<I>> CompiledCircuit<I, C> {
-    pub fn prove(&self, inputs: [impl Into<Fr>; I], public_inputs: Vec<impl Into<Fr>>) -> Proof {
-        let inputs = inputs.map(Into::into);
-        let public_inputs = public_inputs
+        // the gates are only checked one by one when the proof fails, to report the broken ones
+        match prove::<E, C, H, W>(self, advice, public_inputs) {
+            Err(PlonkError::Unsatisfied(_)) => {
+                let failures = self.check_advice(&self.advice(inputs)).err();
+                Err(PlonkError::Unsatisfied(failures.unwrap_or_default()))
+            }
+            result => result,
+        }
+    }
+    /// the inputs as elements of the field, there must be as many as the circuit takes
+    pub(crate) fn inputs(
+        &self,
+        inputs: impl IntoIterator<Item = impl Into<E::Fr>>,
+    ) -> Result<Vec<E::Fr>, PlonkError> {
+        let inputs = inputs.into_iter().map(Into::into).collect::<Vec<_>>();
+        let expected = self.circuit.inputs();
+        match inputs.len() == expected {
+            true => Ok(inputs),
+            false => Err(PlonkError::Inputs {
+                expected,
+                got: inputs.len(),
+            }),
+        }
+    }
+    /// synthesizes the circuit on the inputs, the columns of the wires of the rows it adds
+    pub(crate) fn advice(&self, inputs: Vec<E::Fr>) -> [Vec<E::Fr>; W] {
+        let inputs = inputs
             .into_iter()
-            .map(Into::into)
-            .collect::<Vec<_>>();
+            .map(|value| ComputeVar::<E::Fr, W>::Wire { value })
+            .collect();
+        // each proof writes its rows to its own arena
+        with_arena(|| self.circuit.synthesize(&mut ConstraintSystem::new(inputs)))
+    }
 
-        let advice: [Vec<Fr>; 3] = Default::default();
-        let advice = Rc::new(Mutex::new(advice));
-        let inputs = inputs.map(|input| ComputeVar {
-            value: input,
-            advice_values: advice.clone(),
-        });
+    pub fn verify(&self, proof: Proof<E, W>) -> Result<(), PlonkError> {
+        self.verifying_key().verify(proof)
+    }
+    pub fn verify_with_hash<H: TranscriptHash>(
+        &self,
+        proof: Proof<E, W>,
+    ) -> Result<(), PlonkError> {
+        self.verifying_key().verify_with_hash::<H>(proof)
+    }
+    pub fn verify_batch(&self, proofs: &[Proof<E, W>]) -> Result<(), PlonkError> {
+        self.verifying_key().verify_batch(proofs)
+    }
+    pub fn invalid_proofs(&self, proofs: &[Proof<E, W>]) -> Vec<usize> {
+        self.verifying_key().invalid_proofs(proofs)
+    }
+}
 
-        C::run(inputs);
-        let advice = Rc::try_unwrap(advice).unwrap().into_inner().unwrap();
+impl<E: PairingEngine, const W: usize> VerifyingKey<E, W> {
+    pub fn verify(&self, proof: Proof<E, W>) -> Result<(), PlonkError> {
+        self.verify_with_hash::<Blake2bHash>(proof)
+    }
+    pub fn verify_with_hash<H: TranscriptHash>(
+        &self,
+        proof: Proof<E, W>,
+    ) -> Result<(), PlonkError> {
+        let scheme = KzgScheme::new(&self.srs);
+        let check = pairing_check::<E, H, W>(self, self.digest::<H>(), &proof, &scheme)?;
+        match scheme.check(&check) {
+            true => Ok(()),
+            false => Err(PlonkError::InvalidProof),
+        }
+    }
+    /// verifies all the proofs with a single pairing check, their checks are combined with
+    /// random factors
+    pub fn verify_batch(&self, proofs: &[Proof<E, W>]) -> Result<(), PlonkError> {
+        self.verify_batch_with_hash::<Blake2bHash>(proofs)
+    }
+    pub fn verify_batch_with_hash<H: TranscriptHash>(
+        &self,
+        proofs: &[Proof<E, W>],
+    ) -> Result<(), PlonkError> {
+        let scheme = KzgScheme::new(&self.srs);
+        let digest = self.digest::<H>();
         let mut rng = rand::thread_rng();
-        // r list is 3 different random values
-        // after extends col values will from 5 to 8(add 3)
-        // advice now is poly for input data
-        let advice = advice
-            .map(|mut col| {
-                col.resize(self.rows - 3, Fr::zero());
-                let r = [(); 3].map(|_| Fr::rand(&mut rng));
-                col.extend_from_slice(&r);
-                col
+        let checks = proofs
+            .iter()
+            .map(|proof| pairing_check::<E, H, W>(self, digest, proof, &scheme))
+            .collect::<Result<Vec<_>, _>>()?;
+        let checks = checks
+            .into_iter()
+            .map(|check| (check, E::Fr::rand(&mut rng)));
+        match scheme.check(&PairingCheck::combine(checks)) {
+            true => Ok(()),
+            false => Err(PlonkError::InvalidProof),
+        }
+    }
+    /// the indices of the proofs that do not verify. All of them are checked as a batch first,
+    /// and one by one only when the batch fails
+    pub fn invalid_proofs(&self, proofs: &[Proof<E, W>]) -> Vec<usize> {
+        self.invalid_proofs_with_hash::<Blake2bHash>(proofs)
+    }
+    pub fn invalid_proofs_with_hash<H: TranscriptHash>(
+        &self,
+        proofs: &[Proof<E, W>],
+    ) -> Vec<usize> {
+        if self.verify_batch_with_hash::<H>(proofs).is_ok() {
+            return vec![];
+        }
+        let scheme = KzgScheme::new(&self.srs);
+        let digest = self.digest::<H>();
+        proofs
+            .iter()
+            .enumerate()
+            .filter(|(_, proof)| {
+                let check = pairing_check::<E, H, W>(self, digest, proof, &scheme);
+                !check.is_ok_and(|check| scheme.check(&check))
             })
-            .map(|col| Evaluations::from_vec_and_domain(col, self.domain).interpolate());
-
-        let mut public_inputs = public_inputs.to_vec();
-        public_inputs.resize(self.rows, Fr::zero());
-
-        let proof = prove(&self, advice, public_inputs);
-        proof
+            .map(|(index, _)| index)
+            .collect()
     }
+}
 
-    pub fn verify(&self, proof: Proof) -> bool {
-        let scheme = KzgScheme::new(&self.srs);
-        verify(&self, proof, &scheme)
+/// interpolates PI(x), which is `-input` at the public input rows and 0 elsewhere
+fn public_inputs_poly<F: FftField>(
+    public_rows: &[usize],
+    public_inputs: &[F],
+    domain: GeneralEvaluationDomain<F>,
+) -> Poly<F> {
+    let mut evals = vec![F::zero(); domain.size()];
+    for (row, input) in public_rows.iter().zip(public_inputs) {
+        evals[*row] = -*input;
     }
+    Evaluations::from_vec_and_domain(evals, domain).interpolate()
 }
 
 #[derive(Debug)]
-pub struct PolyProof {
-    commitment: KzgCommitment,
-    opening: KzgOpening,
+pub struct PolyProof<E: PairingEngine> {
+    pub(crate) commitment: KzgCommitment<E>,
+    pub(crate) eval: E::Fr,
 }
-impl Display for PolyProof {
+impl<E: PairingEngine> Display for PolyProof<E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "polyprof:")?;
         writeln!(f, "commitment:{:#?}", self.commitment)?;
-        writeln!(f, "point:{}", self.opening.0)?;
-        writeln!(f, "val:{}", self.opening.1)?;
+        writeln!(f, "val:{}", self.eval)?;
         Ok(())
     }
 }
+/// a commitment evaluated at the evaluation point and at the next row
+#[derive(Debug)]
+pub struct PermutationProof<E: PairingEngine> {
+    pub(crate) commitment: KzgCommitment<E>,
+    z: E::Fr,
+    zw: E::Fr,
+}
+/// the commitments and evaluations of the lookup argument
 #[derive(Debug)]
-pub struct PermutationProof {
-    commitment: KzgCommitment,
-    z: KzgOpening,
-    zw: KzgOpening,
+pub struct LookupProof<E: PairingEngine> {
+    f: PolyProof<E>,
+    h1: PermutationProof<E>,
+    h2: PermutationProof<E>,
+    accumulator: PermutationProof<E>,
+    /// the compressed table at the evaluation point and at the next row
+    table: [E::Fr; 2],
+    /// q_lookup and q_table at the evaluation point
+    selectors: [E::Fr; 2],
 }
+/// A proof of a circuit with `W` wires.
 #[derive(Debug)]
-pub struct Proof {
-    pub a: PolyProof,
-    pub b: PolyProof,
-    pub c: PolyProof,
-    pub permutation: PermutationProof,
-    pub evaluation_point: Fr,
-    t: [KzgCommitment; 3],
-    r: KzgOpening,
-    pub public_inputs: Vec<Fr>,
+pub struct Proof<E: PairingEngine, const W: usize = 3> {
+    /// a, b, c, …
+    pub wires: [PolyProof<E>; W],
+    pub permutation: PermutationProof<E>,
+    pub evaluation_point: E::Fr,
+    pub(crate) t: [KzgCommitment<E>; 3],
+    /// the sigmas of all the wires but the last at `evaluation_point`, `W - 1` of them
+    sigma: Vec<E::Fr>,
+    /// the wires at `evaluation_point * w`, only present when the circuit has custom gates
+    pub(crate) next_row: Option<[E::Fr; W]>,
+    /// only present when the circuit has tables
+    pub(crate) lookup: Option<LookupProof<E>>,
+    /// witnesses of the batch openings at `evaluation_point` and `evaluation_point * w`
+    pub(crate) openings: [E::G1Affine; 2],
+    pub public_inputs: Vec<E::Fr>,
+}
+impl<E: PairingEngine, const W: usize> Proof<E, W> {
+    /// the evaluations at the point and at the next row, in the order of the batch openings.
+    /// the linearisation polynomial, which is 0 at the point, is opened last and not included
+    pub(crate) fn evaluations(&self) -> [Vec<E::Fr>; 2] {
+        let mut at_point = self
+            .wires
+            .iter()
+            .map(|proof| proof.eval)
+            .collect::<Vec<_>>();
+        at_point.extend_from_slice(&self.sigma);
+        at_point.push(self.permutation.z);
+        let mut next_row = vec![self.permutation.zw];
+        next_row.extend(self.next_row.iter().flatten());
+        if let Some(lookup) = &self.lookup {
+            let [lookup_point, lookup_next] = lookup.evaluations();
+            at_point.extend(lookup_point);
+            next_row.extend(lookup_next);
+        }
+        [at_point, next_row]
+    }
+    /// the commitments to the polynomials of `evaluations`
+    fn commitments(&self, vk: &VerifyingKey<E, W>, zeta: E::Fr) -> [Vec<KzgCommitment<E>>; 2] {
+        let advice = self.wires.each_ref().map(|proof| proof.commitment);
+        let mut at_point = advice.to_vec();
+        at_point.extend_from_slice(&vk.sigma_commitments[..W - 1]);
+        at_point.push(self.permutation.commitment);
+        let mut next_row = vec![self.permutation.commitment];
+        if self.next_row.is_some() {
+            next_row.extend_from_slice(&advice);
+        }
+        if let (Some(lookup), Some(fixed)) = (&self.lookup, &vk.lookup) {
+            let [lookup_point, lookup_next] = lookup.commitments(fixed, zeta);
+            at_point.extend(lookup_point);
+            next_row.extend(lookup_next);
+        }
+        [at_point, next_row]
+    }
 }
-fn prove<const I: usize, C: CircuitDescription<I>>(
-    circuit: &CompiledCircuit<I, C>,
-    advice: [Poly; 3],
-    public_inputs: Vec<Fr>,
-) -> Proof {
+fn prove<E: PairingEngine, C: Circuit, H: TranscriptHash, const W: usize>(
+    circuit: &CompiledCircuit<E, C, W>,
+    values: [Vec<E::Fr>; W],
+    public_inputs: Vec<E::Fr>,
+) -> Result<Proof<E, W>, PlonkError> {
     let scheme = KzgScheme::new(&circuit.srs);
     let domain = &circuit.domain;
     let w = domain.element(1);
 
-    let public_inputs_poly =
-        Evaluations::from_vec_and_domain(public_inputs.clone(), *domain).interpolate();
-    // [w_a, w_b, w_c] commitments
-    let commitments = {
-        let [a, b, c] = &advice;
-        round1(&a, &b, &c, &scheme)
-    };
-    let challenge_generator = ChallengeGenerator::with_digest(&commitments);
-    let [beta, gamma] = challenge_generator.generate_challenges();
-    let values = advice
-        .clone()
-        .map(|e| e.evaluate_over_domain(*domain).evals.to_vec());
+    let public_inputs_poly = public_inputs_poly(&circuit.public_rows, &public_inputs, *domain);
+    // the wires are opened at the next row too with custom gates
+    let openings = 1 + !circuit.gate_constrains.custom.is_empty() as usize;
+    // [w_a, w_b, w_c, …] commitments
+    let (polys, commitments): (Vec<_>, Vec<_>) = values
+        .iter()
+        .map(|col| commit_column(col.clone(), openings, *domain, &circuit.basis, &scheme))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
+    let polys: [Poly<E::Fr>; W] = polys.try_into().unwrap();
+    let commitments: [KzgCommitment<E>; W] = commitments.try_into().unwrap();
+    let mut transcript = start_transcript::<E, H>(
+        circuit.verifying_key().digest::<H>(),
+        &public_inputs,
+        &commitments,
+    );
+    let [beta, gamma, zeta] = transcript.challenges([b"beta", b"gamma", b"zeta"]);
 
-    // accumulator poly: acc_poly -> acc(x)  acc_poly_w -> acc(x*w)
-    let (acc_poly, acc_commitment, acc_poly_w) = {
-        let domain = domain;
+    let lookup = circuit.lookup.as_ref().map(|tables| {
+        let witness = tables.witness(&values[0], zeta, *domain, &circuit.basis, &scheme)?;
+        for (label, commitment) in LOOKUP_LABELS.iter().zip(witness.commitments.iter()) {
+            transcript.append_commitment(label, commitment);
+        }
+        let [delta, epsilon] = transcript.challenges([b"delta", b"epsilon"]);
+        let challenges = LookupChallenges {
+            zeta,
+            delta,
+            epsilon,
+        };
+        let acc = tables.accumulator(&witness, &challenges);
+        let (acc, acc_commitment) = commit_column(acc, 2, *domain, &circuit.basis, &scheme)?;
+        Ok::<_, PlonkError>((tables, witness, acc, acc_commitment, challenges))
+    });
+    let lookup = lookup.transpose()?;
+
+    // accumulator poly: acc_poly -> acc(x)
+    let (acc_poly, acc_commitment) = {
         let mut evals = circuit.copy_constrains.prove(&values, beta, gamma);
         evals.pop();
-        let acc_shifted = {
-            let mut evals_shifted = evals.clone();
-            evals_shifted.rotate_left(1);
-            let evals = Evaluations::from_vec_and_domain(evals_shifted, *domain);
-            evals.interpolate()
-        };
-        let acc = Evaluations::from_vec_and_domain(evals, domain.clone());
-        let acc = acc.interpolate();
-        let commitment = scheme.commit(&acc);
-        (acc, commitment, acc_shifted)
+        // opened at the point and at the next row
+        commit_column(evals, 2, *domain, &circuit.basis, &scheme)?
     };
-    let [a, b, c] = advice;
-    let mut challenge_generator = ChallengeGenerator::with_digest(&commitments);
-    challenge_generator.digest(&acc_commitment);
+    transcript.append_commitment(b"permutation", &acc_commitment);
+    if let Some((_, _, _, acc_commitment, _)) = &lookup {
+        transcript.append_commitment(b"lookup accumulator", acc_commitment);
+    }
+    let alpha = transcript.challenge(b"alpha");
 
-    let [alpha, evaluation_point] = challenge_generator.generate_challenges();
     let proof = {
-        let public_eval = public_inputs_poly.evaluate(&evaluation_point);
-        let quotient = quotient_polynomial(
+        let wires = polys.each_ref().map(|poly| circuit.coset.fft(poly));
+        let lookup_term = lookup
+            .as_ref()
+            .map(|(tables, witness, acc, _, challenges)| {
+                let coset = &circuit.coset;
+                tables.quotient_term(coset, &wires[0], witness, acc, challenges, alpha, *domain)
+            });
+        let mut quotient = quotient_polynomial(
             circuit,
-            [&a, &b, &c],
-            (&acc_poly, &acc_poly_w),
+            &wires,
+            &acc_poly,
             [alpha, beta, gamma],
-            public_inputs_poly,
-        );
-        let mut commitments = commitments.into_iter();
-        let openings = [a, b, c].map(|poly| {
-            let commitment = commitments.next().unwrap();
-            let opening = scheme.open(poly, evaluation_point);
-            PolyProof {
-                commitment,
-                opening,
-            }
-        });
-        let advice_evals = openings
+            &public_inputs_poly,
+            lookup_term,
+        )
+        .ok_or(PlonkError::Unsatisfied(vec![]))?;
+        quotient.blind(&mut rand::thread_rng());
+        let t = quotient.commit(&scheme)?;
+        for commitment in t.iter() {
+            transcript.append_commitment(b"t", commitment);
+        }
+        let evaluation_point = transcript.challenge(b"evaluation point");
+        let point_w = evaluation_point * w;
+        let public_eval = public_inputs_poly.evaluate(&evaluation_point);
+        // all the sigmas but the last are opened, the last one is in the linearisation
+        let sigmas = &circuit.sigmas[..W - 1];
+        let advice_evals = polys
+            .each_ref()
+            .map(|poly| poly.evaluate(&evaluation_point));
+        let next_row = match circuit.gate_constrains.custom.is_empty() {
+            true => None,
+            false => Some(polys.each_ref().map(|poly| poly.evaluate(&point_w))),
+        };
+        let next_evals = next_row.unwrap_or([E::Fr::zero(); W]);
+        let sigma = sigmas
             .iter()
-            .map(|open| open.opening.1)
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap();
-        let [a, b, c] = openings;
-        let z = scheme.open(acc_poly.clone(), evaluation_point);
-        let zw = scheme.open(acc_poly.clone(), evaluation_point * w);
+            .map(|poly| poly.evaluate(&evaluation_point))
+            .collect();
+        let acc_evals = [evaluation_point, point_w].map(|point| acc_poly.evaluate(&point));
+        let (lookup, lookup_eval, [lookup_point, lookup_next]) = match lookup {
+            Some((tables, witness, acc, acc_commitment, challenges)) => {
+                let (proof, polys) =
+                    tables.open(witness, acc, acc_commitment, zeta, evaluation_point, w);
+                let evals =
+                    LookupEvals::from_proof(&proof, advice_evals[0], evaluation_point, *domain);
+                (Some(proof), evals.identity(&challenges, alpha), polys)
+            }
+            None => (None, E::Fr::zero(), Default::default()),
+        };
 
         let linearisation = linearisation_poly(
             circuit,
             advice_evals,
-            [z, zw].map(|open| open.1),
-            acc_poly,
+            next_evals,
+            acc_evals,
+            acc_poly.clone(),
             [alpha, beta, gamma],
             evaluation_point,
             &quotient,
-            public_eval,
+            public_eval + lookup_eval,
         );
-        let r = scheme.open(linearisation, evaluation_point);
-        let permutation = PermutationProof {
-            commitment: acc_commitment,
-            z,
-            zw,
-        };
-        let t = quotient.commit(&scheme);
-        Proof {
-            a,
-            b,
-            c,
-            permutation,
+        let mut proof = Proof {
+            wires: std::array::from_fn(|i| PolyProof {
+                commitment: commitments[i],
+                eval: advice_evals[i],
+            }),
+            permutation: PermutationProof {
+                commitment: acc_commitment,
+                z: acc_evals[0],
+                zw: acc_evals[1],
+            },
             evaluation_point,
             t,
-            r,
+            sigma,
+            next_row,
+            lookup,
+            openings: [E::G1Affine::zero(); 2],
             public_inputs,
+        };
+        for eval in proof.evaluations().iter().flatten() {
+            transcript.append_scalar(b"evaluation", eval);
+        }
+        let challenge = transcript.challenge(b"opening");
+        // in the order of `Proof::evaluations`
+        let mut at_point = polys.to_vec();
+        at_point.extend_from_slice(sigmas);
+        at_point.push(acc_poly.clone());
+        at_point.extend(lookup_point);
+        at_point.push(linearisation);
+        let mut at_next_row = vec![acc_poly];
+        if proof.next_row.is_some() {
+            at_next_row.extend(polys);
         }
+        at_next_row.extend(lookup_next);
+        proof.openings = [
+            scheme.open_batch(&at_point, evaluation_point, challenge)?.0,
+            scheme.open_batch(&at_next_row, point_w, challenge)?.0,
+        ];
+        proof
     };
-    proof
+    Ok(proof)
 }
-fn verify<const I: usize, C: CircuitDescription<I>>(
-    circuit: &CompiledCircuit<I, C>,
-    proof: Proof,
-    scheme: &KzgScheme,
-) -> bool {
-    let domain = &circuit.domain;
-    let challenges = verify_challenges(&proof);
-
-    let (alpha, beta, gamma, point) = challenges;
-    let mut public_inputs = proof.public_inputs.clone();
-    public_inputs.resize(circuit.rows, Fr::zero());
-    let public_eval = Evaluations::from_vec_and_domain(public_inputs.clone(), *domain)
-        .interpolate()
-        .evaluate(&point);
-    let acc_commitment = proof.permutation.commitment.clone();
-    let eval_point = proof.evaluation_point;
-    if eval_point != point {
-        return false;
+/// interpolates the values of a column, blinded for `openings` evaluations by [`blinder`]. The
+/// commitment to the values is taken in the lagrange basis
+fn commit_column<E: PairingEngine>(
+    evals: Vec<E::Fr>,
+    openings: usize,
+    domain: GeneralEvaluationDomain<E::Fr>,
+    basis: &LagrangeBasis<E>,
+    scheme: &KzgScheme<E>,
+) -> Result<(Poly<E::Fr>, KzgCommitment<E>), KzgError> {
+    let blinder = blinder(domain, openings + 1, &mut rand::thread_rng());
+    let commitment = basis.commit(&evals)? + scheme.commit(&blinder)?;
+    let poly = Evaluations::from_vec_and_domain(evals, domain).interpolate() + blinder;
+    Ok((poly, commitment))
+}
+/// checks everything but the pairings, and returns the pairing check left to do.
+/// `digest` is the digest of the verifying key with `H`
+fn pairing_check<E: PairingEngine, H: TranscriptHash, const W: usize>(
+    vk: &VerifyingKey<E, W>,
+    digest: E::Fr,
+    proof: &Proof<E, W>,
+    scheme: &KzgScheme<E>,
+) -> Result<PairingCheck<E>, PlonkError> {
+    let domain = &vk.domain;
+    if proof.public_inputs.len() != vk.public_rows.len() {
+        return Err(PlonkError::PublicInputs {
+            expected: vk.public_rows.len(),
+            got: proof.public_inputs.len(),
+        });
+    }
+    let Challenges {
+        alpha,
+        beta,
+        gamma,
+        point,
+        opening,
+        separator,
+        lookup: lookup_challenges,
+    } = verify_challenges::<E, H, W>(digest, proof);
+    let public_eval =
+        public_inputs_poly(&vk.public_rows, &proof.public_inputs, *domain).evaluate(&point);
+    if proof.evaluation_point != point {
+        return Err(PlonkError::InvalidProof);
     };
-    let quotient = proof.t.clone();
-    let r_opening = proof.r;
-    let (advice, acc) = match verify_openings(proof, scheme, domain.element(1)) {
-        Some(evals) => evals,
-        None => {
-            println!("openings not ok");
-            return false;
+    let has_custom = !vk.custom.is_empty();
+    if proof.next_row.is_some() != has_custom {
+        return Err(PlonkError::MalformedProof);
+    }
+    let lookup_eval = match (&vk.lookup, &proof.lookup) {
+        (Some(_), Some(lookup)) => {
+            LookupEvals::from_proof(lookup, proof.wires[0].eval, point, *domain)
+                .identity(&lookup_challenges, alpha)
         }
+        (None, None) => E::Fr::zero(),
+        _ => return Err(PlonkError::MalformedProof),
     };
+    let advice = proof.wires.each_ref().map(|proof| proof.eval);
+    let next = proof.next_row.unwrap_or([E::Fr::zero(); W]);
+    let acc = [proof.permutation.z, proof.permutation.zw];
     let r = linearisation_commitment(
-        circuit,
+        vk,
         advice,
-        acc_commitment,
-        [acc.0, acc.1],
-        eval_point,
-        quotient,
+        next,
+        &proof.sigma,
+        proof.permutation.commitment,
+        acc,
+        point,
+        proof.t,
         [alpha, beta, gamma],
-        public_eval,
+        public_eval + lookup_eval,
     );
 
-    let open_valid = scheme.verify(&r, &r_opening, eval_point);
-    open_valid && r_opening.1.is_zero()
+    // r is 0 at the point
+    let [mut at_point, at_next_row] = proof.commitments(vk, lookup_challenges.zeta);
+    at_point.push(r);
+    let [mut point_evals, next_row_evals] = proof.evaluations();
+    point_evals.push(E::Fr::zero());
+    let [point_opening, next_row_opening] = [point_evals, next_row_evals]
+        .into_iter()
+        .zip(proof.openings)
+        .map(|(evals, witness)| KzgBatchOpening(witness, evals))
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+    let batches = [
+        (&at_point[..], &point_opening, point),
+        (
+            &at_next_row[..],
+            &next_row_opening,
+            point * domain.element(1),
+        ),
+    ];
+    Ok(scheme.multi_point_check(&batches, opening, separator)?)
 }
-///generates alpha, beta, gamma and the eval point
-fn verify_challenges(proof: &Proof) -> (Fr, Fr, Fr, Fr) {
-    let commitments = [&proof.a, &proof.b, &proof.c].map(|proof| proof.commitment.clone());
-    let challenge_generator = ChallengeGenerator::with_digest(&commitments);
-    let challenge: [Fr; 2] = challenge_generator.generate_challenges();
-    let mut challenge_generator = ChallengeGenerator::with_digest(&commitments);
-    challenge_generator.digest(&proof.permutation.commitment);
-    let [alpha, point] = challenge_generator.generate_challenges();
+/// labels of the commitments to f, h1 and h2
+const LOOKUP_LABELS: [&[u8]; 3] = [b"f", b"h1", b"h2"];
 
-    (alpha, challenge[0], challenge[1], point)
+/// the label of the commitment to the i-th wire, a, b, c, d, …
+fn wire_label(i: usize) -> [u8; 1] {
+    [b'a' + i as u8]
 }
-fn verify_openings(proof: Proof, scheme: &KzgScheme, w: Fr) -> Option<([Fr; 3], (Fr, Fr))> {
-    let Proof {
-        a,
-        b,
-        c,
-        permutation,
-        evaluation_point,
-        ..
-    } = proof;
-    let advice = [a, b, c];
-
-    let valid = advice.iter().all(|proof| {
-        let PolyProof {
-            commitment,
-            opening,
-        } = proof;
-        scheme.verify(commitment, opening, evaluation_point)
-    });
-    if !valid {
-        return None;
-    };
-    let advice = advice.map(|proof| proof.opening.eval());
-    let acc = {
-        let PermutationProof { commitment, z, zw } = permutation;
-        let zv = scheme.verify(&commitment, &z, evaluation_point);
-        let zwv = scheme.verify(&commitment, &zw, evaluation_point * w);
-        if zv && zwv {
-            (z.eval(), zw.eval())
-        } else {
-            return None;
-        }
-    };
-    Some((advice, acc))
+/// starts the transcript of a proof with the verifying key, the public inputs and the wires
+fn start_transcript<E: PairingEngine, H: TranscriptHash>(
+    digest: E::Fr,
+    public_inputs: &[E::Fr],
+    commitments: &[KzgCommitment<E>],
+) -> Transcript<E::Fr, H> {
+    let mut transcript = Transcript::new(b"TyPLONK");
+    transcript.append_scalar(b"verifying key", &digest);
+    for input in public_inputs {
+        transcript.append_scalar(b"public input", input);
+    }
+    for (i, commitment) in commitments.iter().enumerate() {
+        transcript.append_commitment(&wire_label(i), commitment);
+    }
+    transcript
 }
-
-/// returns commitments to a, b, c
-fn round1(a: &Poly, b: &Poly, c: &Poly, scheme: &KzgScheme) -> [KzgCommitment; 3] {
-    [a, b, c]
-        .iter()
-        .map(|poly| scheme.commit(poly))
-        .collect::<Vec<_>>()
-        .try_into()
-        .unwrap()
+/// the challenges of a proof, drawn from its transcript
+struct Challenges<F> {
+    alpha: F,
+    beta: F,
+    gamma: F,
+    point: F,
+    /// combines the polynomials opened at the same point
+    opening: F,
+    /// combines the openings at the point and at the next row
+    separator: F,
+    lookup: LookupChallenges<F>,
 }
-
-fn quotient_polynomial<const I: usize, C: CircuitDescription<I>>(
-    circuit: &CompiledCircuit<I, C>,
-    advice: [&Poly; 3],
-    // Z and Zw
-    acc: (&Poly, &Poly),
-    // challenges alpha, beta, gamma
-    challenges: [Fr; 3],
-    public_inputs: Poly,
-) -> SlicedPoly<3> {
-    let domain = &circuit.domain;
-    let w = domain.element(1);
-    let gates = &circuit.gate_constrains;
-    let permutation = &circuit.copy_constrains;
-    let GateConstrains {
-        q_l,
-        q_r,
-        q_o,
-        q_m,
-        q_c,
-        ..
-    } = gates;
-    let [a, b, c] = advice;
-    let CompiledPermutation { cols, cosets, .. } = permutation;
-    let [alpha, beta, gamma] = challenges;
-
-    let line1 = &(&(q_l.naive_mul(a) + q_r.naive_mul(b)) - &(q_o.naive_mul(c))
-        + q_m.naive_mul(a).naive_mul(b))
-        + q_c
-        + public_inputs;
-    vanishes(&line1, *domain);
-
-    let line2 = [a, b, c]
-        .iter()
-        .zip(cosets)
-        .map(|(advice, coset)| {
-            let rhs = DensePolynomial::from_coefficients_vec(vec![gamma, *coset * beta]);
-            *advice + &rhs
-        })
-        .reduce(|one, other| one.naive_mul(&other))
-        .unwrap();
-    let line2_eval = line2.evaluate(&w);
-    let line2 = line2.naive_mul(acc.0);
-    let permutations = cols.clone().map(|col| {
-        let eval =
-            Evaluations::from_vec_and_domain(col.clone().iter().map(|e| e.1).collect(), *domain);
-        eval.interpolate()
-    });
-    let line3 = [a, b, c]
-        .iter()
-        .zip(permutations)
-        .map(|(advice, permutation)| {
-            let gamma = DensePolynomial::from_coefficients_vec(vec![gamma]);
-            let perm = permutation.mul(beta);
-            *advice + &perm + gamma
-        })
-        .reduce(|one, other| one.naive_mul(&other))
-        .unwrap();
-    let line3_eval = line3.evaluate(&w);
-    assert_eq!(
-        line2_eval * acc.0.evaluate(&w) - line3_eval * acc.0.evaluate(&w.square()),
-        Fr::zero()
-    );
-    let line3 = line3.naive_mul(acc.1);
-    let line4 = {
-        let l0 = l0_poly(*domain);
-        let mut acc = acc.0.clone();
-        acc.coeffs[0] -= Fr::from(1);
-        acc.naive_mul(&l0)
+///generates the challenges the prover drew from the transcript, and the separator of the openings
+fn verify_challenges<E: PairingEngine, H: TranscriptHash, const W: usize>(
+    digest: E::Fr,
+    proof: &Proof<E, W>,
+) -> Challenges<E::Fr> {
+    let commitments = proof.wires.each_ref().map(|proof| proof.commitment);
+    let mut transcript = start_transcript::<E, H>(digest, &proof.public_inputs, &commitments);
+    let [beta, gamma, zeta] = transcript.challenges([b"beta", b"gamma", b"zeta"]);
+    let [delta, epsilon] = match &proof.lookup {
+        Some(lookup) => {
+            let commitments = [
+                &lookup.f.commitment,
+                &lookup.h1.commitment,
+                &lookup.h2.commitment,
+            ];
+            for (label, commitment) in LOOKUP_LABELS.iter().zip(commitments) {
+                transcript.append_commitment(label, commitment);
+            }
+            transcript.challenges([b"delta", b"epsilon"])
+        }
+        None => [E::Fr::zero(); 2],
+    };
+    transcript.append_commitment(b"permutation", &proof.permutation.commitment);
+    if let Some(lookup) = &proof.lookup {
+        transcript.append_commitment(b"lookup accumulator", &lookup.accumulator.commitment);
+    }
+    let alpha = transcript.challenge(b"alpha");
+    for commitment in proof.t.iter() {
+        transcript.append_commitment(b"t", commitment);
+    }
+    let point = transcript.challenge(b"evaluation point");
+    let lookup = LookupChallenges {
+        zeta,
+        delta,
+        epsilon,
     };
-    vanishes(&line4, *domain);
-    let combination_element = [Fr::one(), alpha, alpha, alpha.square()];
-
-    let constrains = [line1, line2, -line3, line4];
-    let target = constrains
-        .into_iter()
-        .zip(combination_element.into_iter())
-        .map(|(constrain, elem)| constrain.mul(elem))
-        .reduce(Add::add)
-        .unwrap();
 
-    //vanishes(&target, *domain);
-    let target = target.divide_by_vanishing_poly(*domain).unwrap();
-    SlicedPoly::from_poly(target.0, domain.size())
+    for eval in proof.evaluations().iter().flatten() {
+        transcript.append_scalar(b"evaluation", eval);
+    }
+    let opening = transcript.challenge(b"opening");
+    for witness in proof.openings {
+        transcript.append_commitment(b"opening", &KzgCommitment::<E>(witness));
+    }
+    let separator = transcript.challenge(b"separator");
+    Challenges {
+        alpha,
+        beta,
+        gamma,
+        point,
+        opening,
+        separator,
+        lookup,
+    }
 }
-fn linearisation_poly<const I: usize, C: CircuitDescription<I>>(
-    circuit: &CompiledCircuit<I, C>,
-    advice_evals: [Fr; 3],
-    acc_evals: [Fr; 2],
-    acc: Poly,
+
+#[allow(clippy::too_many_arguments)]
+fn linearisation_poly<E: PairingEngine, C: Circuit, const W: usize>(
+    circuit: &CompiledCircuit<E, C, W>,
+    advice_evals: [E::Fr; W],
+    next_evals: [E::Fr; W],
+    acc_evals: [E::Fr; 2],
+    acc: Poly<E::Fr>,
     //alpha, beta, gamma
-    challenges: [Fr; 3],
-    eval_point: Fr,
-    t: &SlicedPoly<3>,
-    public_eval: Fr,
-) -> Poly {
+    challenges: [E::Fr; 3],
+    eval_point: E::Fr,
+    t: &SlicedPoly<E::Fr, 3>,
+    public_eval: E::Fr,
+) -> Poly<E::Fr> {
     let domain = &circuit.domain;
     let gates = &circuit.gate_constrains;
-    let permutation = &circuit.copy_constrains;
     let GateConstrains {
         q_l,
         q_r,
         q_o,
         q_m,
         q_c,
+        wire_selectors,
         ..
     } = gates;
-    let CompiledPermutation { cols, cosets, .. } = permutation;
-    let [a, b, c] = advice_evals;
+    let cosets = &circuit.copy_constrains.cosets;
+    let [a, b, c] = [advice_evals[0], advice_evals[1], advice_evals[2]];
     let [alpha, beta, gamma] = challenges;
     let line1 = q_l.mul(a) + (&(q_r.mul(b)) - &(q_o.mul(c))) + (&q_m.mul(a * b) + q_c);
     let line1 = add_to_poly(line1, public_eval);
+    let line1 = wire_selectors
+        .iter()
+        .zip(&advice_evals[3..])
+        .fold(line1, |line1, (selector, eval)| line1 + selector.mul(*eval));
+    let line1 = gates
+        .custom
+        .iter()
+        .flat_map(|custom| {
+            let terms = custom.gate.terms(&advice_evals, &next_evals);
+            custom.polys.iter().zip(terms)
+        })
+        .fold(line1, |line1, (selector, term)| line1 + selector.mul(term));
 
     let line2 = cosets
         .iter()
@@ -415,61 +661,67 @@ fn linearisation_poly<const I: usize, C: CircuitDescription<I>>(
         .unwrap();
     let line2 = acc.mul(line2);
 
-    let sigma_evals = cols.clone().map(|col| {
-        let eval =
-            Evaluations::from_vec_and_domain(col.clone().iter().map(|e| e.1).collect(), *domain);
-        let poly = eval.interpolate();
-        let eval = poly.evaluate(&eval_point);
-        (poly, eval)
-    });
-
-    let copy_permutation_ab =
-        (a + beta * sigma_evals[0].1 + gamma) * (b + beta * sigma_evals[1].1 + gamma);
-    let copy_permutation_c = add_to_poly(sigma_evals[2].0.mul(beta), gamma + c);
-    let line3 = copy_permutation_c
-        .mul(copy_permutation_ab)
+    // the last sigma stays a polynomial, the others are evaluated
+    let (sigma_last, sigmas) = circuit.sigmas.split_last().unwrap();
+    let copy_permutation_evals = sigmas
+        .iter()
+        .zip(advice_evals.iter())
+        .map(|(sigma, eval)| *eval + beta * sigma.evaluate(&eval_point) + gamma)
+        .product::<E::Fr>();
+    let copy_permutation_last = add_to_poly(sigma_last.mul(beta), gamma + advice_evals[W - 1]);
+    let line3 = copy_permutation_last
+        .mul(copy_permutation_evals)
         .mul(acc_evals[1]);
 
     let copy_constrain = &line2 - &line3;
 
     let l0_eval = l0_poly(*domain).evaluate(&eval_point);
-    let l0_eval = Poly::from_coefficients_vec(vec![l0_eval]);
-    let line4 = add_to_poly(acc, Fr::from(-1)).mul(&l0_eval);
+    let line4 = add_to_poly(acc, -E::Fr::one()).mul(l0_eval);
 
     let line5 = t
         .compact(eval_point)
         .mul(domain.evaluate_vanishing_polynomial(eval_point));
 
-    let r = &(line1 + copy_constrain.mul(alpha) + line4.mul(alpha.square())) - &line5;
-    r
+    &(line1 + copy_constrain.mul(alpha) + line4.mul(alpha.square())) - &line5
 }
 
-fn linearisation_commitment<const I: usize, C: CircuitDescription<I>>(
-    circuit: &CompiledCircuit<I, C>,
-    advice_evals: [Fr; 3],
-    acc: KzgCommitment,
-    acc_evals: [Fr; 2],
-    eval_point: Fr,
-    quotient: [KzgCommitment; 3],
+#[allow(clippy::too_many_arguments)]
+fn linearisation_commitment<E: PairingEngine, const W: usize>(
+    vk: &VerifyingKey<E, W>,
+    advice_evals: [E::Fr; W],
+    next_evals: [E::Fr; W],
+    sigma_evals: &[E::Fr],
+    acc: KzgCommitment<E>,
+    acc_evals: [E::Fr; 2],
+    eval_point: E::Fr,
+    quotient: [KzgCommitment<E>; 3],
     //alpha,beta,gamma
-    challenges: [Fr; 3],
-    public_eval: Fr,
-) -> KzgCommitment {
-    let srs = &circuit.srs;
-    let scheme = KzgScheme::new(srs);
-    let domain = &circuit.domain;
-    let cols = domain.size();
-    let fixed_commitments = &circuit.gate_constrains.fixed_commitments;
-    let permutation = &circuit.copy_constrains;
-    let sigma_evals = permutation.sigma_evals(&eval_point, *domain);
-    let sigma_commitments = permutation.sigma_commitments(&scheme, *domain);
-    let CompiledPermutation { cosets, .. } = permutation;
+    challenges: [E::Fr; 3],
+    public_eval: E::Fr,
+) -> KzgCommitment<E> {
+    let scheme = KzgScheme::new(&vk.srs);
+    let domain = &vk.domain;
+    let fixed_commitments = &vk.fixed_commitments;
+    let sigma_commitments = &vk.sigma_commitments;
+    let cosets = &vk.cosets;
     let [alpha, beta, gamma] = challenges;
 
     let line1 = {
-        let [a, b, c] = advice_evals;
+        let [a, b, c] = [advice_evals[0], advice_evals[1], advice_evals[2]];
         let [q_l, q_r, q_o, q_m, q_c] = fixed_commitments;
-        q_l * a + q_r * b - q_o * c + q_m * a * b + *q_c
+        let line1 = q_l * a + q_r * b - q_o * c + q_m * a * b + *q_c;
+        let line1 = vk
+            .wire_commitments
+            .iter()
+            .zip(&advice_evals[3..])
+            .fold(line1, |line1, (selector, eval)| line1 + *selector * *eval);
+        vk.custom
+            .iter()
+            .flat_map(|custom| {
+                let terms = custom.gate.terms(&advice_evals, &next_evals);
+                custom.commitments.iter().zip(terms)
+            })
+            .fold(line1, |line1, (selector, term)| line1 + *selector * term)
     };
 
     let line2 = cosets
@@ -485,12 +737,12 @@ fn linearisation_commitment<const I: usize, C: CircuitDescription<I>>(
         .iter()
         .zip(advice_evals.iter())
         .map(|(sigma_eval, eval)| *eval + beta * sigma_eval + gamma)
-        .take(2)
         .reduce(Mul::mul)
         .unwrap();
-    let line3 = sigma_commitments[2] * line3 * alpha * beta * acc_evals[1];
+    let line3 = sigma_commitments[W - 1] * line3 * alpha * beta * acc_evals[1];
 
-    let quotient = <SlicedPoly<3>>::compact_commitment(cols, quotient, eval_point);
+    let quotient =
+        <SlicedPoly<E::Fr, 3>>::compact_commitment(vk.slice_degree, quotient, eval_point);
     let vanish_eval = domain.evaluate_vanishing_polynomial(eval_point);
     let line5 = quotient * vanish_eval;
 
@@ -498,17 +750,10 @@ fn linearisation_commitment<const I: usize, C: CircuitDescription<I>>(
         .iter()
         .zip(advice_evals.iter())
         .map(|(sigma_eval, eval)| *eval + beta * sigma_eval + gamma)
-        .take(2)
         .reduce(Mul::mul)
         .unwrap();
-    let constant_perm = constant_perm * (advice_evals[2] + gamma) * acc_evals[1];
-    let constant = alpha * constant_perm + l0_eval * alpha.square() + public_eval;
-    let scheme = KzgScheme::new(&circuit.srs);
+    let constant_perm = constant_perm * (advice_evals[W - 1] + gamma) * acc_evals[1];
+    let constant = alpha * constant_perm + l0_eval * alpha.square() - public_eval;
 
     line1 + (line2 - (line3 + scheme.identity() * constant)) - line5
 }
-pub fn vanishes(poly: &Poly, domain: impl EvaluationDomain<Fr>) {
-    let (_, rest) = poly.divide_by_vanishing_poly(domain).unwrap();
-    assert!(rest.is_zero());
-    //println!("vanishes");
-}
@@ -0,0 +1,341 @@
+//! Plookup argument over the tables of the circuit.
+//!
+//! Every entry of a table is compressed as `value + ζ·id`, the rows doing a lookup compress
+//! `a + ζ·q_table` in the same way into f. The sorted concatenation s of f and the table is split
+//! in h1 = s[..n] and h2 = s[n - 1..], and the accumulator Z checks s is a permutation of f and
+//! the table that keeps the order of the table.
+use super::{commit_column, LookupProof, PermutationProof, PolyProof};
+use crate::{utils::l0_poly, CosetEvals, LookupTables, PlonkError, Poly};
+use ark_ec::PairingEngine;
+use ark_ff::{FftField, One};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain, Polynomial};
+use kgz::{srs::LagrangeBasis, KzgCommitment, KzgScheme};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{collections::HashMap, ops::Mul};
+
+/// the polynomials to open at the point and at the next row
+type Openings<F> = [Vec<Poly<F>>; 2];
+
+/// ζ compresses entries and queries, δ and ε are the challenges of the accumulator
+#[derive(Clone, Copy)]
+pub(super) struct LookupChallenges<F> {
+    pub zeta: F,
+    pub delta: F,
+    pub epsilon: F,
+}
+
+/// the compressed queries and the sorted witness
+pub(super) struct LookupWitness<E: PairingEngine> {
+    /// `a + ζ·q_table` in the lookup rows and the first entry of the table elsewhere
+    pub f: Poly<E::Fr>,
+    pub h1: Poly<E::Fr>,
+    pub h2: Poly<E::Fr>,
+    pub commitments: [KzgCommitment<E>; 3],
+    evals: [Vec<E::Fr>; 3],
+}
+
+/// the values the lookup identities are checked on, at a single point
+#[derive(Clone, Copy)]
+pub(super) struct LookupEvals<F> {
+    a: F,
+    q_lookup: F,
+    q_table: F,
+    f: F,
+    /// evaluations at the point and at the next row
+    table: [F; 2],
+    h1: [F; 2],
+    h2: [F; 2],
+    acc: [F; 2],
+    l0: F,
+    l_last: F,
+    /// `X - w^(n-1)`, turns the accumulator check off in the last row
+    not_last: F,
+}
+
+impl<E: PairingEngine> LookupTables<E> {
+    /// the compressed column of all the tables, `value + ζ·id`
+    fn table(&self, zeta: E::Fr) -> Vec<E::Fr> {
+        self.entries
+            .iter()
+            .map(|(value, id)| *value + zeta * id)
+            .collect()
+    }
+    fn table_poly(&self, zeta: E::Fr) -> Poly<E::Fr> {
+        &self.values + &self.ids.mul(zeta)
+    }
+
+    /// compresses the queries in `a` and sorts them together with the table
+    pub(super) fn witness(
+        &self,
+        a: &[E::Fr],
+        zeta: E::Fr,
+        domain: GeneralEvaluationDomain<E::Fr>,
+        basis: &LagrangeBasis<E>,
+        scheme: &KzgScheme<E>,
+    ) -> Result<LookupWitness<E>, PlonkError> {
+        let n = domain.size();
+        let table = self.table(zeta);
+        let mut f = vec![table[0]; n];
+        for (row, id) in self.queries.iter() {
+            f[*row] = a[*row] + zeta * id;
+        }
+        // the last row of f is not part of the argument
+        let mut counts = HashMap::<E::Fr, usize>::new();
+        for query in f.iter().take(n - 1) {
+            *counts.entry(*query).or_default() += 1;
+        }
+        // queries go right after the first entry of the table equal to them
+        let mut sorted = Vec::with_capacity(2 * n - 1);
+        for entry in table.iter() {
+            sorted.push(*entry);
+            if let Some(count) = counts.remove(entry) {
+                sorted.extend(std::iter::repeat_n(*entry, count));
+            }
+        }
+        // a looked up value is not in its table
+        if !counts.is_empty() {
+            return Err(PlonkError::Unsatisfied(vec![]));
+        }
+        let h1 = sorted[..n].to_vec();
+        let h2 = sorted[n - 1..].to_vec();
+        let evals = [f, h1, h2];
+        // f is opened at the point, h1 and h2 at the next row too
+        let blind = |evals: &Vec<E::Fr>, openings| {
+            commit_column(evals.clone(), openings, domain, basis, scheme)
+        };
+        let (f, f_commitment) = blind(&evals[0], 1)?;
+        let (h1, h1_commitment) = blind(&evals[1], 2)?;
+        let (h2, h2_commitment) = blind(&evals[2], 2)?;
+        let commitments = [f_commitment, h1_commitment, h2_commitment];
+        Ok(LookupWitness {
+            f,
+            h1,
+            h2,
+            commitments,
+            evals,
+        })
+    }
+
+    /// evaluations of the accumulator, `Z(w^(i+1)) = Z(w^i)·num_i/den_i` starting at 1
+    pub(super) fn accumulator(
+        &self,
+        witness: &LookupWitness<E>,
+        challenges: &LookupChallenges<E::Fr>,
+    ) -> Vec<E::Fr> {
+        let LookupChallenges {
+            zeta,
+            delta,
+            epsilon,
+        } = *challenges;
+        let table = self.table(zeta);
+        let [f, h1, h2] = &witness.evals;
+        let one_delta = E::Fr::one() + delta;
+        let e = epsilon * one_delta;
+        let mut acc = Vec::with_capacity(table.len());
+        acc.push(E::Fr::one());
+        for i in 0..table.len() - 1 {
+            let num = one_delta * (epsilon + f[i]) * (e + table[i] + delta * table[i + 1]);
+            let den = (e + h1[i] + delta * h1[i + 1]) * (e + h2[i] + delta * h2[i + 1]);
+            let last = acc[i];
+            acc.push(last * num / den);
+        }
+        acc
+    }
+
+    /// the lookup identities evaluated over the coset of the quotient, `a` being the evaluations
+    /// of the wire
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn quotient_term(
+        &self,
+        coset: &CosetEvals<E::Fr>,
+        a: &[E::Fr],
+        witness: &LookupWitness<E>,
+        acc: &Poly<E::Fr>,
+        challenges: &LookupChallenges<E::Fr>,
+        alpha: E::Fr,
+        domain: GeneralEvaluationDomain<E::Fr>,
+    ) -> Vec<E::Fr> {
+        let last = domain.element(domain.size() - 1);
+        let [q_lookup, q_table, values, ids, l_last] =
+            coset.lookup.as_ref().expect("the circuit has tables");
+        let table = values
+            .iter()
+            .zip(ids)
+            .map(|(value, id)| *value + challenges.zeta * id)
+            .collect::<Vec<_>>();
+        let [f, h1, h2, acc] =
+            [&witness.f, &witness.h1, &witness.h2, acc].map(|poly| coset.fft(poly));
+        let [h1_w, h2_w, acc_w, table_w] =
+            [&h1, &h2, &acc, &table].map(|evals| coset.shifted(evals));
+        let points = coset.points();
+        let eval = |(i, point): (usize, &E::Fr)| {
+            let evals = LookupEvals {
+                a: a[i],
+                q_lookup: q_lookup[i],
+                q_table: q_table[i],
+                f: f[i],
+                table: [table[i], table_w[i]],
+                h1: [h1[i], h1_w[i]],
+                h2: [h2[i], h2_w[i]],
+                acc: [acc[i], acc_w[i]],
+                l0: coset.l0[i],
+                l_last: l_last[i],
+                not_last: *point - last,
+            };
+            evals.identity(challenges, alpha)
+        };
+        #[cfg(feature = "parallel")]
+        let evals = points.par_iter().enumerate().map(eval).collect();
+        #[cfg(not(feature = "parallel"))]
+        let evals = points.iter().enumerate().map(eval).collect();
+        evals
+    }
+
+    /// evaluates the witness, the accumulator, the table and the selectors, and returns the
+    /// polynomials to open at the point and at the next row in the order of
+    /// [`LookupProof::evaluations`]
+    pub(super) fn open(
+        &self,
+        witness: LookupWitness<E>,
+        acc: Poly<E::Fr>,
+        acc_commitment: KzgCommitment<E>,
+        zeta: E::Fr,
+        point: E::Fr,
+        w: E::Fr,
+    ) -> (LookupProof<E>, Openings<E::Fr>) {
+        let LookupWitness {
+            f,
+            h1,
+            h2,
+            commitments,
+            ..
+        } = witness;
+        let [f_commitment, h1_commitment, h2_commitment] = commitments;
+        let shifted = |poly: &Poly<E::Fr>, commitment| PermutationProof {
+            commitment,
+            z: poly.evaluate(&point),
+            zw: poly.evaluate(&(point * w)),
+        };
+        let table = self.table_poly(zeta);
+        let proof = LookupProof {
+            f: PolyProof {
+                commitment: f_commitment,
+                eval: f.evaluate(&point),
+            },
+            h1: shifted(&h1, h1_commitment),
+            h2: shifted(&h2, h2_commitment),
+            accumulator: shifted(&acc, acc_commitment),
+            table: [point, point * w].map(|point| table.evaluate(&point)),
+            selectors: [&self.q_lookup, &self.q_table].map(|poly| poly.evaluate(&point)),
+        };
+        let at_next_row = vec![h1.clone(), h2.clone(), acc.clone(), table.clone()];
+        let at_point = vec![
+            f,
+            h1,
+            h2,
+            acc,
+            table,
+            self.q_lookup.clone(),
+            self.q_table.clone(),
+        ];
+        (proof, [at_point, at_next_row])
+    }
+}
+
+impl<E: PairingEngine> LookupProof<E> {
+    /// f, h1, h2, Z, the table, q_lookup and q_table at the point, then h1, h2, Z and the table
+    /// at the next row
+    pub(super) fn evaluations(&self) -> [Vec<E::Fr>; 2] {
+        let shifted = [&self.h1, &self.h2, &self.accumulator];
+        let mut at_point = vec![self.f.eval];
+        at_point.extend(shifted.iter().map(|proof| proof.z));
+        at_point.push(self.table[0]);
+        at_point.extend_from_slice(&self.selectors);
+        let mut at_next_row = shifted.iter().map(|proof| proof.zw).collect::<Vec<_>>();
+        at_next_row.push(self.table[1]);
+        [at_point, at_next_row]
+    }
+    /// the commitments to the polynomials of `evaluations`, `fixed` are the commitments to
+    /// q_lookup, q_table, the table values and ids
+    pub(super) fn commitments(
+        &self,
+        fixed: &[KzgCommitment<E>; 4],
+        zeta: E::Fr,
+    ) -> [Vec<KzgCommitment<E>>; 2] {
+        let table = fixed[2] + fixed[3] * zeta;
+        let shifted = [&self.h1, &self.h2, &self.accumulator].map(|proof| proof.commitment);
+        let mut at_point = vec![self.f.commitment];
+        at_point.extend_from_slice(&shifted);
+        at_point.extend([table, fixed[0], fixed[1]]);
+        let mut at_next_row = shifted.to_vec();
+        at_next_row.push(table);
+        [at_point, at_next_row]
+    }
+}
+
+impl<F: FftField> LookupEvals<F> {
+    /// the evaluations at `point` taken from the proof
+    pub(super) fn from_proof<E: PairingEngine<Fr = F>>(
+        proof: &LookupProof<E>,
+        a: F,
+        point: F,
+        domain: GeneralEvaluationDomain<F>,
+    ) -> Self {
+        let n = domain.size();
+        let shifted = |proof: &PermutationProof<E>| [proof.z, proof.zw];
+        Self {
+            a,
+            q_lookup: proof.selectors[0],
+            q_table: proof.selectors[1],
+            f: proof.f.eval,
+            table: proof.table,
+            h1: shifted(&proof.h1),
+            h2: shifted(&proof.h2),
+            acc: shifted(&proof.accumulator),
+            l0: l0_poly(domain).evaluate(&point),
+            l_last: domain.evaluate_all_lagrange_coefficients(point)[n - 1],
+            not_last: point - domain.element(n - 1),
+        }
+    }
+
+    /// `Σ α^(3+k)·T_k` with
+    /// - T_0 = q_lookup·(a + ζ·q_table - f)
+    /// - T_1 = (X - w^(n-1))·(Z·(1+δ)(ε+f)(ε(1+δ) + t + δ·t(wX)) - Z(wX)·(ε(1+δ) + h1 + δ·h1(wX))(ε(1+δ) + h2 + δ·h2(wX)))
+    /// - T_2 = L_0·(Z - 1)
+    /// - T_3 = L_(n-1)·(Z - 1)
+    /// - T_4 = L_(n-1)·(h1 - h2(wX))
+    pub(super) fn identity(&self, challenges: &LookupChallenges<F>, alpha: F) -> F {
+        let LookupChallenges {
+            zeta,
+            delta,
+            epsilon,
+        } = *challenges;
+        let one_delta = F::one() + delta;
+        let e = epsilon * one_delta;
+        let query = self.q_lookup * (self.a + zeta * self.q_table - self.f);
+        let num = self.acc[0]
+            * one_delta
+            * (epsilon + self.f)
+            * (e + self.table[0] + delta * self.table[1]);
+        let den = self.acc[1]
+            * (e + self.h1[0] + delta * self.h1[1])
+            * (e + self.h2[0] + delta * self.h2[1]);
+        let terms = [
+            query,
+            self.not_last * (num - den),
+            self.l0 * (self.acc[0] - F::one()),
+            self.l_last * (self.acc[0] - F::one()),
+            self.l_last * (self.h1[0] - self.h2[1]),
+        ];
+        let mut power = alpha.pow([3]);
+        terms
+            .iter()
+            .map(|term| {
+                let term = *term * power;
+                power *= alpha;
+                term
+            })
+            .sum()
+    }
+}
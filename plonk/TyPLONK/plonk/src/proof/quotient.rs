@@ -0,0 +1,184 @@
+//! The quotient polynomial, computed pointwise over a coset.
+//!
+//! The quotient has degree below `3·s`, s being the size of its slices, which takes the degree
+//! of the gates and of the blinding into account. The identities are evaluated over the coset
+//! `g·H'` of the subgroup H' of size `k·n`, k being the power of two with `k·n >= 3·s`, where the
+//! vanishing polynomial of the domain is never 0, so the division is pointwise and a single
+//! inverse FFT gives the quotient back. As w is the k-th power of the generator of H', the
+//! evaluations of `p(wX)` are the ones of p k positions ahead.
+use crate::{
+    description::Circuit,
+    utils::{l0_poly, SlicedPoly},
+    CompiledCircuit, CosetEvals, GateConstrains, LookupTables, Poly,
+};
+use ark_ec::PairingEngine;
+use ark_ff::{Field, One, PrimeField, Zero};
+use ark_poly::{EvaluationDomain, Evaluations, GeneralEvaluationDomain, UVPolynomial};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+impl<F: PrimeField> CosetEvals<F> {
+    /// evaluates the fixed polynomials of the circuit over the coset, there is a sigma for each
+    /// wire and the quotient is cut in 3 slices of `slice_degree`
+    pub(crate) fn new<E: PairingEngine<Fr = F>>(
+        gates: &GateConstrains<E>,
+        sigmas: &[Poly<F>],
+        lookup: Option<&LookupTables<E>>,
+        domain: GeneralEvaluationDomain<F>,
+        slice_degree: usize,
+    ) -> Self {
+        let extension = (3 * slice_degree)
+            .div_ceil(domain.size())
+            .next_power_of_two();
+        let coset = <GeneralEvaluationDomain<F>>::new(extension * domain.size()).unwrap();
+        let fft = |poly: &Poly<F>| coset.coset_fft(poly);
+        let GateConstrains {
+            q_l,
+            q_r,
+            q_o,
+            q_m,
+            q_c,
+            wire_selectors,
+            custom,
+            ..
+        } = gates;
+        let lookup = lookup.map(|tables| {
+            let n = domain.size();
+            let mut last = vec![F::zero(); n];
+            last[n - 1] = F::one();
+            let l_last = Evaluations::from_vec_and_domain(last, domain).interpolate();
+            [
+                &tables.q_lookup,
+                &tables.q_table,
+                &tables.values,
+                &tables.ids,
+                &l_last,
+            ]
+            .map(fft)
+        });
+        Self {
+            domain: coset,
+            extension,
+            selectors: [q_l, q_r, q_o, q_m, q_c].map(fft),
+            wire_selectors: wire_selectors.iter().map(fft).collect(),
+            sigmas: sigmas.iter().map(fft).collect(),
+            custom: custom
+                .iter()
+                .map(|custom| custom.polys.iter().map(fft).collect())
+                .collect(),
+            l0: fft(&l0_poly(domain)),
+            lookup,
+        }
+    }
+    pub(super) fn fft(&self, poly: &Poly<F>) -> Vec<F> {
+        self.domain.coset_fft(poly)
+    }
+    /// the points of the coset, in the order of the evaluations
+    pub(super) fn points(&self) -> Vec<F> {
+        let offset = F::multiplicative_generator();
+        self.domain
+            .elements()
+            .map(|element| offset * element)
+            .collect()
+    }
+    /// the evaluations of `p(wX)` from the ones of p
+    pub(super) fn shifted(&self, evals: &[F]) -> Vec<F> {
+        let mut shifted = evals.to_vec();
+        shifted.rotate_left(self.extension);
+        shifted
+    }
+}
+
+/// `(gates + α·permutation + α²·L_0·(Z - 1) + lookup) / Z_H`, with the evaluations of the wires and
+/// of the lookup identities over the coset
+pub(super) fn quotient_polynomial<E: PairingEngine, C: Circuit, const W: usize>(
+    circuit: &CompiledCircuit<E, C, W>,
+    wires: &[Vec<E::Fr>; W],
+    acc: &Poly<E::Fr>,
+    // challenges alpha, beta, gamma
+    challenges: [E::Fr; 3],
+    public_inputs: &Poly<E::Fr>,
+    lookup: Option<Vec<E::Fr>>,
+) -> Option<SlicedPoly<E::Fr, 3>> {
+    let coset = &circuit.coset;
+    // only the parts of the circuit the points need are shared with the threads
+    let gate_constrains = &circuit.gate_constrains;
+    let n = circuit.domain.size() as u64;
+    let size = coset.domain.size();
+    let [alpha, beta, gamma] = challenges;
+    let acc = coset.fft(acc);
+    let acc_w = coset.shifted(&acc);
+    let public_inputs = coset.fft(public_inputs);
+    let points = coset.points();
+    let cosets = circuit.copy_constrains.cosets;
+    // `X^n - 1` only takes k different values over the coset
+    let vanishing_inverse = points[..coset.extension]
+        .iter()
+        .map(|point| (point.pow([n]) - E::Fr::one()).inverse().unwrap())
+        .collect::<Vec<_>>();
+    let [q_l, q_r, q_o, q_m, q_c] = &coset.selectors;
+    let eval = |i: usize| {
+        let next = (i + coset.extension) % size;
+        let row = wires.each_ref().map(|col| col[i]);
+        let [a, b, c] = [row[0], row[1], row[2]];
+        let wide = coset
+            .wire_selectors
+            .iter()
+            .zip(&row[3..])
+            .map(|(q, wire)| q[i] * wire)
+            .sum::<E::Fr>();
+        let gates = q_l[i] * a + q_r[i] * b - q_o[i] * c
+            + q_m[i] * a * b
+            + q_c[i]
+            + wide
+            + public_inputs[i]
+            + custom_gates(gate_constrains, coset, wires, i, next);
+        let identity = row
+            .iter()
+            .zip(cosets)
+            .map(|(wire, coset)| *wire + beta * coset * points[i] + gamma)
+            .product::<E::Fr>()
+            * acc[i];
+        let permuted = row
+            .iter()
+            .zip(coset.sigmas.iter())
+            .map(|(wire, sigma)| *wire + beta * sigma[i] + gamma)
+            .product::<E::Fr>()
+            * acc_w[i];
+        let start = coset.l0[i] * (acc[i] - E::Fr::one());
+        let lookup = lookup.as_ref().map_or(E::Fr::zero(), |lookup| lookup[i]);
+        let numerator = gates + alpha * (identity - permuted) + alpha.square() * start + lookup;
+        numerator * vanishing_inverse[i % coset.extension]
+    };
+    // the points of the coset are independent
+    #[cfg(feature = "parallel")]
+    let evals = (0..size).into_par_iter().map(eval).collect::<Vec<_>>();
+    #[cfg(not(feature = "parallel"))]
+    let evals = (0..size).map(eval).collect::<Vec<_>>();
+    let quotient = Poly::from_coefficients_vec(coset.domain.coset_ifft(&evals));
+    SlicedPoly::from_poly(quotient, circuit.slice_degree)
+}
+
+/// `Σ q_k·f_k` of the custom gates at the i-th point of the coset
+fn custom_gates<E: PairingEngine, const W: usize>(
+    gates: &GateConstrains<E>,
+    coset: &CosetEvals<E::Fr>,
+    wires: &[Vec<E::Fr>; W],
+    i: usize,
+    next: usize,
+) -> E::Fr {
+    let row = wires.each_ref().map(|col| col[i]);
+    let next_row = wires.each_ref().map(|col| col[next]);
+    gates
+        .custom
+        .iter()
+        .zip(coset.custom.iter())
+        .flat_map(|(custom, selectors)| {
+            let terms = custom.gate.terms(&row, &next_row);
+            selectors
+                .iter()
+                .zip(terms)
+                .map(move |(q, term)| q[i] * term)
+        })
+        .sum()
+}
@@ -0,0 +1,266 @@
+//! Byte format of proofs.
+//!
+//! A proof starts with the magic `TPPF` and [`PROOF_VERSION`] as a little endian u32, followed by
+//! its fields in declaration order:
+//!
+//! - wires: a, b, c, … each a commitment and its evaluation, as many as the circuit has wires
+//! - permutation: the commitment to the accumulator and its evaluations at z and zw
+//! - evaluation_point
+//! - t: 3 commitments, sigma: an evaluation for each wire but the last
+//! - next_row: a bool, then an evaluation for each wire when true
+//! - lookup: a bool, then when true f as a commitment and an evaluation, h1, h2 and the
+//!   accumulator as commitments with 2 evaluations, 2 evaluations of the table and 2 of the
+//!   selectors
+//! - openings: the 2 witnesses of the batch openings, points
+//! - public_inputs: a u64 length and the scalars
+//!
+//! The number of wires is not written, a proof is read with the one of its circuit.
+//!
+//! Scalars are 32 bytes little endian, points are the
+//! `ark-serialize` encoding of G1, 48 bytes compressed or 96 uncompressed on BLS12-381 and 32 or 64
+//! on BN254. Every point is checked to
+//! be on the curve and in the prime order subgroup when read, except with `deserialize_unchecked`.
+use super::{LookupProof, PermutationProof, PolyProof, Proof};
+use ark_ec::PairingEngine;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use kgz::KzgCommitment;
+
+const MAGIC: &[u8; 4] = b"TPPF";
+/// the version of the byte format, proofs of other versions are rejected
+pub const PROOF_VERSION: u32 = 2;
+
+/// compressed points, or uncompressed points checked or not when read
+#[derive(Clone, Copy)]
+enum Mode {
+    Compressed,
+    Uncompressed,
+    Unchecked,
+}
+
+/// writing and reading in one of the modes, implemented by every part of a proof
+trait Encode: Sized {
+    fn encode<W: Write>(&self, writer: &mut W, mode: Mode) -> Result<(), SerializationError>;
+    fn decode<R: Read>(reader: &mut R, mode: Mode) -> Result<Self, SerializationError>;
+}
+
+/// a scalar of the proof, a blanket impl over the fields would overlap the other impls
+struct Scalar<F>(F);
+
+impl<F: PrimeField> Encode for Scalar<F> {
+    fn encode<W: Write>(&self, writer: &mut W, _mode: Mode) -> Result<(), SerializationError> {
+        self.0.serialize(writer)
+    }
+    fn decode<R: Read>(reader: &mut R, _mode: Mode) -> Result<Self, SerializationError> {
+        F::deserialize(reader).map(Scalar)
+    }
+}
+impl Encode for bool {
+    fn encode<W: Write>(&self, writer: &mut W, _mode: Mode) -> Result<(), SerializationError> {
+        self.serialize(writer)
+    }
+    fn decode<R: Read>(reader: &mut R, _mode: Mode) -> Result<Self, SerializationError> {
+        Self::deserialize(reader)
+    }
+}
+impl<E: PairingEngine> Encode for KzgCommitment<E> {
+    fn encode<W: Write>(&self, writer: &mut W, mode: Mode) -> Result<(), SerializationError> {
+        match mode {
+            Mode::Compressed => self.0.serialize(writer),
+            Mode::Uncompressed | Mode::Unchecked => self.0.serialize_uncompressed(writer),
+        }
+    }
+    fn decode<R: Read>(reader: &mut R, mode: Mode) -> Result<Self, SerializationError> {
+        let point = match mode {
+            Mode::Compressed => E::G1Affine::deserialize(reader)?,
+            // only the subgroup is checked by ark-serialize, a point off the curve does not
+            // survive the round trip through its compressed form, which only keeps x
+            Mode::Uncompressed => {
+                let point = E::G1Affine::deserialize_uncompressed(reader)?;
+                let mut compressed = vec![];
+                point.serialize(&mut compressed)?;
+                match E::G1Affine::deserialize(&compressed[..]) {
+                    Ok(checked) if checked == point => point,
+                    _ => return Err(SerializationError::InvalidData),
+                }
+            }
+            Mode::Unchecked => E::G1Affine::deserialize_unchecked(reader)?,
+        };
+        Ok(Self(point))
+    }
+}
+impl<T: Encode, const N: usize> Encode for [T; N] {
+    fn encode<W: Write>(&self, writer: &mut W, mode: Mode) -> Result<(), SerializationError> {
+        self.iter().try_for_each(|item| item.encode(writer, mode))
+    }
+    fn decode<R: Read>(reader: &mut R, mode: Mode) -> Result<Self, SerializationError> {
+        let items = (0..N)
+            .map(|_| T::decode(reader, mode))
+            .collect::<Result<Vec<_>, _>>()?;
+        match items.try_into() {
+            Ok(items) => Ok(items),
+            Err(_) => unreachable!("N items were read"),
+        }
+    }
+}
+impl<T: Encode> Encode for Option<T> {
+    fn encode<W: Write>(&self, writer: &mut W, mode: Mode) -> Result<(), SerializationError> {
+        self.is_some().encode(writer, mode)?;
+        match self {
+            Some(item) => item.encode(writer, mode),
+            None => Ok(()),
+        }
+    }
+    fn decode<R: Read>(reader: &mut R, mode: Mode) -> Result<Self, SerializationError> {
+        match bool::decode(reader, mode)? {
+            true => T::decode(reader, mode).map(Some),
+            false => Ok(None),
+        }
+    }
+}
+impl<F: PrimeField> Encode for Vec<Scalar<F>> {
+    fn encode<W: Write>(&self, writer: &mut W, _mode: Mode) -> Result<(), SerializationError> {
+        let scalars = self.iter().map(|scalar| scalar.0).collect::<Vec<_>>();
+        scalars.serialize(writer)
+    }
+    fn decode<R: Read>(reader: &mut R, _mode: Mode) -> Result<Self, SerializationError> {
+        let scalars = Vec::<F>::deserialize(reader)?;
+        Ok(scalars.into_iter().map(Scalar).collect())
+    }
+}
+
+/// the scalars of an array
+fn scalars<F: Copy, const N: usize>(values: [F; N]) -> [Scalar<F>; N] {
+    values.map(Scalar)
+}
+fn values<F, const N: usize>(scalars: [Scalar<F>; N]) -> [F; N] {
+    scalars.map(|scalar| scalar.0)
+}
+
+impl<E: PairingEngine> Encode for PolyProof<E> {
+    fn encode<W: Write>(&self, writer: &mut W, mode: Mode) -> Result<(), SerializationError> {
+        self.commitment.encode(writer, mode)?;
+        Scalar(self.eval).encode(writer, mode)
+    }
+    fn decode<R: Read>(reader: &mut R, mode: Mode) -> Result<Self, SerializationError> {
+        Ok(Self {
+            commitment: Encode::decode(reader, mode)?,
+            eval: Scalar::decode(reader, mode)?.0,
+        })
+    }
+}
+impl<E: PairingEngine> Encode for PermutationProof<E> {
+    fn encode<W: Write>(&self, writer: &mut W, mode: Mode) -> Result<(), SerializationError> {
+        self.commitment.encode(writer, mode)?;
+        scalars([self.z, self.zw]).encode(writer, mode)
+    }
+    fn decode<R: Read>(reader: &mut R, mode: Mode) -> Result<Self, SerializationError> {
+        let commitment = Encode::decode(reader, mode)?;
+        let [z, zw] = values(Encode::decode(reader, mode)?);
+        Ok(Self { commitment, z, zw })
+    }
+}
+impl<E: PairingEngine> Encode for LookupProof<E> {
+    fn encode<W: Write>(&self, writer: &mut W, mode: Mode) -> Result<(), SerializationError> {
+        self.f.encode(writer, mode)?;
+        self.h1.encode(writer, mode)?;
+        self.h2.encode(writer, mode)?;
+        self.accumulator.encode(writer, mode)?;
+        scalars(self.table).encode(writer, mode)?;
+        scalars(self.selectors).encode(writer, mode)
+    }
+    fn decode<R: Read>(reader: &mut R, mode: Mode) -> Result<Self, SerializationError> {
+        Ok(Self {
+            f: Encode::decode(reader, mode)?,
+            h1: Encode::decode(reader, mode)?,
+            h2: Encode::decode(reader, mode)?,
+            accumulator: Encode::decode(reader, mode)?,
+            table: values(Encode::decode(reader, mode)?),
+            selectors: values(Encode::decode(reader, mode)?),
+        })
+    }
+}
+impl<E: PairingEngine, const N: usize> Encode for Proof<E, N> {
+    fn encode<W: Write>(&self, writer: &mut W, mode: Mode) -> Result<(), SerializationError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&PROOF_VERSION.to_le_bytes())?;
+        self.wires.encode(writer, mode)?;
+        self.permutation.encode(writer, mode)?;
+        Scalar(self.evaluation_point).encode(writer, mode)?;
+        self.t.encode(writer, mode)?;
+        for sigma in self.sigma.iter() {
+            Scalar(*sigma).encode(writer, mode)?;
+        }
+        self.next_row.map(scalars).encode(writer, mode)?;
+        self.lookup.encode(writer, mode)?;
+        self.openings.map(KzgCommitment::<E>).encode(writer, mode)?;
+        let public_inputs = self.public_inputs.iter().copied().map(Scalar);
+        public_inputs.collect::<Vec<_>>().encode(writer, mode)
+    }
+    fn decode<R: Read>(reader: &mut R, mode: Mode) -> Result<Self, SerializationError> {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        if header[..4] != MAGIC[..] || header[4..] != PROOF_VERSION.to_le_bytes() {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(Self {
+            wires: Encode::decode(reader, mode)?,
+            permutation: Encode::decode(reader, mode)?,
+            evaluation_point: Scalar::decode(reader, mode)?.0,
+            t: Encode::decode(reader, mode)?,
+            sigma: (1..N)
+                .map(|_| Scalar::decode(reader, mode).map(|scalar| scalar.0))
+                .collect::<Result<_, _>>()?,
+            next_row: Option::decode(reader, mode)?.map(values),
+            lookup: Encode::decode(reader, mode)?,
+            openings: <[KzgCommitment<E>; 2]>::decode(reader, mode)?.map(|opening| opening.0),
+            public_inputs: Vec::decode(reader, mode)?
+                .into_iter()
+                .map(|scalar: Scalar<_>| scalar.0)
+                .collect(),
+        })
+    }
+}
+
+/// implements the ark-serialize traits with `Encode`, `<const N>` for the types generic over the
+/// number of wires
+macro_rules! canonical {
+    ($($name:ident $(<const $n:ident>)?),*) => {$(
+        impl<E: PairingEngine $(, const $n: usize)?> CanonicalSerialize for $name<E $(, $n)?> {
+            fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+                self.encode(&mut writer, Mode::Compressed)
+            }
+            fn serialized_size(&self) -> usize {
+                encoded_size(self, Mode::Compressed)
+            }
+            fn serialize_uncompressed<W: Write>(
+                &self,
+                mut writer: W,
+            ) -> Result<(), SerializationError> {
+                self.encode(&mut writer, Mode::Uncompressed)
+            }
+            fn uncompressed_size(&self) -> usize {
+                encoded_size(self, Mode::Uncompressed)
+            }
+        }
+        impl<E: PairingEngine $(, const $n: usize)?> CanonicalDeserialize for $name<E $(, $n)?> {
+            fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+                Self::decode(&mut reader, Mode::Compressed)
+            }
+            fn deserialize_uncompressed<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+                Self::decode(&mut reader, Mode::Uncompressed)
+            }
+            fn deserialize_unchecked<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+                Self::decode(&mut reader, Mode::Unchecked)
+            }
+        }
+    )*};
+}
+canonical!(Proof<const N>, PolyProof, PermutationProof, LookupProof);
+
+fn encoded_size(item: &impl Encode, mode: Mode) -> usize {
+    let mut bytes = vec![];
+    item.encode(&mut bytes, mode)
+        .expect("writing to a vec does not fail");
+    bytes.len()
+}
@@ -0,0 +1,152 @@
+//! Zero knowledge, checked statistically: with the challenges of the verifier fixed, the proofs of
+//! two witnesses of a circuit must follow the same distribution.
+use super::Proof;
+use crate::{
+    curve_tests,
+    description::{CircuitDescription, Var},
+    gate::{BoolGate, CustomGate},
+    lookup::Table,
+    transcript::{Blake2bHash, TranscriptHash},
+    Poly,
+};
+use ark_ec::PairingEngine;
+use ark_ff::PrimeField;
+use ark_poly::{Evaluations, Polynomial};
+use ark_serialize::CanonicalSerialize;
+use kgz::KzgCommitment;
+
+/// proofs of each witness
+const SAMPLES: usize = 64;
+
+/// challenges that only depend on their position in the transcript, every message having a fixed
+/// size, so the verifier draws the same ones for every proof
+struct FixedChallenges;
+
+impl TranscriptHash for FixedChallenges {
+    fn hash_to_field<F: PrimeField>(data: &[u8]) -> F {
+        Blake2bHash::hash_to_field(&(data.len() as u64).to_le_bytes())
+    }
+}
+
+/// `a·b + c` with a and b nibbles and c a bit, opens the wires at the next row and has a lookup
+struct Secret;
+impl CircuitDescription<3> for Secret {
+    fn run<V: Var>(inputs: [V; 3]) {
+        let [a, b, c] = inputs;
+        let nibble = Table::range("nibble", 4);
+        a.lookup(&nibble);
+        b.lookup(&nibble);
+        V::custom_constraint(&BoolGate, &[V::Field::from(1_u64)], &[&c]);
+        (a * b + c).expose();
+    }
+    fn custom_gates<F: PrimeField>() -> Vec<Box<dyn CustomGate<F>>> {
+        vec![Box::new(BoolGate)]
+    }
+    fn tables<F: PrimeField>() -> Vec<Table<F>> {
+        vec![Table::range("nibble", 4)]
+    }
+}
+
+/// the 2 lowest bits of each commitment and evaluation of the proof
+fn buckets<E: PairingEngine>(proof: &Proof<E>) -> Vec<usize> {
+    let mut commitments = proof
+        .wires
+        .iter()
+        .map(|wire| wire.commitment)
+        .collect::<Vec<_>>();
+    commitments.push(proof.permutation.commitment);
+    commitments.extend(proof.t);
+    if let Some(lookup) = &proof.lookup {
+        commitments.extend([
+            lookup.f.commitment,
+            lookup.h1.commitment,
+            lookup.h2.commitment,
+            lookup.accumulator.commitment,
+        ]);
+    }
+    commitments.extend(proof.openings.map(KzgCommitment));
+    // the lowest byte of x comes first
+    let points = commitments.iter().map(|commitment| {
+        let mut bytes = vec![];
+        commitment.serialize(&mut bytes).unwrap();
+        (bytes[0] & 3) as usize
+    });
+    let scalars = proof.evaluations().into_iter().flatten().map(bucket);
+    points.chain(scalars).collect()
+}
+fn bucket<F: PrimeField>(scalar: F) -> usize {
+    (scalar.into_repr().as_ref()[0] & 3) as usize
+}
+
+/// the chi-square statistic of the two samples having the same buckets at each position, summed
+/// over the positions, and its degrees of freedom. A position constant in both samples adds nothing
+fn chi_square(first: &[Vec<usize>], second: &[Vec<usize>]) -> (f64, usize) {
+    let sizes = [first.len(), second.len()].map(|size| size as f64);
+    let mut statistic = 0.0;
+    let mut freedom = 0;
+    for position in 0..first[0].len() {
+        let mut counts = [[0.0; 4]; 2];
+        for (counts, sample) in counts.iter_mut().zip([first, second]) {
+            for transcript in sample {
+                counts[transcript[position]] += 1.0;
+            }
+        }
+        let mut buckets = 0;
+        for bucket in 0..4 {
+            let total = counts[0][bucket] + counts[1][bucket];
+            if total == 0.0 {
+                continue;
+            }
+            buckets += 1;
+            for (counts, size) in counts.iter().zip(sizes) {
+                let expected = total * size / (sizes[0] + sizes[1]);
+                statistic += (counts[bucket] - expected).powi(2) / expected;
+            }
+        }
+        freedom += buckets - 1;
+    }
+    (statistic, freedom)
+}
+/// the samples pass the chi-square test at 6 standard deviations, `df + 6·√(2·df)`
+fn indistinguishable(first: &[Vec<usize>], second: &[Vec<usize>]) -> bool {
+    let (statistic, freedom) = chi_square(first, second);
+    let freedom = freedom as f64;
+    statistic < freedom + 6.0 * (2.0 * freedom).sqrt()
+}
+
+fn transcripts_indistinguishable<E: PairingEngine>() {
+    let circuit = Secret::build::<E>().unwrap();
+    let witnesses = [[3_u64, 4, 1], [13, 1, 0]];
+    let proof = circuit
+        .prove_with_hash::<FixedChallenges>(witnesses[0])
+        .unwrap();
+    let point = proof.evaluation_point;
+    assert!(circuit.verify_with_hash::<FixedChallenges>(proof).is_ok());
+
+    let [first, second] = witnesses.map(|witness| {
+        (0..SAMPLES)
+            .map(|_| {
+                let proof = circuit.prove_with_hash::<FixedChallenges>(witness).unwrap();
+                assert_eq!(proof.evaluation_point, point);
+                buckets(&proof)
+            })
+            .collect::<Vec<_>>()
+    });
+    assert!(indistinguishable(&first, &second));
+
+    // the wires at the point, as an unblinded prover would send them
+    let [first, second] = witnesses.map(|witness| {
+        let wires = circuit
+            .advice(witness.map(E::Fr::from).to_vec())
+            .map(|mut col| {
+                col.resize(circuit.rows, E::Fr::from(0_u64));
+                let poly: Poly<E::Fr> =
+                    Evaluations::from_vec_and_domain(col, circuit.domain).interpolate();
+                bucket(poly.evaluate(&point))
+            });
+        vec![wires.to_vec(); SAMPLES]
+    });
+    assert!(!indistinguishable(&first, &second));
+}
+
+curve_tests!(transcripts_indistinguishable);
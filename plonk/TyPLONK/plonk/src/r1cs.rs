@@ -0,0 +1,285 @@
+//! Import of the circuits circom compiles to `.r1cs`, with their `.wtns` witnesses.
+//!
+//! A `.r1cs` file is `"r1cs"`, a u32 version and a u32 number of sections, each being a u32 type, a
+//! u64 size and its data. Section 1 is the header: n8, the prime in n8 bytes, the number of wires,
+//! public outputs, public inputs and private inputs as u32, a u64 number of labels and a u32
+//! number of constraints. Section 2 holds the constraints `A·w * B·w = C·w`, each linear
+//! combination being a u32 number of terms followed by a u32 wire and its coefficient in n8
+//! bytes. A `.wtns` file has the same layout, its header is n8, the prime and the u32 number of
+//! values, section 2 holds the values. Integers and field elements are little endian, field
+//! elements in standard form.
+//!
+//! The wire 0 is the constant 1, followed by the public outputs, the public inputs, the private
+//! inputs and the intermediate signals. The circuit takes all the wires but the constant as
+//! inputs, `witness[1..]`, and exposes the public ones.
+use crate::{
+    builder::CircuitBuilder,
+    description::{Circuit, ConstraintSystem, Var},
+};
+use ark_ff::{BigInteger, FpParameters, FromBytes, PrimeField};
+use ark_serialize::SerializationError;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+const R1CS_MAGIC: &[u8; 4] = b"r1cs";
+const WTNS_MAGIC: &[u8; 4] = b"wtns";
+const HEADER: u32 = 1;
+const CONSTRAINTS: u32 = 2;
+const VALUES: u32 = 2;
+
+/// `Σ k·w`, as the wires and their coefficients
+pub type Combination<F> = Vec<(usize, F)>;
+
+/// `A·w * B·w = C·w`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint<F> {
+    pub a: Combination<F>,
+    pub b: Combination<F>,
+    pub c: Combination<F>,
+}
+
+/// The constraints of a circom circuit, translated to gates as a [`Circuit`]. Each constraint is
+/// the product of two linear combinations copied to a third one, a linear constraint needs no
+/// multiplication.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct R1cs<F> {
+    /// the wires, the constant 1 included
+    pub wires: usize,
+    /// the public outputs and inputs, the wires following the constant
+    pub public: usize,
+    pub constraints: Vec<Constraint<F>>,
+}
+
+/// The gates the translation of an r1cs takes, compared to its constraints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Blowup {
+    pub constraints: usize,
+    /// the rows before padding, the public inputs included
+    pub gates: usize,
+}
+
+impl Blowup {
+    /// gates per constraint
+    pub fn ratio(&self) -> f64 {
+        self.gates as f64 / self.constraints.max(1) as f64
+    }
+}
+impl Display for Blowup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} constraints translated to {} gates, {:.2} per constraint",
+            self.constraints,
+            self.gates,
+            self.ratio()
+        )
+    }
+}
+
+impl<F: PrimeField> R1cs<F> {
+    /// reads a `.r1cs` file over the field `F`, files over another prime are rejected
+    pub fn read<R: Read + Seek>(mut reader: R) -> Result<Self, SerializationError> {
+        let positions = read_sections(&mut reader, R1CS_MAGIC)?;
+        seek_section(&mut reader, &positions, HEADER)?;
+        read_prime::<F>(&mut reader)?;
+        let wires = read_u32(&mut reader)? as usize;
+        let outputs = read_u32(&mut reader)? as usize;
+        let public_inputs = read_u32(&mut reader)? as usize;
+        let _private_inputs = read_u32(&mut reader)?;
+        let _labels = read_u64(&mut reader)?;
+        let constraints = read_u32(&mut reader)?;
+        let public = outputs + public_inputs;
+        if wires == 0 || public >= wires {
+            return Err(SerializationError::InvalidData);
+        }
+
+        seek_section(&mut reader, &positions, CONSTRAINTS)?;
+        let mut combination = || -> Result<Combination<F>, SerializationError> {
+            let terms = read_u32(&mut reader)?;
+            (0..terms)
+                .map(|_| {
+                    let wire = read_u32(&mut reader)? as usize;
+                    if wire >= wires {
+                        return Err(SerializationError::InvalidData);
+                    }
+                    Ok((wire, read_element(&mut reader)?))
+                })
+                .collect()
+        };
+        let constraints = (0..constraints)
+            .map(|_| {
+                Ok(Constraint {
+                    a: combination()?,
+                    b: combination()?,
+                    c: combination()?,
+                })
+            })
+            .collect::<Result<_, SerializationError>>()?;
+        Ok(Self {
+            wires,
+            public,
+            constraints,
+        })
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SerializationError> {
+        let file = File::open(path)?;
+        Self::read(BufReader::new(file))
+    }
+    /// the gates of the translation with `W` wires
+    pub fn blowup<const W: usize>(&self) -> Blowup {
+        Blowup {
+            constraints: self.constraints.len(),
+            gates: CircuitBuilder::<F, W>::gates(self),
+        }
+    }
+}
+
+/// reads the values of a `.wtns` file over the field `F`, the first one is the constant 1
+pub fn read_witness<F: PrimeField, R: Read + Seek>(
+    mut reader: R,
+) -> Result<Vec<F>, SerializationError> {
+    let positions = read_sections(&mut reader, WTNS_MAGIC)?;
+    seek_section(&mut reader, &positions, HEADER)?;
+    read_prime::<F>(&mut reader)?;
+    let values = read_u32(&mut reader)?;
+    seek_section(&mut reader, &positions, VALUES)?;
+    let witness = (0..values)
+        .map(|_| read_element(&mut reader))
+        .collect::<Result<Vec<F>, _>>()?;
+    match witness.first() {
+        Some(one) if one.is_one() => Ok(witness),
+        _ => Err(SerializationError::InvalidData),
+    }
+}
+pub fn load_witness<F: PrimeField>(path: impl AsRef<Path>) -> Result<Vec<F>, SerializationError> {
+    let file = File::open(path)?;
+    read_witness(BufReader::new(file))
+}
+
+impl<F: PrimeField> Circuit for R1cs<F> {
+    fn inputs(&self) -> usize {
+        self.wires - 1
+    }
+    fn synthesize<V: Var>(&self, cs: &mut ConstraintSystem<V>) {
+        assert_eq!(
+            F::Params::MODULUS.to_bytes_le(),
+            <V::Field as PrimeField>::Params::MODULUS.to_bytes_le(),
+            "the r1cs is over another field than the circuit"
+        );
+        let wires = std::iter::once(V::constant(1_u64))
+            .chain(cs.inputs(self.wires - 1))
+            .collect::<Vec<_>>();
+        for wire in &wires[1..=self.public] {
+            wire.expose();
+        }
+        for Constraint { a, b, c } in &self.constraints {
+            // a constant side only scales the other one
+            let product = match (constant(a), constant(b)) {
+                (Some(k), _) => combination(b, k, &wires),
+                (_, Some(k)) => combination(a, k, &wires),
+                _ => combination(a, F::one(), &wires) * combination(b, F::one(), &wires),
+            };
+            product.assert_eq(&combination(c, F::one(), &wires));
+        }
+    }
+}
+
+/// the value of a combination of the wire 0 only
+fn constant<F: PrimeField>(terms: &Combination<F>) -> Option<F> {
+    terms
+        .iter()
+        .all(|(wire, _)| *wire == 0)
+        .then(|| terms.iter().map(|(_, k)| *k).sum())
+}
+/// `factor·Σ k·w`, a lone wire with a coefficient of 1 takes no row
+fn combination<F: PrimeField, V: Var>(terms: &Combination<F>, factor: F, wires: &[V]) -> V {
+    match terms[..] {
+        [(wire, k)] if wire != 0 && (k * factor).is_one() => wires[wire].clone(),
+        _ => {
+            let terms = terms
+                .iter()
+                .filter(|(_, k)| !k.is_zero())
+                .map(|(wire, k)| (convert::<F, V::Field>(*k * factor), &wires[*wire]))
+                .collect::<Vec<_>>();
+            V::linear_combination(&terms)
+        }
+    }
+}
+/// an element of the same field under another type
+fn convert<F: PrimeField, G: PrimeField>(element: F) -> G {
+    G::from_le_bytes_mod_order(&element.into_repr().to_bytes_le())
+}
+
+/// checks the magic and returns the position of the data of each section type
+fn read_sections<R: Read + Seek>(
+    reader: &mut R,
+    magic: &[u8; 4],
+) -> Result<HashMap<u32, u64>, SerializationError> {
+    let mut read = [0; 4];
+    reader.read_exact(&mut read)?;
+    if &read != magic {
+        return Err(SerializationError::InvalidData);
+    }
+    let _version = read_u32(reader)?;
+    let sections = read_u32(reader)?;
+    let mut positions = HashMap::new();
+    for _ in 0..sections {
+        let kind = read_u32(reader)?;
+        let size = read_u64(reader)?;
+        // the first section of a type is kept
+        positions.entry(kind).or_insert(reader.stream_position()?);
+        reader.seek(SeekFrom::Current(size as i64))?;
+    }
+    Ok(positions)
+}
+fn seek_section(
+    reader: &mut impl Seek,
+    positions: &HashMap<u32, u64>,
+    kind: u32,
+) -> Result<(), SerializationError> {
+    let position = positions
+        .get(&kind)
+        .ok_or(SerializationError::InvalidData)?;
+    reader.seek(SeekFrom::Start(*position))?;
+    Ok(())
+}
+/// n8 and the prime, which must be the modulus of `F`
+fn read_prime<F: PrimeField>(reader: &mut impl Read) -> Result<(), SerializationError> {
+    let n8 = read_u32(reader)? as usize;
+    if n8 != element_bytes::<F>() {
+        return Err(SerializationError::InvalidData);
+    }
+    let mut prime = vec![0; n8];
+    reader.read_exact(&mut prime)?;
+    match prime == F::Params::MODULUS.to_bytes_le() {
+        true => Ok(()),
+        false => Err(SerializationError::InvalidData),
+    }
+}
+fn read_u32(reader: &mut impl Read) -> Result<u32, SerializationError> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+fn read_u64(reader: &mut impl Read) -> Result<u64, SerializationError> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+/// bytes of an element of the field
+fn element_bytes<F: PrimeField>() -> usize {
+    <F::BigInt as BigInteger>::NUM_LIMBS * 8
+}
+/// an element in standard form, below the modulus
+fn read_element<F: PrimeField>(reader: &mut impl Read) -> Result<F, SerializationError> {
+    let repr = F::BigInt::read(reader)?;
+    F::from_repr(repr).ok_or(SerializationError::InvalidData)
+}
+
+#[cfg(test)]
+mod test;
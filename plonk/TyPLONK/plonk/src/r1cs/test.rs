@@ -0,0 +1,144 @@
+use super::{read_witness, Blowup, R1cs, CONSTRAINTS, HEADER, R1CS_MAGIC, VALUES, WTNS_MAGIC};
+use crate::{curve_tests, description::Circuit, PlonkError};
+use ark_ec::PairingEngine;
+use ark_ff::{BigInteger, FpParameters, PrimeField};
+use std::io::Cursor;
+
+fn section(bytes: &mut Vec<u8>, kind: u32, data: Vec<u8>) {
+    bytes.extend_from_slice(&kind.to_le_bytes());
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    bytes.extend(data);
+}
+/// n8 and the prime
+fn prime(prime: Vec<u8>) -> Vec<u8> {
+    let mut data = (prime.len() as u32).to_le_bytes().to_vec();
+    data.extend(prime);
+    data
+}
+fn modulus<F: PrimeField>() -> Vec<u8> {
+    F::Params::MODULUS.to_bytes_le()
+}
+/// `out = x³ + x + 5` and `2·x·x = 2·x²`, over the wires `[1, out, x, x², x³]` with out public.
+/// The constraints come before the header and a labels section, to check sections are looked up
+fn cubic<F: PrimeField>(prime_bytes: Vec<u8>) -> Vec<u8> {
+    let constraints: [[&[(u32, u64)]; 3]; 4] = [
+        [&[(2, 1)], &[(2, 1)], &[(3, 1)]],
+        [&[(3, 1)], &[(2, 1)], &[(4, 1)]],
+        [&[(4, 1), (2, 1), (0, 5)], &[(0, 1)], &[(1, 1)]],
+        [&[(2, 2)], &[(2, 1)], &[(3, 2)]],
+    ];
+    let mut data = vec![];
+    for combinations in constraints {
+        for terms in combinations {
+            data.extend_from_slice(&(terms.len() as u32).to_le_bytes());
+            for (wire, k) in terms {
+                data.extend_from_slice(&wire.to_le_bytes());
+                data.extend(F::from(*k).into_repr().to_bytes_le());
+            }
+        }
+    }
+    let mut bytes = R1CS_MAGIC.to_vec();
+    bytes.extend_from_slice(&1_u32.to_le_bytes());
+    bytes.extend_from_slice(&3_u32.to_le_bytes());
+    section(&mut bytes, CONSTRAINTS, data);
+    let mut header = prime(prime_bytes);
+    // wires, outputs, public inputs, private inputs, labels and constraints
+    for count in [5_u32, 1, 0, 1] {
+        header.extend_from_slice(&count.to_le_bytes());
+    }
+    header.extend_from_slice(&5_u64.to_le_bytes());
+    header.extend_from_slice(&(constraints.len() as u32).to_le_bytes());
+    section(&mut bytes, HEADER, header);
+    section(&mut bytes, 3, vec![0; 40]);
+    bytes
+}
+fn wtns<F: PrimeField>(values: &[u64]) -> Vec<u8> {
+    let mut bytes = WTNS_MAGIC.to_vec();
+    bytes.extend_from_slice(&2_u32.to_le_bytes());
+    bytes.extend_from_slice(&2_u32.to_le_bytes());
+    let mut header = prime(modulus::<F>());
+    header.extend_from_slice(&(values.len() as u32).to_le_bytes());
+    section(&mut bytes, HEADER, header);
+    let mut data = vec![];
+    for value in values {
+        data.extend(F::from(*value).into_repr().to_bytes_le());
+    }
+    section(&mut bytes, VALUES, data);
+    bytes
+}
+
+fn cubic_circuit<E: PairingEngine>() {
+    let file = cubic::<E::Fr>(modulus::<E::Fr>());
+    let r1cs = R1cs::<E::Fr>::read(Cursor::new(&file)).unwrap();
+    assert_eq!([r1cs.wires, r1cs.public], [5, 1]);
+    assert_eq!(r1cs.constraints.len(), 4);
+    assert_eq!(r1cs.constraints[2].a[2], (0, E::Fr::from(5_u64)));
+    assert_eq!(r1cs.inputs(), 4);
+
+    let witness = read_witness::<E::Fr, _>(Cursor::new(wtns::<E::Fr>(&[1, 35, 3, 9, 27]))).unwrap();
+    let circuit = r1cs.clone().build::<E>().unwrap();
+    let proof = circuit.prove(witness[1..].to_vec()).unwrap();
+    assert_eq!(proof.public_inputs, vec![E::Fr::from(35_u64)]);
+    assert!(circuit.verify(proof).is_ok());
+    assert!(matches!(
+        circuit.prove([36_u64, 3, 9, 27]),
+        Err(PlonkError::Unsatisfied(_))
+    ));
+    assert!(matches!(
+        circuit.prove([35_u64, 3, 9, 28]),
+        Err(PlonkError::Unsatisfied(_))
+    ));
+    let circuit = r1cs.build_wide::<E, 4>().unwrap();
+    let proof = circuit.prove(witness[1..].to_vec()).unwrap();
+    assert!(circuit.verify(proof).is_ok());
+}
+fn blowup<E: PairingEngine>() {
+    let r1cs = R1cs::<E::Fr>::read(Cursor::new(cubic::<E::Fr>(modulus::<E::Fr>()))).unwrap();
+    // the public input, a row for each product, x³ + x + 5, 2·x and 2·x²
+    let blowup = r1cs.blowup::<3>();
+    assert_eq!(
+        blowup,
+        Blowup {
+            constraints: 4,
+            gates: 7
+        }
+    );
+    assert_eq!(
+        blowup.to_string(),
+        "4 constraints translated to 7 gates, 1.75 per constraint"
+    );
+}
+fn invalid_files<E: PairingEngine>() {
+    // a file over another prime
+    let file = cubic::<E::Fr>(modulus::<E::Fq>());
+    assert!(R1cs::<E::Fr>::read(Cursor::new(&file)).is_err());
+    let mut file = cubic::<E::Fr>(modulus::<E::Fr>());
+    assert!(R1cs::<E::Fr>::read(Cursor::new(&file[..file.len() - 60])).is_err());
+    // the wire of the first term of the first constraint, after 4 + 4 + 4 + 12 + 4 bytes
+    file[28] = 5;
+    assert!(R1cs::<E::Fr>::read(Cursor::new(&file)).is_err());
+    file[0] = b'w';
+    assert!(R1cs::<E::Fr>::read(Cursor::new(&file)).is_err());
+    // the first value is not the constant 1
+    let witness = wtns::<E::Fr>(&[2, 35, 3, 9, 27]);
+    assert!(read_witness::<E::Fr, _>(Cursor::new(witness)).is_err());
+    let witness = wtns::<E::Fr>(&[1, 35, 3, 9, 27]);
+    assert!(read_witness::<E::Fq, _>(Cursor::new(witness)).is_err());
+}
+
+curve_tests!(cubic_circuit, blowup, invalid_files);
+
+/// a circuit of the summa project, compiled by circom over BN254
+#[test]
+fn circom_file() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../../summa-solvency-v1/zk_prover/examples/build/incremental_mst_inclusion.r1cs"
+    );
+    let r1cs = R1cs::<ark_bn254::Fr>::load(path).unwrap();
+    assert_eq!([r1cs.wires, r1cs.public], [4201, 4]);
+    assert_eq!(r1cs.constraints.len(), 4201);
+    let blowup = r1cs.blowup::<3>();
+    assert!(blowup.gates > blowup.constraints);
+    assert!(r1cs.blowup::<5>().gates < blowup.gates);
+}
@@ -0,0 +1,158 @@
+//! Fiat-Shamir transcript.
+//!
+//! Everything the prover sends is absorbed with a label, and every challenge is the hash of all
+//! that was absorbed before it, itself absorbed once drawn. The hash function is pluggable through
+//! [`TranscriptHash`]. The challenges are elements of the scalar field `F` of the curve.
+use crate::poseidon;
+use ark_ec::PairingEngine;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use blake2::{Blake2b, Digest};
+use kgz::KzgCommitment;
+use sha3::Keccak256;
+use std::marker::PhantomData;
+
+/// Hashes the transcript to a challenge.
+pub trait TranscriptHash {
+    /// a uniformly distributed field element, hashes with small outputs must widen them
+    fn hash_to_field<F: PrimeField>(data: &[u8]) -> F;
+}
+
+/// Blake2b-512, the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake2bHash;
+
+impl TranscriptHash for Blake2bHash {
+    fn hash_to_field<F: PrimeField>(data: &[u8]) -> F {
+        wide_reduction(&Blake2b::digest(data))
+    }
+}
+
+/// Keccak-256 of the data followed by a 0 byte, then by a 1 byte, for 512 bits
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeccakHash;
+
+impl TranscriptHash for KeccakHash {
+    fn hash_to_field<F: PrimeField>(data: &[u8]) -> F {
+        let mut bytes = Vec::with_capacity(64);
+        for suffix in [0_u8, 1] {
+            let mut hasher = Keccak256::new();
+            hasher.update(data);
+            hasher.update([suffix]);
+            bytes.extend_from_slice(&hasher.finalize());
+        }
+        wide_reduction(&bytes)
+    }
+}
+
+/// [`poseidon::hash`] of the length of the data in bytes, then of the data in chunks of 31 bytes,
+/// little endian, which are below r for the fields of more than 248 bits. Cheap to recompute in a circuit that verifies the proof
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoseidonHash;
+
+impl TranscriptHash for PoseidonHash {
+    fn hash_to_field<F: PrimeField>(data: &[u8]) -> F {
+        let length = F::from(data.len() as u64);
+        let chunks = data.chunks(31).map(F::from_le_bytes_mod_order);
+        let elements = std::iter::once(length).chain(chunks).collect::<Vec<_>>();
+        poseidon::hash(&elements)
+    }
+}
+
+/// reduces 64 bytes modulo r, the bias is below 2^-250
+fn wide_reduction<F: PrimeField>(bytes: &[u8]) -> F {
+    debug_assert_eq!(bytes.len(), 64);
+    F::from_le_bytes_mod_order(bytes)
+}
+
+#[derive(Clone)]
+pub struct Transcript<F, H: TranscriptHash = Blake2bHash> {
+    data: Vec<u8>,
+    hash: PhantomData<(F, H)>,
+}
+
+impl<F: PrimeField, H: TranscriptHash> Transcript<F, H> {
+    /// `protocol` separates the transcripts of different protocols
+    pub fn new(protocol: &[u8]) -> Self {
+        let mut transcript = Self {
+            data: vec![],
+            hash: PhantomData,
+        };
+        transcript.append_message(b"protocol", protocol);
+        transcript
+    }
+    /// the label and the message are length prefixed, so no two sequences of messages collide
+    pub fn append_message(&mut self, label: &[u8], message: &[u8]) {
+        for bytes in [label, message] {
+            self.data
+                .extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            self.data.extend_from_slice(bytes);
+        }
+    }
+    pub fn append_commitment<E: PairingEngine<Fr = F>>(
+        &mut self,
+        label: &[u8],
+        commitment: &KzgCommitment<E>,
+    ) {
+        let mut bytes = vec![];
+        commitment.serialize(&mut bytes).unwrap();
+        self.append_message(label, &bytes);
+    }
+    pub fn append_scalar(&mut self, label: &[u8], scalar: &F) {
+        let mut bytes = vec![];
+        scalar.serialize(&mut bytes).unwrap();
+        self.append_message(label, &bytes);
+    }
+    /// hashes everything absorbed so far and absorbs the challenge
+    pub fn challenge(&mut self, label: &[u8]) -> F {
+        self.append_message(label, &[]);
+        let challenge = H::hash_to_field(&self.data);
+        self.append_scalar(b"challenge", &challenge);
+        challenge
+    }
+    pub fn challenges<const N: usize>(&mut self, labels: [&[u8]; N]) -> [F; N] {
+        labels.map(|label| self.challenge(label))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Blake2bHash, KeccakHash, PoseidonHash, Transcript, TranscriptHash};
+    use ark_bls12_381::Fr;
+
+    fn challenges<H: TranscriptHash>(protocol: &[u8], message: &[u8]) -> [Fr; 2] {
+        let mut transcript = Transcript::<Fr, H>::new(protocol);
+        transcript.append_message(b"message", message);
+        transcript.challenges([b"first", b"second"])
+    }
+
+    #[test]
+    fn deterministic() {
+        let [a, b] = challenges::<Blake2bHash>(b"test", b"abc");
+        assert_eq!([a, b], challenges::<Blake2bHash>(b"test", b"abc"));
+        assert_ne!(a, b);
+        assert_ne!(a, challenges::<KeccakHash>(b"test", b"abc")[0]);
+        let [c, d] = challenges::<PoseidonHash>(b"test", b"abc");
+        assert_eq!([c, d], challenges::<PoseidonHash>(b"test", b"abc"));
+        assert_ne!(c, d);
+        assert_ne!(a, c);
+    }
+    #[test]
+    fn separation() {
+        let challenge = challenges::<Blake2bHash>(b"test", b"abc")[0];
+        assert_ne!(challenge, challenges::<Blake2bHash>(b"other", b"abc")[0]);
+        assert_ne!(challenge, challenges::<Blake2bHash>(b"test", b"abd")[0]);
+        // moving bytes from the label to the message changes the challenges
+        let mut transcript = Transcript::<Fr, Blake2bHash>::new(b"test");
+        transcript.append_message(b"messag", b"eabc");
+        assert_ne!(challenge, transcript.challenge(b"first"));
+    }
+    #[test]
+    fn poseidon_padding() {
+        // the chunks are padded with zeros, the length tells the data apart
+        let hash = PoseidonHash::hash_to_field::<Fr>;
+        assert_ne!(hash(b"abc"), hash(b"abc\0"));
+        assert_ne!(hash(b""), hash(b"\0"));
+        assert_ne!(hash(&[7; 31]), hash(&[7; 32]));
+    }
+}
@@ -1,16 +1,17 @@
 use crate::Poly;
-use ark_bls12_381::Fr;
-use ark_ff::{One, Zero};
+use ark_ec::PairingEngine;
+use ark_ff::{FftField, Field, Zero};
 use ark_poly::{
     univariate::{DenseOrSparsePolynomial, SparsePolynomial},
     EvaluationDomain, Polynomial, UVPolynomial,
 };
 #[cfg(test)]
 use kgz::KzgOpening;
-use kgz::{KzgCommitment, KzgScheme};
+use kgz::{KzgCommitment, KzgError, KzgScheme};
+use rand::Rng;
 use std::ops::Mul;
 
-pub fn add_to_poly(mut poly: Poly, number: Fr) -> Poly {
+pub fn add_to_poly<F: Field>(mut poly: Poly<F>, number: F) -> Poly<F> {
     if poly.coeffs.is_empty() {
         Poly::from_coefficients_vec(vec![number])
     } else {
@@ -19,44 +20,90 @@ pub fn add_to_poly(mut poly: Poly, number: Fr) -> Poly {
     }
 }
 
+/// `b(X)·(X^n - 1)` with b random of degree `count - 1`, 0 over the domain. Added to a
+/// polynomial opened at `count - 1` points, the commitment and the openings reveal nothing of its
+/// values over the domain
+pub fn blinder<F: FftField>(
+    domain: impl EvaluationDomain<F>,
+    count: usize,
+    rng: &mut impl Rng,
+) -> Poly<F> {
+    let n = domain.size();
+    let mut coeffs = vec![F::zero(); n + count];
+    for i in 0..count {
+        let b = F::rand(rng);
+        coeffs[i] -= b;
+        coeffs[n + i] += b;
+    }
+    Poly::from_coefficients_vec(coeffs)
+}
+
 ///with [D] for the max degree for each slice, and [S] as the number of slices
 #[derive(Debug)]
-pub(crate) struct SlicedPoly<const S: usize> {
-    slices: [Poly; S],
+pub(crate) struct SlicedPoly<F: Field, const S: usize> {
+    slices: [Poly<F>; S],
     //the degree of each slice
     degree: usize,
 }
 
-impl<const S: usize> SlicedPoly<S> {
-    pub fn from_poly(poly: Poly, degree: usize) -> Self {
-        assert!(poly.degree() / S <= degree);
+impl<F: Field, const S: usize> SlicedPoly<F, S> {
+    /// `None` when the polynomial does not fit in the slices
+    pub fn from_poly(poly: Poly<F>, degree: usize) -> Option<Self> {
+        if poly.degree() >= S * degree {
+            return None;
+        }
         let coeffs = poly.coeffs;
-        let mut slices = [(); S].map(|_| Poly::zero());
+        let mut slices = [(); S].map(|_| Poly::<F>::zero());
         coeffs
             .chunks(degree)
-            .map(|coeffs| Poly::from_coefficients_slice(coeffs))
+            .map(Poly::from_coefficients_slice)
             .enumerate()
             .for_each(|(index, slice)| {
                 slices[index] = slice;
             });
-        Self { slices, degree }
+        Some(Self { slices, degree })
+    }
+    /// adds `b_i·X^degree` to the i-th slice and subtracts `b_i` from the next one, which keeps
+    /// the polynomial and hides the commitments of the slices. Each slice but the last gets a
+    /// coefficient more
+    pub fn blind(&mut self, rng: &mut impl Rng) {
+        for i in 0..S - 1 {
+            let b = F::rand(rng);
+            let mut high = vec![F::zero(); self.degree + 1];
+            high[self.degree] = b;
+            self.slices[i] += &Poly::from_coefficients_vec(high);
+            self.slices[i + 1] = add_to_poly(self.slices[i + 1].clone(), -b);
+        }
     }
-    pub fn commit(&self, scheme: &KzgScheme) -> [KzgCommitment; S] {
-        //waiting for array.each_ref()
-        self.slices.clone().map(|slice| scheme.commit(&slice))
+    pub fn commit<E: PairingEngine<Fr = F>>(
+        &self,
+        scheme: &KzgScheme<E>,
+    ) -> Result<[KzgCommitment<E>; S], KzgError> {
+        let commitments = self
+            .slices
+            .iter()
+            .map(|slice| scheme.commit(slice))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(commitments.try_into().unwrap())
     }
     #[cfg(test)]
-    fn open(&self, scheme: &KzgScheme, point: Fr) -> [KzgOpening; S] {
-        self.slices.clone().map(|slice| scheme.open(slice, point))
+    fn open<E: PairingEngine<Fr = F>>(
+        &self,
+        scheme: &KzgScheme<E>,
+        point: F,
+    ) -> [KzgOpening<E>; S] {
+        self.slices
+            .clone()
+            .map(|slice| scheme.open(slice, point).unwrap())
     }
     #[cfg(test)]
-    fn verify_opening(
-        commits: &[KzgCommitment; S],
-        openings: [KzgOpening; S],
-        scheme: &KzgScheme,
-        point: Fr,
+    fn verify_opening<E: PairingEngine<Fr = F>>(
+        commits: &[KzgCommitment<E>; S],
+        openings: [KzgOpening<E>; S],
+        scheme: &KzgScheme<E>,
+        point: F,
         degree: usize,
-    ) -> Option<Fr> {
+    ) -> Option<F> {
         let valid = commits
             .iter()
             .zip(openings.iter())
@@ -67,7 +114,7 @@ impl<const S: usize> SlicedPoly<S> {
                 .enumerate()
                 .map(|(index, slice)| {
                     let exponent =
-                        SparsePolynomial::from_coefficients_slice(&[((degree) * index, Fr::one())]);
+                        SparsePolynomial::from_coefficients_slice(&[((degree) * index, F::one())]);
                     slice.1 * exponent.evaluate(&point)
                 })
                 .reduce(|one, other| one + other)
@@ -78,46 +125,42 @@ impl<const S: usize> SlicedPoly<S> {
         }
     }
     #[cfg(test)]
-    fn eval(&self, point: Fr) -> Fr {
+    fn eval(&self, point: F) -> F {
         self.slices
             .iter()
             //.rev()
             .enumerate()
             .map(|(index, slice)| {
-                let exponent = SparsePolynomial::from_coefficients_slice(&[(
-                    (self.degree) * index,
-                    Fr::one(),
-                )]);
+                let exponent =
+                    SparsePolynomial::from_coefficients_slice(&[((self.degree) * index, F::one())]);
                 slice.evaluate(&point) * exponent.evaluate(&point)
             })
             .reduce(|one, other| one + other)
             .unwrap()
     }
-    pub fn compact(&self, point: Fr) -> Poly {
+    pub fn compact(&self, point: F) -> Poly<F> {
         self.slices
             .iter()
             .enumerate()
             .map(|(index, slice)| {
-                let exponent = SparsePolynomial::from_coefficients_slice(&[(
-                    (self.degree) * index,
-                    Fr::one(),
-                )]);
+                let exponent =
+                    SparsePolynomial::from_coefficients_slice(&[((self.degree) * index, F::one())]);
                 slice.mul(exponent.evaluate(&point))
             })
             .reduce(|one, other| one + other)
             .unwrap()
     }
-    pub fn compact_commitment(
+    pub fn compact_commitment<E: PairingEngine<Fr = F>>(
         degree: usize,
-        commitments: [KzgCommitment; S],
-        point: Fr,
-    ) -> KzgCommitment {
+        commitments: [KzgCommitment<E>; S],
+        point: F,
+    ) -> KzgCommitment<E> {
         commitments
             .iter()
             .enumerate()
             .map(|(index, commit)| {
                 let exponent =
-                    SparsePolynomial::from_coefficients_slice(&[((degree) * index, Fr::one())]);
+                    SparsePolynomial::from_coefficients_slice(&[((degree) * index, F::one())]);
                 commit * exponent.evaluate(&point)
             })
             .reduce(|one, other| one + other)
@@ -127,41 +170,62 @@ impl<const S: usize> SlicedPoly<S> {
 
 #[test]
 fn slicing() {
+    use ark_bls12_381::{Bls12_381, Fr};
     use kgz::srs::Srs;
     let eval_point = Fr::from(4);
-    let coeffs = [1, 2, 3, 4, 5, 6, 7, 8, 9].map(|e| Fr::from(e));
+    let coeffs = [1, 2, 3, 4, 5, 6, 7, 8, 9].map(Fr::from);
     let poly = Poly::from_coefficients_slice(&coeffs);
     let eval = poly.evaluate(&eval_point);
     //println!("unsliced eval: {}", eval);
-    let sliced_poly = <SlicedPoly<5>>::from_poly(poly, 2);
+    assert!(<SlicedPoly<Fr, 4>>::from_poly(poly.clone(), 2).is_none());
+    let sliced_poly = <SlicedPoly<Fr, 5>>::from_poly(poly.clone(), 2).unwrap();
     //println!("slices:{}", sliced_poly.slices.len());
     //println!("sliced:{:?}", sliced_poly);
     let eval2 = sliced_poly.eval(eval_point);
     //println!("sliced eval: {}", eval);
     assert_eq!(eval, eval2);
-    let srs = Srs::random(16);
+    let srs = Srs::<Bls12_381>::random(16);
     let scheme = KzgScheme::new(&srs);
-    let commits = sliced_poly.commit(&scheme);
+    let commits = sliced_poly.commit(&scheme).unwrap();
     let openings = sliced_poly.open(&scheme, eval_point);
-    let valid = <SlicedPoly<5>>::verify_opening(&commits, openings, &scheme, eval_point, 2);
+    let valid = <SlicedPoly<Fr, 5>>::verify_opening(&commits, openings, &scheme, eval_point, 2);
     assert_eq!(valid.unwrap(), eval);
+
+    let mut blinded = <SlicedPoly<Fr, 5>>::from_poly(poly, 2).unwrap();
+    blinded.blind(&mut rand::thread_rng());
+    assert_eq!(blinded.eval(eval_point), eval);
+    assert_eq!(blinded.compact(eval_point).evaluate(&eval_point), eval);
+    assert_ne!(blinded.commit(&scheme).unwrap(), commits);
+    assert!(blinded.slices[..4].iter().all(|slice| slice.degree() == 2));
+}
+
+#[test]
+fn blinding() {
+    use ark_bls12_381::Fr;
+    use ark_poly::GeneralEvaluationDomain;
+    let domain = <GeneralEvaluationDomain<Fr>>::new(8).unwrap();
+    let blinder = blinder(domain, 3, &mut rand::thread_rng());
+    assert_eq!(blinder.degree(), 10);
+    assert!(domain.elements().all(|x| blinder.evaluate(&x).is_zero()));
+    assert!(!blinder.evaluate(&Fr::from(2)).is_zero());
 }
 
-pub fn l0_poly(domain: impl EvaluationDomain<Fr>) -> Poly {
+pub fn l0_poly<F: FftField>(domain: impl EvaluationDomain<F>) -> Poly<F> {
     let n = domain.size();
-    let den = Poly::from_coefficients_vec(vec![Fr::from(-1), Fr::from(1)]);
-    let den: DenseOrSparsePolynomial<_> = den.mul(Fr::from(n as i32)).into();
+    let den = Poly::from_coefficients_vec(vec![-F::one(), F::one()]);
+    let den: DenseOrSparsePolynomial<_> = den.mul(F::from(n as u64)).into();
     //let vanish = domain.vanishing_polynomial();
-    let vanish = SparsePolynomial::from_coefficients_vec(vec![(n, Fr::one()), (0, Fr::from(-1))]);
+    let vanish = SparsePolynomial::from_coefficients_vec(vec![(n, F::one()), (0, -F::one())]);
     let vanish: DenseOrSparsePolynomial<_> = vanish.into();
-    let rhs = vanish.divide_with_q_and_r(&den).unwrap().0;
-    rhs
+    vanish.divide_with_q_and_r(&den).unwrap().0
 }
 
 #[test]
 fn l0() {
+    use ark_bls12_381::Fr;
+    use ark_ff::One;
     use ark_poly::Radix2EvaluationDomain;
-    let domain = Radix2EvaluationDomain::new(2_usize.pow(16)).unwrap();
+    let domain = Radix2EvaluationDomain::<Fr>::new(2_usize.pow(16)).unwrap();
     let l0 = l0_poly(domain);
     println!("w:{}", domain.element(1));
     println!("eval0:{}", l0.evaluate(&Fr::from(0)));
@@ -178,15 +242,16 @@ fn l0() {
 
 #[test]
 fn sliced() {
+    use ark_bls12_381::{Bls12_381, Fr};
     use kgz::srs::Srs;
-    let srs = Srs::random(3);
+    let srs = Srs::<Bls12_381>::random(3);
     let scheme = KzgScheme::new(&srs);
     let point = &Fr::from(4);
-    let coeffs = [1, 2, 3, 4, 5, 6, 7, 8].map(|e| Fr::from(e));
+    let coeffs = [1, 2, 3, 4, 5, 6, 7, 8].map(Fr::from);
     let poly = Poly::from_coefficients_slice(&coeffs);
     let whole_eval = poly.evaluate(point);
     println!("whole eval: {}", whole_eval);
-    let sliced = <SlicedPoly<3>>::from_poly(poly, 3);
+    let sliced = <SlicedPoly<Fr, 3>>::from_poly(poly, 3).unwrap();
     let sliced_eval = sliced.eval(*point);
     println!("sliced eval: {}", sliced_eval);
     assert_eq!(whole_eval, sliced_eval);
@@ -194,8 +259,8 @@ fn sliced() {
     let compact_eval = compact_poly.evaluate(point);
     println!("compact eval: {}", compact_eval);
     assert_eq!(whole_eval, compact_eval);
-    let compact_commit = scheme.commit(&compact_poly);
-    let sliced_commit = sliced.commit(&scheme);
-    let sliced_commit = <SlicedPoly<3>>::compact_commitment(3, sliced_commit, *point);
+    let compact_commit = scheme.commit(&compact_poly).unwrap();
+    let sliced_commit = sliced.commit(&scheme).unwrap();
+    let sliced_commit = <SlicedPoly<Fr, 3>>::compact_commitment(3, sliced_commit, *point);
     assert_eq!(compact_commit, sliced_commit);
 }
@@ -1,9 +1,13 @@
-use plonk::description::{CircuitDescription, Var};
+use plonk::{
+    description::{CircuitDescription, Var},
+    Bls12_381, PlonkError,
+};
 
 struct Circuit;
 impl CircuitDescription<3> for Circuit {
     fn run<V: Var>(inputs: [V; 3]) {
         let [a, b, c] = inputs;
+        c.expose();
         let a = a.clone() * a;
         let b = b.clone() * b;
         let c = c.clone() * c;
@@ -13,8 +17,8 @@ impl CircuitDescription<3> for Circuit {
     }
 }
 
-fn main() {
-    let circuit = Circuit::build();
-    let proof = circuit.prove([3, 4, 5], vec![0]);
-    assert!(circuit.verify(proof));
-}
\ No newline at end of file
+fn main() -> Result<(), PlonkError> {
+    let circuit = Circuit::build::<Bls12_381>()?;
+    let proof = circuit.prove([3, 4, 5])?;
+    circuit.verify(proof)
+}